@@ -0,0 +1,192 @@
+//! The speaker. The emulator thread mixes each frame's sound with a `Mixer` and pushes it to a
+//! `SampleQueue`, which the output device drains at its own pace.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::config::AudioConfig;
+use crate::emulator::Emulator;
+
+/// Most sound the queue holds before dropping the oldest, so a slow device or a fast emulator
+/// can't make the speaker fall behind the screen
+const MAX_QUEUED_SECONDS: f32 = 0.1;
+
+/// Turns the emulator's sound into samples, one frame at a time
+pub struct Mixer {
+    config: AudioConfig,
+    rate: u32,
+    /// Where the buzzer is in its wave, 0.0 to 1.0
+    phase: f32,
+    /// Fraction of a sample left over from the last frame
+    sample_carry: f64,
+    /// When the last frame was mixed, frames at unlimited speed last as long as they take
+    last_frame: Option<Instant>,
+}
+
+impl Mixer {
+    pub fn new(config: AudioConfig, rate: u32) -> Self {
+        Self {
+            config,
+            rate,
+            phase: 0.0,
+            sample_carry: 0.0,
+            last_frame: None,
+        }
+    }
+
+    /// The sound of the frame that just ran, -1.0 to 1.0 at the output rate. A MEGA-CHIP
+    /// sample plays over the buzzer, which is a square wave that plays while the sound timer
+    /// is running. Frames are as long as the emulator's speed makes them, so the speaker keeps
+    /// up with the output device.
+    pub fn frame(&mut self, emu: &mut Emulator) -> Vec<f32> {
+        let mut out = vec![0.0; self.frame_length(emu.frame_rate())];
+        if !self.config.enabled {
+            return out;
+        }
+
+        if emu.fill_sample_audio(&mut out, self.rate) {
+            self.apply_volume(&mut out);
+            return out;
+        }
+        if emu.sound_timer() == 0 {
+            return out;
+        }
+
+        let step = self.config.frequency.max(0.0) / self.rate as f32;
+        for sample in out.iter_mut() {
+            *sample = if self.phase < 0.5 { 1.0 } else { -1.0 };
+            self.phase = (self.phase + step).fract();
+        }
+
+        self.apply_volume(&mut out);
+        out
+    }
+
+    /// Samples in a frame at `frame_rate`, or in the time since the last frame without one
+    fn frame_length(&mut self, frame_rate: Option<f64>) -> usize {
+        let now = Instant::now();
+        let seconds = match (frame_rate, self.last_frame) {
+            (Some(frame_rate), _) => 1.0 / frame_rate,
+            // a pause or a slow frame shouldn't come out as a burst of sound
+            (None, Some(last_frame)) => now.duration_since(last_frame).as_secs_f64().min(1.0 / 60.0),
+            (None, None) => 1.0 / 60.0,
+        };
+        self.last_frame = Some(now);
+
+        self.sample_carry += self.rate as f64 * seconds;
+        let samples = self.sample_carry as usize;
+        self.sample_carry -= samples as f64;
+        samples
+    }
+
+    fn apply_volume(&self, out: &mut [f32]) {
+        let volume = self.config.volume.clamp(0.0, 1.0);
+        for sample in out.iter_mut() {
+            *sample *= volume;
+        }
+    }
+}
+
+/// Samples on their way from the emulator thread to the output device. Clones share the
+/// same queue.
+#[derive(Clone)]
+pub struct SampleQueue {
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    capacity: usize,
+}
+
+impl SampleQueue {
+    pub fn new(rate: u32) -> Self {
+        Self {
+            samples: Arc::default(),
+            capacity: (rate as f32 * MAX_QUEUED_SECONDS) as usize,
+        }
+    }
+
+    pub fn push(&self, samples: &[f32]) {
+        let mut queue = self.samples.lock().unwrap();
+        queue.extend(samples);
+
+        let excess = queue.len().saturating_sub(self.capacity);
+        queue.drain(..excess);
+    }
+
+    /// Fills `out` with queued samples, silence once the queue runs dry. Every sample is
+    /// repeated for each of the `channels`.
+    pub fn pop_into(&self, out: &mut [f32], channels: usize) {
+        let mut queue = self.samples.lock().unwrap();
+
+        for frame in out.chunks_mut(channels.max(1)) {
+            frame.fill(queue.pop_front().unwrap_or(0.0));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The default output device, playing whatever is pushed to `queue`
+#[cfg(feature = "audio")]
+pub struct AudioOutput {
+    pub queue: SampleQueue,
+    /// Samples per second the device plays
+    pub rate: u32,
+    // the sound stops when this is dropped
+    _stream: cpal::Stream,
+}
+
+#[cfg(feature = "audio")]
+impl AudioOutput {
+    pub fn open() -> Result<Self, String> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("no audio output device")?;
+        let supported = device.default_output_config().map_err(|err| err.to_string())?;
+        let config = supported.config();
+        let rate = config.sample_rate.0;
+        let queue = SampleQueue::new(rate);
+
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, queue.clone()),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, queue.clone()),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, queue.clone()),
+        }?;
+        stream.play().map_err(|err| err.to_string())?;
+
+        Ok(Self { queue, rate, _stream: stream })
+    }
+}
+
+#[cfg(feature = "audio")]
+fn build_stream<T: cpal::Sample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    queue: SampleQueue,
+) -> Result<cpal::Stream, String> {
+    use cpal::traits::DeviceTrait;
+
+    let channels = config.channels as usize;
+    let mut mixed = Vec::new();
+
+    device
+        .build_output_stream(
+            config,
+            move |out: &mut [T], _| {
+                mixed.resize(out.len(), 0.0);
+                queue.pop_into(&mut mixed, channels);
+                for (out, sample) in out.iter_mut().zip(&mixed) {
+                    *out = T::from(sample);
+                }
+            },
+            |err| eprintln!("Audio output failed: {}", err),
+        )
+        .map_err(|err| err.to_string())
+}
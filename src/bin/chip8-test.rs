@@ -0,0 +1,144 @@
+//! Runs a ROM without a window and checks the screen it leaves behind.
+//!
+//! ```text
+//! chip8-test <rom> [--frames N | --cycles N] [--hash SHA1 | --image FILE.pbm]
+//!            [--quirks vip|schip|xo-chip] [--timing "ipf N"|cosmac-vip] [--seed N]
+//!            [--platform chip8|hires|chip8x|chip8e|megachip]
+//!            [--format text|json|junit] [--output FILE] [--save-image FILE.pbm]
+//! ```
+//!
+//! Exits with 0 when the screen matches, 1 when it doesn't and 2 on bad arguments.
+
+use std::process::exit;
+
+use chip8_rs::emulator::quirks::Quirks;
+use chip8_rs::testing::{self, Expected, RunLength, TestCase};
+
+const USAGE: &str = "usage: chip8-test <rom> [--frames N | --cycles N] [--hash SHA1 | --image FILE.pbm]
+                  [--quirks vip|schip|xo-chip] [--timing \"ipf N\"|cosmac-vip] [--seed N]
+                  [--platform chip8|hires|chip8x|chip8e|megachip]
+                  [--format text|json|junit] [--output FILE] [--save-image FILE.pbm]";
+
+enum Format {
+    Text,
+    Json,
+    Junit,
+}
+
+struct Options {
+    case: TestCase,
+    format: Format,
+    output: Option<String>,
+    save_image: Option<String>,
+}
+
+fn main() {
+    let options = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            exit(2);
+        }
+    };
+
+    let result = testing::run_test(&options.case);
+
+    if let Some(path) = &options.save_image {
+        if let Err(err) = std::fs::write(path, testing::to_pbm(&result.framebuffer)) {
+            eprintln!("Couldn't save image to {}: {}", path, err);
+        }
+    }
+
+    let report = match options.format {
+        Format::Text => match &result.failure {
+            Some(failure) => format!("FAIL {}: {}\n", result.name, failure),
+            None => format!("PASS {} ({})\n{}", result.name, result.hash, testing::to_text(&result.framebuffer)),
+        },
+        Format::Json => testing::json_report(std::slice::from_ref(&result)),
+        Format::Junit => testing::junit_report("chip8-test", std::slice::from_ref(&result)),
+    };
+
+    match &options.output {
+        Some(path) => {
+            if let Err(err) = std::fs::write(path, report) {
+                eprintln!("Couldn't write report to {}: {}", path, err);
+                exit(2);
+            }
+        }
+        None => print!("{}", report),
+    }
+
+    exit(if result.passed() { 0 } else { 1 });
+}
+
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut rom_path = None;
+    let mut run = RunLength::Frames(60);
+    let mut expected = Expected::Nothing;
+    let mut quirks = Quirks::default();
+    let mut timing = Default::default();
+    let mut seed = 0;
+    let mut platform = Default::default();
+    let mut format = Format::Text;
+    let mut output = None;
+    let mut save_image = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "--frames" => run = RunLength::Frames(parse_number(&value()?)?),
+            "--cycles" => run = RunLength::Cycles(parse_number(&value()?)?),
+            "--hash" => expected = Expected::Hash(value()?),
+            "--image" => {
+                let path = value()?;
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("Couldn't read {}: {}", path, err))?;
+                let image = testing::parse_pbm(&text).map_err(|err| format!("{}: {}", path, err))?;
+                expected = Expected::Image(Box::new(image));
+            }
+            "--quirks" => {
+                let name = value()?;
+                quirks = Quirks::preset(&name).ok_or_else(|| format!("unknown quirks preset {:?}", name))?;
+            }
+            "--timing" => timing = value()?.parse()?,
+            "--seed" => seed = parse_number(&value()?)?,
+            "--platform" => platform = value()?.parse()?,
+            "--format" => {
+                format = match value()?.as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "junit" => Format::Junit,
+                    other => return Err(format!("unknown format {:?}", other)),
+                }
+            }
+            "--output" => output = Some(value()?),
+            "--save-image" => save_image = Some(value()?),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let rom_path = rom_path.ok_or("no ROM given")?;
+    let rom = std::fs::read(&rom_path).map_err(|err| format!("Couldn't read {}: {}", rom_path, err))?;
+
+    let mut case = TestCase::new(&rom_path, rom, run, expected);
+    case.quirks = quirks;
+    case.timing = timing;
+    case.seed = seed;
+    case.platform = platform;
+
+    Ok(Options {
+        case,
+        format,
+        output,
+        save_image,
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("bad number {:?}", value))
+}
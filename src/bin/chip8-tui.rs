@@ -0,0 +1,310 @@
+//! Terminal frontend, for running the emulator over SSH or anywhere without a window system.
+//!
+//! ```text
+//! chip8-tui <rom> [--quirks vip|schip|xo-chip] [--timing "ipf N"|cosmac-vip] [--font NAME|FILE]
+//!           [--font-address ADDR] [--platform chip8|hires|chip8x|chip8e|megachip] [--symbols FILE]
+//!           [--record-png DIR]
+//! ```
+//!
+//! The display is drawn with Unicode half blocks, two pixels per character, next to a panel
+//! with the registers. Keys, quirks and timing come from the config file like in the GUI,
+//! the options override them. Esc quits.
+//!
+//! The ROM can be in a zip archive, `game.zip#game.ch8` picks one out of an archive with several.
+//! Symbols for the PC and the stack come from `--symbols`, or a `.sym` file next to the ROM.
+//! `--record-png` saves every frame that changes the screen as a PNG in the directory.
+
+use std::io::{stdout, Stdout, Write};
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chip8_rs::config::Config;
+use chip8_rs::emulator::font::Font;
+use chip8_rs::emulator::platform::Platform;
+use chip8_rs::emulator::quirks::Quirks;
+use chip8_rs::emulator::sink::{PngRecorder, TerminalSink};
+use chip8_rs::emulator::symbols::Symbols;
+use chip8_rs::emulator::timing::TimingModel;
+use chip8_rs::emulator::{CpuState, Emulator};
+use chip8_rs::romdb::RomDatabase;
+use chip8_rs::DebugInfo;
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyboardEnhancementFlags, KeyModifiers,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{cursor, execute, queue, style, terminal};
+
+const USAGE: &str = "usage: chip8-tui <rom> [--quirks vip|schip|xo-chip] [--timing \"ipf N\"|cosmac-vip] \
+                     [--font vip|dream6800|eti660|schip|octo|FILE] [--font-address ADDR] \
+                     [--platform chip8|hires|chip8x|chip8e|megachip] [--symbols FILE] [--record-png DIR]";
+
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+/// Image pixels per CHIP-8 pixel for `--record-png`
+const PNG_SCALE: u32 = 4;
+
+/// Most terminals only report key presses, so a key counts as held for this many frames after
+/// its last press or auto-repeat. Terminals that report releases end the hold right away.
+const KEY_HOLD_FRAMES: u32 = 8;
+
+fn main() {
+    let config = Config::load().unwrap_or_else(|err| {
+        eprintln!("{}, using the default settings", err);
+        Config::default()
+    });
+
+    let (mut emu, keys, title) = match parse_args(std::env::args().skip(1).collect(), &config) {
+        Ok(setup) => setup,
+        Err(err) => {
+            eprintln!("{}\n{}", err, USAGE);
+            exit(2);
+        }
+    };
+
+    let mut out = stdout();
+    let key_releases = match setup_terminal(&mut out) {
+        Ok(key_releases) => key_releases,
+        Err(err) => {
+            eprintln!("Couldn't set up the terminal: {}", err);
+            exit(1);
+        }
+    };
+
+    // the screen goes inside the border
+    emu.add_sink(Box::new(TerminalSink::new(stdout()).at(1, 1)));
+
+    let result = run(&mut emu, &keys, &title, &mut out);
+
+    restore_terminal(&mut out, key_releases);
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        exit(1);
+    }
+}
+
+/// Sets up the emulator and returns it with the character for each CHIP-8 key and the ROM's title
+fn parse_args(args: Vec<String>, config: &Config) -> Result<(Emulator, [char; 16], String), String> {
+    let mut rom_path = None;
+    let mut quirks: Option<Quirks> = None;
+    let mut timing: Option<TimingModel> = None;
+    let mut font: Option<Font> = None;
+    let mut font_address: Option<u16> = None;
+    let mut platform: Option<Platform> = None;
+    let mut symbols_path: Option<String> = None;
+    let mut png_dir: Option<String> = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "--quirks" => {
+                let name = value()?;
+                quirks = Some(Quirks::preset(&name).ok_or_else(|| format!("unknown quirks preset {:?}", name))?);
+            }
+            "--timing" => timing = Some(value()?.parse()?),
+            "--font" => font = Some(Font::from_name_or_file(&value()?)?),
+            "--font-address" => font_address = Some(parse_address(&value()?)?),
+            "--platform" => platform = Some(value()?.parse()?),
+            "--symbols" => symbols_path = Some(value()?),
+            "--record-png" => png_dir = Some(value()?),
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if rom_path.is_none() => rom_path = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    let rom_path = rom_path.ok_or("no ROM given")?;
+    let rom_database = RomDatabase::load_default();
+    let mut emu = Emulator::new(Arc::new(Mutex::new(DebugInfo::new())));
+    // the platform decides how big the ROM can be, so it's picked before loading
+    match platform {
+        Some(platform) => emu.set_platform_lookup(Box::new(move |_, _| Some(platform))),
+        None => emu.set_platform_lookup(config.platform_lookup(&rom_database)),
+    }
+    for warning in emu.load_rom_from_file(&rom_path).map_err(|err| err.to_string())? {
+        eprintln!("{}", warning);
+    }
+
+    let rom_info = emu
+        .rom_file_info()
+        .or_else(|| rom_database.lookup(emu.rom_sha1()))
+        .cloned();
+    let settings = config.resolve(emu.rom_sha1(), rom_info.as_ref()).map_err(|err| err.to_string())?;
+    emu.timing = timing.unwrap_or(settings.timing);
+    emu.set_quirks(quirks.unwrap_or(settings.quirks));
+    emu.set_font(font.unwrap_or(settings.font), font_address.unwrap_or(settings.font_address))?;
+    emu.reset();
+
+    let symbols_path = symbols_path.map(Into::into).or_else(|| Symbols::path_for_rom(&rom_path));
+    if let Some(path) = symbols_path {
+        let symbols = Symbols::load(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        emu.set_symbols(symbols);
+    }
+
+    if let Some(dir) = png_dir {
+        let recorder = PngRecorder::new(&dir, PNG_SCALE).map_err(|err| format!("{}: {}", dir, err))?;
+        emu.add_sink(Box::new(recorder));
+    }
+
+    // key names are single letters or digits
+    let keys = settings.keys.map(|name| name.chars().next().unwrap_or(' '));
+
+    let title = match rom_info {
+        Some(info) if info.authors.is_empty() => info.title,
+        Some(info) => format!("{} by {}", info.title, info.authors.join(", ")),
+        None => rom_path,
+    };
+
+    Ok((emu, keys, title))
+}
+
+/// Hex with a `0x` prefix, decimal otherwise
+fn parse_address(value: &str) -> Result<u16, String> {
+    let parsed = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+
+    parsed.map_err(|_| format!("invalid address {:?}", value))
+}
+
+/// Returns whether the terminal will report key releases
+fn setup_terminal(out: &mut Stdout) -> std::io::Result<bool> {
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide, terminal::Clear(terminal::ClearType::All))?;
+
+    let key_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+    if key_releases {
+        execute!(out, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES))?;
+    }
+
+    Ok(key_releases)
+}
+
+fn restore_terminal(out: &mut Stdout, key_releases: bool) {
+    if key_releases {
+        let _ = execute!(out, PopKeyboardEnhancementFlags);
+    }
+
+    let _ = execute!(out, cursor::Show, terminal::LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+fn run(emu: &mut Emulator, keys: &[char; 16], title: &str, out: &mut Stdout) -> std::io::Result<()> {
+    // frames left before each key counts as released
+    let mut held = [0u32; 16];
+    let mut next_frame = Instant::now();
+
+    loop {
+        // handle input until the next frame is due
+        while event::poll(next_frame.saturating_duration_since(Instant::now()))? {
+            if let Event::Key(key) = event::read()? {
+                let quit = key.code == KeyCode::Esc
+                    || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+                if quit {
+                    return Ok(());
+                }
+
+                if let KeyCode::Char(c) = key.code {
+                    if let Some(chip8_key) = keys.iter().position(|key| *key == c.to_ascii_lowercase()) {
+                        held[chip8_key] = match key.kind {
+                            KeyEventKind::Release => 0,
+                            _ => KEY_HOLD_FRAMES,
+                        };
+                    }
+                }
+            }
+        }
+        next_frame += FRAME_TIME;
+
+        // don't try to catch up after the terminal stalled
+        if next_frame < Instant::now() {
+            next_frame = Instant::now() + FRAME_TIME;
+        }
+
+        let keypad = held
+            .iter()
+            .enumerate()
+            .filter(|(_, frames)| **frames > 0)
+            .fold(0u16, |keypad, (key, _)| keypad | 1 << key);
+        for frames in held.iter_mut() {
+            *frames = frames.saturating_sub(1);
+        }
+
+        emu.run_frame(keypad);
+
+        let status = match emu.cpu_error() {
+            Some(err) => format!("{} - {}", title, err),
+            None => title.to_string(),
+        };
+        let (width, height) = (emu.framebuffer().width(), emu.framebuffer().height());
+        draw(out, (width, height), &emu.cpu_state(), &emu.symbols(), keypad, &status)?;
+    }
+}
+
+/// Draws the border, the register panel and the title around the screen, which the
+/// `TerminalSink` draws inside the border
+fn draw(
+    out: &mut Stdout,
+    (width, height): (usize, usize),
+    cpu: &CpuState,
+    symbols: &Symbols,
+    keypad: u16,
+    title: &str,
+) -> std::io::Result<()> {
+    let panel = register_panel(cpu, symbols, keypad);
+    let lines = height.div_ceil(2);
+
+    queue!(out, cursor::MoveTo(0, 0))?;
+    queue!(out, style::Print(format!("┌{}┐", "─".repeat(width))))?;
+
+    for i in 0..lines {
+        let side = panel.get(i).map(String::as_str).unwrap_or("");
+        let row = i as u16 + 1;
+        queue!(out, cursor::MoveTo(0, row), style::Print("│"))?;
+        queue!(out, cursor::MoveTo(width as u16 + 1, row), style::Print(format!("│ {:<24}", side)))?;
+    }
+
+    queue!(out, cursor::MoveTo(0, lines as u16 + 1))?;
+    queue!(out, style::Print(format!("└{}┘\r\n", "─".repeat(width))))?;
+    queue!(out, style::Print(format!("{} - Esc quits", title)))?;
+
+    out.flush()
+}
+
+/// One line per display row, as much as fits next to the screen
+fn register_panel(cpu: &CpuState, symbols: &Symbols, keypad: u16) -> Vec<String> {
+    let mut lines = vec![format!("PC {:03X}   I {:03X}", cpu.pc, cpu.idx)];
+    if !symbols.is_empty() {
+        lines.push(format!("   {}", symbols.describe(cpu.pc)));
+    }
+    lines.push(format!("DT {:02X}    ST {:02X}", cpu.dly_timer, cpu.snd_timer));
+    lines.push(String::new());
+
+    for row in 0..8 {
+        lines.push(format!(
+            "V{:X} {:02X}    V{:X} {:02X}",
+            row,
+            cpu.v_reg[row],
+            row + 8,
+            cpu.v_reg[row + 8]
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push(format!(
+        "Stack {}",
+        cpu.stack.iter().map(|&address| symbols.describe(address)).collect::<Vec<_>>().join(" ")
+    ));
+
+    let keys: String = (0..16)
+        .map(|key| if keypad & (1 << key) != 0 { format!("{:X}", key) } else { String::from("·") })
+        .collect();
+    lines.push(format!("Keys  {}", keys));
+
+    lines
+}
@@ -0,0 +1,453 @@
+//! Settings kept in `chip8-rs/config.toml` in the user's config directory
+//! (`$XDG_CONFIG_HOME` on Linux). Every ROM can override the defaults in a
+//! `[roms.<sha1>]` section, keyed by the SHA-1 of the ROM.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::crt::CrtPreset;
+use crate::emulator::check_font_address;
+use crate::emulator::PlatformLookup;
+use crate::emulator::font::{Font, DEFAULT_FONT_ADDRESS};
+use crate::emulator::platform::Platform;
+use crate::emulator::quirks::Quirks;
+use crate::emulator::timing::{Speed, TimingModel};
+use crate::gamepad::{PadButton, PadMapping};
+use crate::input::KEY_CHARS;
+use crate::romdb::{RomDatabase, RomInfo};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Host key for each CHIP-8 key 0-F, a letter or a digit
+    pub keys: Vec<String>,
+    pub palette: Palette,
+    /// `0.25x` to `4x`, or `unlimited`
+    pub speed: String,
+    /// `ipf <instructions per frame>` or `cosmac-vip`
+    pub timing: String,
+    /// `vip`, `schip` or `xo-chip`
+    pub quirks: String,
+    /// `vip`, `dream6800`, `eti660`, `schip`, `octo`, or the path of an 80 byte font file
+    pub font: String,
+    /// Where the font goes in memory, some ROMs expect it at 0x050
+    pub font_address: u16,
+    /// `chip8`, `hires`, `chip8x`, `chip8e` or `megachip`
+    pub platform: String,
+    pub audio: AudioConfig,
+    pub gamepad: GamepadConfig,
+    pub window: WindowLayout,
+    /// Per-ROM overrides keyed by the ROM's SHA-1 as lowercase hex
+    pub roms: BTreeMap<String, RomOverrides>,
+}
+
+/// Colors as `#RRGGBB`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub foreground: String,
+    pub background: String,
+}
+
+/// The buzzer
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    pub enabled: bool,
+    /// 0.0 to 1.0
+    pub volume: f32,
+    /// Tone in Hz
+    pub frequency: f32,
+}
+
+/// Game controllers
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadConfig {
+    /// CHIP-8 key for each button as a hex digit, like `dpad-up = "2"`. Buttons left out press
+    /// nothing.
+    pub buttons: BTreeMap<String, String>,
+    /// Whether the left stick presses the D-pad's keys
+    pub stick: bool,
+    /// How far the stick has to move before it counts, 0.0 to 1.0
+    pub deadzone: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowLayout {
+    pub width: u32,
+    pub height: u32,
+    pub show_debug: bool,
+    pub show_movie: bool,
+    pub show_timing: bool,
+    pub show_keypad: bool,
+    /// `off`, `scanlines`, `television` or `arcade`
+    pub crt: String,
+}
+
+/// Anything left out falls back to the defaults at the top of the file
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RomOverrides {
+    /// Only there to make the file readable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<Palette>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<QuirksOverride>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_address: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamepad: Option<GamepadConfig>,
+}
+
+/// A ROM's quirks, a preset name like the default `quirks` or a table with every flag for
+/// combinations no preset has
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum QuirksOverride {
+    Preset(String),
+    Custom(Quirks),
+}
+
+/// The settings that apply to one ROM, with the overrides merged in and parsed
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomSettings {
+    /// Lowercase key names for CHIP-8 keys 0-F
+    pub keys: [String; 16],
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+    pub speed: Speed,
+    pub timing: TimingModel,
+    pub quirks: Quirks,
+    pub font: Font,
+    pub font_address: u16,
+    pub platform: Platform,
+    pub gamepad: PadMapping,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(String),
+    /// A value that parsed as TOML but doesn't make sense, with the section it's in
+    Invalid { section: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Parse(msg) => write!(f, "Invalid config file: {}", msg),
+            ConfigError::Invalid { section, message } => write!(f, "Invalid config in {}: {}", section, message),
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            keys: KEY_CHARS.iter().map(|key| key.to_string()).collect(),
+            palette: Palette::default(),
+            speed: Speed::Normal.to_string(),
+            timing: TimingModel::default().to_string(),
+            quirks: "vip".to_string(),
+            font: "octo".to_string(),
+            font_address: DEFAULT_FONT_ADDRESS,
+            platform: Platform::default().to_string(),
+            audio: AudioConfig::default(),
+            gamepad: GamepadConfig::default(),
+            window: WindowLayout::default(),
+            roms: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            foreground: "#FFFFFF".to_string(),
+            background: "#000000".to_string(),
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 0.5,
+            frequency: 440.0,
+        }
+    }
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig::from(&PadMapping::default())
+    }
+}
+
+impl From<&PadMapping> for GamepadConfig {
+    fn from(mapping: &PadMapping) -> Self {
+        Self {
+            buttons: mapping
+                .buttons()
+                .map(|(button, key)| (button.to_string(), format!("{:X}", key)))
+                .collect(),
+            stick: mapping.stick,
+            deadzone: mapping.deadzone,
+        }
+    }
+}
+
+impl GamepadConfig {
+    pub fn mapping(&self) -> Result<PadMapping, String> {
+        if !(0.0..=1.0).contains(&self.deadzone) {
+            return Err(format!("deadzone {} is not between 0.0 and 1.0", self.deadzone));
+        }
+
+        let mut mapping = PadMapping::empty();
+        mapping.stick = self.stick;
+        mapping.deadzone = self.deadzone;
+
+        for (button, key) in &self.buttons {
+            let button: PadButton = button.parse()?;
+            let key = u8::from_str_radix(key.trim(), 16)
+                .ok()
+                .filter(|key| *key <= 0xF)
+                .ok_or_else(|| format!("{} is mapped to {:?}, which is not a key 0-F", button, key))?;
+            mapping.set_key(button, Some(key));
+        }
+
+        Ok(mapping)
+    }
+}
+
+impl Default for WindowLayout {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            show_debug: true,
+            show_movie: true,
+            show_timing: true,
+            show_keypad: true,
+            crt: CrtPreset::default().to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Where the config lives, None if the platform has no config directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chip8-rs").join("config.toml"))
+    }
+
+    /// Loads the config file, or the defaults when there isn't one yet
+    pub fn load() -> Result<Config, ConfigError> {
+        match Config::path() {
+            Some(path) if path.exists() => Config::load_from(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config, ConfigError> {
+        Config::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses and checks every value, so mistakes show up at startup rather than when a
+    /// particular ROM is loaded
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let config: Config = toml::from_str(text).map_err(|err| ConfigError::Parse(err.to_string()))?;
+
+        config.settings_for("")?;
+        config.crt_preset()?;
+        for sha1 in config.roms.keys() {
+            config.settings_for(sha1)?;
+        }
+
+        Ok(config)
+    }
+
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = Config::path().ok_or_else(|| {
+            ConfigError::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "no config directory"))
+        })?;
+
+        self.save_to(&path)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), ConfigError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let text = toml::to_string_pretty(self).map_err(|err| ConfigError::Parse(err.to_string()))?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// The settings for the ROM with the given SHA-1, the defaults if it has no overrides
+    pub fn settings_for(&self, rom_sha1: &str) -> Result<RomSettings, ConfigError> {
+        self.resolve(rom_sha1, None)
+    }
+
+    /// Like `settings_for`, with what the ROM database knows about the ROM taking the place of
+    /// the defaults. Overrides in the config file still win.
+    pub fn resolve(&self, rom_sha1: &str, info: Option<&RomInfo>) -> Result<RomSettings, ConfigError> {
+        let overrides = self.roms.get(rom_sha1).cloned().unwrap_or_default();
+        let section = if self.roms.contains_key(rom_sha1) {
+            format!("[roms.{}]", rom_sha1)
+        } else {
+            "the defaults".to_string()
+        };
+        let invalid = |message: String| ConfigError::Invalid {
+            section: section.clone(),
+            message,
+        };
+
+        let keys = overrides.keys.as_ref().unwrap_or(&self.keys);
+        let keys: [String; 16] = keys
+            .iter()
+            .map(|key| key.to_lowercase())
+            .collect::<Vec<String>>()
+            .try_into()
+            .map_err(|keys: Vec<String>| invalid(format!("keys needs 16 entries, found {}", keys.len())))?;
+        if let Some(key) = keys.iter().find(|key| !is_key_name(key)) {
+            return Err(invalid(format!("key {:?} is not a letter or digit", key)));
+        }
+
+        let (foreground, background) = match (&overrides.palette, info.and_then(|info| info.colors)) {
+            (None, Some((background, foreground))) => (foreground, background),
+            (palette, _) => {
+                let palette = palette.as_ref().unwrap_or(&self.palette);
+                (
+                    parse_color(&palette.foreground).map_err(invalid)?,
+                    parse_color(&palette.background).map_err(invalid)?,
+                )
+            }
+        };
+
+        let speed = overrides.speed.as_ref().unwrap_or(&self.speed).parse().map_err(invalid)?;
+
+        let timing = match (&overrides.timing, info.and_then(|info| info.tickrate)) {
+            (None, Some(tickrate)) => TimingModel::InstructionsPerFrame(tickrate),
+            (timing, _) => timing.as_ref().unwrap_or(&self.timing).parse().map_err(invalid)?,
+        };
+
+        let quirks = match (&overrides.quirks, info) {
+            (Some(QuirksOverride::Custom(quirks)), _) => *quirks,
+            (None, Some(info)) => info.quirks,
+            (quirks, _) => {
+                let quirks_name = match quirks {
+                    Some(QuirksOverride::Preset(name)) => name,
+                    _ => &self.quirks,
+                };
+                Quirks::preset(quirks_name)
+                    .ok_or_else(|| invalid(format!("unknown quirks preset {:?}", quirks_name)))?
+            }
+        };
+
+        let font = Font::from_name_or_file(overrides.font.as_ref().unwrap_or(&self.font)).map_err(invalid)?;
+        let font_address = overrides.font_address.unwrap_or(self.font_address);
+        check_font_address(font_address).map_err(invalid)?;
+
+        let database_platform = info.and_then(|info| Platform::from_database_id(&info.platform));
+        let platform = match (&overrides.platform, database_platform) {
+            (None, Some(platform)) => platform,
+            (platform, _) => platform.as_ref().unwrap_or(&self.platform).parse().map_err(invalid)?,
+        };
+
+        let gamepad = overrides.gamepad.as_ref().unwrap_or(&self.gamepad).mapping().map_err(invalid)?;
+
+        Ok(RomSettings {
+            keys,
+            foreground,
+            background,
+            speed,
+            timing,
+            quirks,
+            font,
+            font_address,
+            platform,
+            gamepad,
+        })
+    }
+
+    /// The platform `resolve` picks for each ROM, for `Emulator::set_platform_lookup`. What
+    /// the ROM file says goes before the database.
+    pub fn platform_lookup(&self, database: &RomDatabase) -> PlatformLookup {
+        let (config, database) = (self.clone(), database.clone());
+        Box::new(move |rom_sha1, info| {
+            config
+                .resolve(rom_sha1, info.or_else(|| database.lookup(rom_sha1)))
+                .ok()
+                .map(|settings| settings.platform)
+        })
+    }
+
+    /// The look picked for the emulator's screen
+    pub fn crt_preset(&self) -> Result<CrtPreset, ConfigError> {
+        self.window.crt.parse().map_err(|message| ConfigError::Invalid {
+            section: "[window]".to_string(),
+            message,
+        })
+    }
+
+    /// Stores the speed, timing and quirks as overrides for a ROM, keeping its other overrides
+    pub fn remember_rom(&mut self, rom_sha1: &str, speed: Speed, timing: TimingModel, quirks: Quirks) {
+        let overrides = self.roms.entry(rom_sha1.to_string()).or_default();
+
+        overrides.speed = Some(speed.to_string());
+        overrides.timing = Some(timing.to_string());
+        overrides.quirks = Some(match quirks.preset_name() {
+            Some(name) => QuirksOverride::Preset(name.to_string()),
+            None => QuirksOverride::Custom(quirks),
+        });
+    }
+
+    /// Stores a controller mapping as an override for a ROM, keeping its other overrides
+    pub fn remember_gamepad(&mut self, rom_sha1: &str, mapping: &PadMapping) {
+        self.roms.entry(rom_sha1.to_string()).or_default().gamepad = Some(GamepadConfig::from(mapping));
+    }
+}
+
+fn is_key_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if c.is_ascii_alphanumeric())
+}
+
+/// Parses `#RRGGBB`
+pub fn parse_color(value: &str) -> Result<[u8; 3], String> {
+    let hex = value
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.is_ascii())
+        .ok_or_else(|| format!("color {:?} is not #RRGGBB", value))?;
+
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("color {:?} is not #RRGGBB", value))
+    };
+
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
@@ -0,0 +1,98 @@
+//! Looks the GUI can give the emulator's screen, imitating a CRT
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A named set of effects, picked in the GUI or with `crt` in the `[window]` config section
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CrtPreset {
+    /// Sharp square pixels
+    #[default]
+    Off,
+    /// Dark lines between the rows, nothing else
+    Scanlines,
+    /// A curved, glowing TV picture
+    Television,
+    /// Strong glow and heavy scanlines on a flat tube
+    Arcade,
+}
+
+/// How strong each effect is, 0.0 turns it off
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CrtEffects {
+    /// How dark the gaps between rows get, up to 1.0
+    pub scanlines: f32,
+    /// How much the picture bulges, 0.1 is a typical TV
+    pub curvature: f32,
+    /// How much light bleeds into the pixels around lit ones
+    pub bloom: f32,
+    /// How much the corners darken
+    pub vignette: f32,
+}
+
+impl CrtPreset {
+    pub const ALL: [CrtPreset; 4] = [
+        CrtPreset::Off,
+        CrtPreset::Scanlines,
+        CrtPreset::Television,
+        CrtPreset::Arcade,
+    ];
+
+    pub fn effects(&self) -> CrtEffects {
+        match self {
+            CrtPreset::Off => CrtEffects::default(),
+            CrtPreset::Scanlines => CrtEffects {
+                scanlines: 0.5,
+                ..CrtEffects::default()
+            },
+            CrtPreset::Television => CrtEffects {
+                scanlines: 0.35,
+                curvature: 0.12,
+                bloom: 0.4,
+                vignette: 0.5,
+            },
+            CrtPreset::Arcade => CrtEffects {
+                scanlines: 0.7,
+                curvature: 0.03,
+                bloom: 0.8,
+                vignette: 0.25,
+            },
+        }
+    }
+}
+
+impl CrtEffects {
+    /// The values for the shader's uniform block, in the order it declares them
+    pub fn uniforms(&self) -> [f32; 4] {
+        [self.scanlines, self.curvature, self.bloom, self.vignette]
+    }
+
+    pub fn is_off(&self) -> bool {
+        self.uniforms().iter().all(|strength| *strength == 0.0)
+    }
+}
+
+impl fmt::Display for CrtPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrtPreset::Off => write!(f, "off"),
+            CrtPreset::Scanlines => write!(f, "scanlines"),
+            CrtPreset::Television => write!(f, "television"),
+            CrtPreset::Arcade => write!(f, "arcade"),
+        }
+    }
+}
+
+impl FromStr for CrtPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CrtPreset::ALL
+            .into_iter()
+            .find(|preset| preset.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                let names: Vec<String> = CrtPreset::ALL.iter().map(ToString::to_string).collect();
+                format!("unknown CRT preset {:?}, expected one of {}", s, names.join(", "))
+            })
+    }
+}
@@ -0,0 +1,64 @@
+use std::ops::{Deref, DerefMut};
+
+/// Memory as seen by the CPU. Addresses wrap around at the end of the address space.
+pub trait Bus {
+    fn read(&self, address: u32) -> u8;
+    fn write(&mut self, address: u32, value: u8);
+}
+
+impl Bus for [u8; 4096] {
+    fn read(&self, address: u32) -> u8 {
+        self[address as usize & 0xFFF]
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        self[address as usize & 0xFFF] = value;
+    }
+}
+
+/// RAM sized for the platform, 4K on most and 16M for MEGA-CHIP's 24 bit addresses
+pub struct Memory {
+    bytes: Vec<u8>,
+}
+
+impl Memory {
+    /// Size has to be a power of two so addresses can wrap with a mask
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "memory size {} is not a power of two", size);
+        Self { bytes: vec![0; size] }
+    }
+
+    /// Zeroes everything, resizing first if the size changed
+    pub fn reset(&mut self, size: usize) {
+        if self.bytes.len() == size {
+            self.bytes.fill(0);
+        } else {
+            *self = Memory::new(size);
+        }
+    }
+}
+
+impl Deref for Memory {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl DerefMut for Memory {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+impl Bus for Memory {
+    fn read(&self, address: u32) -> u8 {
+        self.bytes[address as usize & (self.bytes.len() - 1)]
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        let mask = self.bytes.len() - 1;
+        self.bytes[address as usize & mask] = value;
+    }
+}
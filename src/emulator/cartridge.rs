@@ -0,0 +1,126 @@
+//! Octo cartridges: GIF images with a label on the front and the program plus its settings
+//! hidden in the pixels. Every pixel's palette index carries two bits of data in its low
+//! bits, four pixels make a byte, most significant bits first, running on through every
+//! frame. The bytes are a 32-bit big endian length followed by that much UTF-8 JSON:
+//!
+//! ```text
+//! {"program": "<Octo source>", "options": {"tickrate": 20, "shiftQuirks": false, ...}}
+//! ```
+//!
+//! There's no Octo assembler here, so the machine code comes from the `.ch8` Octo exports,
+//! saved next to the cartridge with the same name. The cartridge adds its settings. A
+//! cartridge on its own is rejected with `RomError::OctoSource`.
+
+use serde::Deserialize;
+
+use super::quirks::Quirks;
+use crate::config::parse_color;
+use crate::romdb::RomInfo;
+
+/// What a cartridge holds. The program is Octo assembly, not machine code.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cartridge {
+    pub program: String,
+    pub options: CartridgeOptions,
+}
+
+/// The settings Octo saves with a program, anything missing keeps Octo's default
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CartridgeOptions {
+    /// Instructions per frame
+    pub tickrate: u32,
+    /// `#RRGGBB` of lit pixels
+    pub fill_color: String,
+    pub background_color: String,
+    pub shift_quirks: bool,
+    /// `FX55` and `FX65` leave I alone
+    pub load_store_quirks: bool,
+    pub clip_quirks: bool,
+    pub jump_quirks: bool,
+    pub logic_quirks: bool,
+}
+
+impl Default for CartridgeOptions {
+    fn default() -> Self {
+        Self {
+            tickrate: 20,
+            fill_color: "#FFCC00".to_string(),
+            background_color: "#996600".to_string(),
+            shift_quirks: false,
+            load_store_quirks: false,
+            clip_quirks: false,
+            jump_quirks: false,
+            logic_quirks: false,
+        }
+    }
+}
+
+impl CartridgeOptions {
+    pub fn quirks(&self) -> Quirks {
+        Quirks {
+            vf_reset: self.logic_quirks,
+            memory_increment: !self.load_store_quirks,
+            shift_vx: self.shift_quirks,
+            jump_vx: self.jump_quirks,
+            clipping: self.clip_quirks,
+            ..Quirks::xo_chip()
+        }
+    }
+
+    /// Background and foreground, None if either isn't a `#RRGGBB` color
+    pub fn colors(&self) -> Option<([u8; 3], [u8; 3])> {
+        Some((parse_color(&self.background_color).ok()?, parse_color(&self.fill_color).ok()?))
+    }
+
+    /// The options in the shape of a ROM database entry, so they are applied the same way and
+    /// the config file can still override them
+    pub fn rom_info(&self, title: &str) -> RomInfo {
+        RomInfo {
+            title: title.to_string(),
+            authors: Vec::new(),
+            description: None,
+            release: None,
+            platform: "xochip".to_string(),
+            quirks: self.quirks(),
+            tickrate: Some(self.tickrate),
+            key_hints: Vec::new(),
+            colors: self.colors(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    program: String,
+    #[serde(default)]
+    options: CartridgeOptions,
+}
+
+/// Decodes a cartridge from the bytes of its GIF
+pub fn read_cartridge(gif: &[u8]) -> Result<Cartridge, String> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(gif).map_err(|err| err.to_string())?;
+
+    let mut bits = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().map_err(|err| err.to_string())? {
+        bits.extend(frame.buffer.iter().map(|index| index & 3));
+    }
+
+    let bytes: Vec<u8> = bits
+        .chunks_exact(4)
+        .map(|pixels| pixels.iter().fold(0, |byte, bits| byte << 2 | bits))
+        .collect();
+
+    let (length, payload) = bytes.split_at_checked(4).ok_or("no cartridge data in the image")?;
+    let length = u32::from_be_bytes(length.try_into().unwrap()) as usize;
+    let json = payload.get(..length).ok_or("cartridge data is cut short")?;
+
+    let payload: Payload = serde_json::from_slice(json).map_err(|err| format!("invalid cartridge data: {}", err))?;
+
+    Ok(Cartridge {
+        program: payload.program,
+        options: payload.options,
+    })
+}
@@ -1,82 +1,123 @@
-use fixedstep::FixedStep;
-use notan::random::rand;
-use std::{
-    cell::RefCell,
-    rc::Rc,
-    sync::{Arc, Mutex},
-};
+use std::{cell::RefCell, fmt, rc::Rc, sync::Arc};
 
-use super::gpu::GPU;
+use super::bus::Bus;
+use super::display::Display;
+use super::font::DEFAULT_FONT_ADDRESS;
+use super::megachip::{BlendMode, SamplePlayback};
+use super::platform::Platform;
+use super::rom::PROGRAM_START;
+use super::quirks::Quirks;
+use super::rng::{Rng, XorShiftRng};
+use super::symbols::Symbols;
+
+/// Prints what the CPU is doing when tracing is turned on
+macro_rules! trace {
+    ($cpu:expr, $($arg:tt)*) => {
+        if $cpu.trace {
+            println!($($arg)*);
+        }
+    };
+}
 
 pub struct CPU {
-    pub idx: u16,
+    /// 24 bits on MEGA-CHIP, 16 elsewhere
+    pub idx: u32,
     pub pc: u16,
     pub cir: u16,
-    pub stack: Vec<u8>,
-    pub snd_timer: Arc<Mutex<u8>>,
-    pub dly_timer: Arc<Mutex<u8>>,
+    pub stack: Vec<u16>,
+    pub snd_timer: u8,
+    pub dly_timer: u8,
     pub v_reg: [u8; 16],
-    pub memory_ref: Rc<RefCell<[u8; 4096]>>,
-    pub gpu_ref: Rc<RefCell<GPU>>,
+    /// Which of the 16 keys are currently held
+    pub keypad: [bool; 16],
+    /// Key pressed during `FX0A`, the instruction finishes once it is released
+    pub key_wait: Option<u8>,
+    /// Bit N is set once the program looks at key N, until the frontend clears it
+    pub keys_checked: u16,
+    /// Set while CHIP-8E's `FX4F` waits for the delay timer to run out
+    pub delay_wait: bool,
+    pub quirks: Quirks,
+    /// Decides which instructions there are besides plain CHIP-8
+    pub platform: Platform,
+    /// Where `FX29` finds the hex digits
+    pub font_address: u16,
+    pub rng: Box<dyn Rng>,
+    /// The MEGA-CHIP sound `060N` started
+    pub sample: Option<SamplePlayback>,
+    /// Why the program stopped, it doesn't run again until a reset
+    pub error: Option<CpuError>,
+    /// Print every instruction as it executes
+    pub trace: bool,
+    /// Names for the addresses in the trace
+    pub symbols: Arc<Symbols>,
+    pub memory_ref: Rc<RefCell<dyn Bus>>,
+    pub gpu_ref: Rc<RefCell<dyn Display>>,
 }
 
 impl CPU {
     pub fn new(
-        memory_ref: Rc<RefCell<[u8; 4096]>>,
-        gpu_ref: Rc<RefCell<GPU>>,
+        memory_ref: Rc<RefCell<dyn Bus>>,
+        gpu_ref: Rc<RefCell<dyn Display>>,
+        seed: u64,
     ) -> CPU {
-        let mut cpu = CPU {
+        CPU {
             idx: 0,
             pc: 0,
             cir: 0,
             stack: Vec::new(),
-            dly_timer: Arc::new(Mutex::new(0)),
-            snd_timer: Arc::new(Mutex::new(0)),
+            dly_timer: 0,
+            snd_timer: 0,
             v_reg: [0; 16],
+            keypad: [false; 16],
+            key_wait: None,
+            keys_checked: 0,
+            delay_wait: false,
+            quirks: Quirks::default(),
+            platform: Platform::default(),
+            font_address: DEFAULT_FONT_ADDRESS,
+            rng: Box::new(XorShiftRng::new(seed)),
+            sample: None,
+            error: None,
+            trace: false,
+            symbols: Arc::default(),
             memory_ref,
             gpu_ref,
-        };
-
-        // For decrementing dly and snd timers
-        cpu.start_timer_thread();
-
-        cpu
+        }
     }
 
-    fn start_timer_thread(&mut self) {
-        let snd_timer = self.snd_timer.clone();
-        let dly_timer = self.dly_timer.clone();
-
-        std::thread::spawn(move || {
-            // Specification requires 60hz
-            let mut fixed_step = FixedStep::start(60.0).unlimit();
-
-            loop {
-                // Decrement both timers
-                if fixed_step.update() {
-                    let mut snd_timer = snd_timer.lock().unwrap();
-                    let mut dly_timer = dly_timer.lock().unwrap();
-
-                    if *snd_timer > 0 {
-                        *snd_timer -= 1;
-                    }
+    /// Puts the registers back to their power-on state, the quirks and platform are kept
+    pub fn reset(&mut self, pc: u16, seed: u64) {
+        self.idx = 0;
+        self.pc = pc;
+        self.cir = 0;
+        self.stack.clear();
+        self.dly_timer = 0;
+        self.snd_timer = 0;
+        self.v_reg = [0; 16];
+        self.keypad = [false; 16];
+        self.key_wait = None;
+        self.keys_checked = 0;
+        self.delay_wait = false;
+        self.sample = None;
+        self.error = None;
+        self.rng.reseed(seed);
+    }
 
-                    if *dly_timer > 0 {
-                        *dly_timer -= 1;
-                    }
-                }
-            }
-        });
+    /// Decrements both timers, must be called at 60hz
+    pub fn tick_timers(&mut self) {
+        self.snd_timer = self.snd_timer.saturating_sub(1);
+        self.dly_timer = self.dly_timer.saturating_sub(1);
+        self.rng.tick();
     }
 
     pub fn fetch(&mut self) {
         let memory = self.memory_ref.borrow();
 
         // get two consecutive bytes from memory and join them into a single u16
-        self.cir = (memory[self.pc as usize] as u16) << 8 | (memory[(self.pc + 1) as usize] as u16);
+        self.cir = (memory.read(self.pc as u32) as u16) << 8 | (memory.read(self.pc.wrapping_add(1) as u32) as u16);
 
         // increment the program counter
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2) & 0xFFF;
     }
 
     //TODO: Implement better printing to gui
@@ -85,7 +126,11 @@ impl CPU {
 
         if self.cir == 0 {
             // no-op
-            println!("--");
+            trace!(self, "--");
+            return;
+        }
+
+        if self.execute_platform(&instr) {
             return;
         }
 
@@ -94,151 +139,177 @@ impl CPU {
                 match instr.opcode & 0x00FF {
                     0x00E0 => {
                         // clear screen
-                        println!("Clearing screen");
-                        self.gpu_ref.borrow_mut().clear_screen();
+                        trace!(self, "Clearing screen");
+                        self.gpu_ref.borrow_mut().clear();
+                    }
+                    0x00EE => {
+                        // return from subroutine
+                        match self.stack.pop() {
+                            Some(address) => {
+                                trace!(self, "Returning from subroutine to {}", self.symbols.describe(address));
+                                self.pc = address;
+                            }
+                            None => self.fail(CpuError::StackUnderflow { pc: self.pc.wrapping_sub(2) }),
+                        }
                     }
                     _ => {
-                        println!("UNKOWN INSTRUCTION: {:X}", instr.opcode);
+                        trace!(self, "UNKOWN INSTRUCTION: {:X}", instr.opcode);
                     }
                 }
             }
             0x1000 => {
                 // jump to address NNN
-                println!("Jumping to address {:X}", instr.nnn);
+                trace!(self, "Jumping to address {}", self.symbols.describe(instr.nnn));
                 self.pc = instr.nnn;
             }
             0x2000 => {
                 // subroutine at NNN
+                trace!(self, "Calling subroutine at {}", self.symbols.describe(instr.nnn));
+                if self.stack.len() >= self.quirks.stack_depth {
+                    self.fail(CpuError::StackOverflow {
+                        pc: self.pc.wrapping_sub(2),
+                        depth: self.stack.len(),
+                    });
+                } else {
+                    self.stack.push(self.pc);
+                    self.pc = instr.nnn;
+                }
             }
             0x3000 => {
                 // skip next instruction if VX == NN
-                println!("Skipping instruction if V{:X} == {:X}", instr.x, instr.nn);
+                trace!(self, "Skipping instruction if V{:X} == {:X}", instr.x, instr.nn);
                 if self.v_reg[instr.x as usize] == instr.nn {
-                    println!("Skipping instruction");
+                    trace!(self, "Skipping instruction");
                     self.pc += 2;
                 }
             }
             0x4000 => {
                 // skip next instruction if VX != NN
-                println!("Skipping instruction if V{:X} != {:X}", instr.x, instr.nn);
+                trace!(self, "Skipping instruction if V{:X} != {:X}", instr.x, instr.nn);
                 if self.v_reg[instr.x as usize] != instr.nn {
-                    //println!("Skipping instruction");
+                    //trace!(self, "Skipping instruction");
                     self.pc += 2;
                 }
             }
             0x5000 => {
                 // skip if registers are equal
-                println!("Skipping instruction if V{:X} == V{:X}", instr.x, instr.y);
+                trace!(self, "Skipping instruction if V{:X} == V{:X}", instr.x, instr.y);
                 if self.v_reg[instr.x as usize] == self.v_reg[instr.y as usize] {
-                    println!("Skipping instruction");
+                    trace!(self, "Skipping instruction");
                     self.pc += 2;
                 }
             }
             0x6000 => {
                 // set VX to NN
-                println!("Setting V{:X} to {:X}", instr.x, instr.nn);
+                trace!(self, "Setting V{:X} to {:X}", instr.x, instr.nn);
                 self.v_reg[instr.x as usize] = instr.nn;
             }
             0x7000 => {
                 // add values
-                println!("Adding {:X} to V{:X}", instr.nn, instr.x);
-                let sum = self.v_reg[instr.x as usize] as u16 + instr.nn as u16;
-                if sum <= 255 {
-                    self.v_reg[instr.x as usize] += instr.nn;
-                } else {
-                    self.v_reg[instr.x as usize] = 255;
-                }
+                trace!(self, "Adding {:X} to V{:X}", instr.nn, instr.x);
+                // wraps around, VF is left alone
+                self.v_reg[instr.x as usize] = self.v_reg[instr.x as usize].wrapping_add(instr.nn);
             }
             0x8000 => {
                 match instr.n {
                     0x0 => {
                         // store vy in vx
-                        println!("Storing V{:X} in V{:X}", instr.y, instr.x);
+                        trace!(self, "Storing V{:X} in V{:X}", instr.y, instr.x);
                         self.v_reg[instr.x as usize] = self.v_reg[instr.y as usize];
                     }
                     0x1 => {
                         // set vx to vx | vy
-                        println!("Setting V{:X} to V{:X} | V{:X}", instr.x, instr.x, instr.y);
+                        trace!(self, "Setting V{:X} to V{:X} | V{:X}", instr.x, instr.x, instr.y);
                         self.v_reg[instr.x as usize] |= self.v_reg[instr.y as usize];
+
+                        if self.quirks.vf_reset {
+                            self.v_reg[0xF] = 0;
+                        }
                     }
                     0x2 => {
                         // set vx to vx & vy
-                        println!("Setting V{:X} to V{:X} & V{:X}", instr.x, instr.x, instr.y);
+                        trace!(self, "Setting V{:X} to V{:X} & V{:X}", instr.x, instr.x, instr.y);
                         self.v_reg[instr.x as usize] &= self.v_reg[instr.y as usize];
+
+                        if self.quirks.vf_reset {
+                            self.v_reg[0xF] = 0;
+                        }
                     }
                     0x3 => {
                         // set vx to vx ^ vy
-                        println!("Setting V{:X} to V{:X} ^ V{:X}", instr.x, instr.x, instr.y);
+                        trace!(self, "Setting V{:X} to V{:X} ^ V{:X}", instr.x, instr.x, instr.y);
                         self.v_reg[instr.x as usize] ^= self.v_reg[instr.y as usize];
+
+                        if self.quirks.vf_reset {
+                            self.v_reg[0xF] = 0;
+                        }
                     }
                     0x4 => {
                         // add vy to vx, vf = 1 if carry
-                        println!(
+                        trace!(
+                            self,
                             "Adding V{:X} to V{:X} with VF 0x1 if carry",
                             instr.y, instr.x
                         );
 
-                        let sum =
-                            (self.v_reg[instr.x as usize] as u16 + self.v_reg[instr.y as usize] as u16);
+                        let (sum, carry) =
+                            self.v_reg[instr.x as usize].overflowing_add(self.v_reg[instr.y as usize]);
 
-                        if sum > 0xFF {
-                            self.v_reg[0xF] = 1; // Carry
-                            self.v_reg[instr.x as usize] = 255;
-                        } else {
-                            self.v_reg[0xF] = 0; // No carry
-                            self.v_reg[instr.x as usize] = sum as u8;
-                        }
+                        // VF is written last so it wins when X is F
+                        self.v_reg[instr.x as usize] = sum;
+                        self.v_reg[0xF] = carry as u8;
                     }
                     0x5 => {
                         // subtract vy from vx, vf = 0 if borrow
-                        println!(
+                        trace!(
+                            self,
                             "Subtracting V{:X} to V{:X} with VF 0x0 if carry",
                             instr.y, instr.x
                         );
-                        
-                        if self.v_reg[instr.x as usize] > self.v_reg[instr.y as usize] {
-                            self.v_reg[0xF] = 1; // No borrow
-                            self.v_reg[instr.x as usize] -= self.v_reg[instr.y as usize];
-                        } else {
-                            self.v_reg[0xF] = 0; // Borrow
-                            self.v_reg[instr.x as usize] = 0;
-                        }
+
+                        let (difference, borrow) =
+                            self.v_reg[instr.x as usize].overflowing_sub(self.v_reg[instr.y as usize]);
+
+                        self.v_reg[instr.x as usize] = difference;
+                        self.v_reg[0xF] = !borrow as u8;
                     }
                     0x6 => {
                         // right shift vy, store in vx, set vf to least significant bit
-                        println!(
+                        trace!(
+                            self,
                             "Right shifting V{:X} and storing in V{:X}, least sig in VF",
                             instr.y, instr.x
                         );
                     
-                        let least_sig = self.v_reg[instr.y as usize] & 0x1;
-                        self.v_reg[instr.x as usize] = self.v_reg[instr.y as usize] >> 1;
+                        let source = if self.quirks.shift_vx { instr.x } else { instr.y };
+                        let least_sig = self.v_reg[source as usize] & 0x1;
+                        self.v_reg[instr.x as usize] = self.v_reg[source as usize] >> 1;
                         self.v_reg[0xF] = least_sig;
                     }
                     0x7 => {
                         // set vx to vy - vx, vf = 0 if borrow
-                        println!(
+                        trace!(
+                            self,
                             "Subtracting V{:X} to V{:X} with VF 0x0 if carry",
                             instr.x, instr.y
                         );
 
-                        self.v_reg[instr.x as usize] =
-                            self.v_reg[instr.y as usize] - self.v_reg[instr.x as usize];
+                        let (difference, borrow) =
+                            self.v_reg[instr.y as usize].overflowing_sub(self.v_reg[instr.x as usize]);
 
-                        if self.v_reg[instr.y as usize] > self.v_reg[instr.x as usize] {
-                            self.v_reg[0xF] = 1; // No borrow
-                        } else {
-                            self.v_reg[0xF] = 0; // Borrow
-                        }
+                        self.v_reg[instr.x as usize] = difference;
+                        self.v_reg[0xF] = !borrow as u8;
                     }
                     0xE => {
-                        // right shift vy, store in vx, set vf to most significant bit
-                        println!(
+                        // left shift vy, store in vx, set vf to most significant bit
+                        trace!(
+                            self,
                             "Left shifting V{:X} and storing in V{:X}, most sig in VF",
                             instr.y, instr.x
                         );
-                        let most_sig = self.v_reg[instr.y as usize] & 0x80;
-                        self.v_reg[instr.x as usize] = self.v_reg[instr.y as usize] << 1;
+                        let source = if self.quirks.shift_vx { instr.x } else { instr.y };
+                        let most_sig = self.v_reg[source as usize] >> 7;
+                        self.v_reg[instr.x as usize] = self.v_reg[source as usize] << 1;
                         self.v_reg[0xF] = most_sig;
                     }
                     _ => {
@@ -248,45 +319,142 @@ impl CPU {
             }
             0x9000 => {
                 // if vy != vx, skip next instruction
-                println!(
+                trace!(
+                    self,
                     "Skipping next instruction if V{:X} != V{:X}",
                     instr.x, instr.y
                 );
                 if self.v_reg[instr.x as usize] != self.v_reg[instr.y as usize] {
-                    println!("Skipping next instruction");
+                    trace!(self, "Skipping next instruction");
                     self.pc += 2;
                 }
             }
             0xA000 => {
                 // store NNN in index reg
-                println!("Storing {:X} in index reg", instr.nnn);
-                self.idx = instr.nnn;
+                trace!(self, "Storing {} in index reg", self.symbols.describe(instr.nnn));
+                self.idx = instr.nnn as u32;
+            }
+            0xB000 => {
+                if self.quirks.jump_vx {
+                    // jump to XNN + VX
+                    trace!(self, "Jumping to address {} + V{:X}", self.symbols.describe(instr.nnn), instr.x);
+                    self.pc = instr.nnn + self.v_reg[instr.x as usize] as u16;
+                } else {
+                    // jump to NNN + V0
+                    trace!(self, "Jumping to address {} + V0", self.symbols.describe(instr.nnn));
+                    self.pc = instr.nnn + self.v_reg[0] as u16;
+                }
+            }
+            0xE000 => {
+                match instr.nn {
+                    0x9E => {
+                        // skip next instruction if key VX is pressed
+                        trace!(self, "Skipping instruction if key V{:X} is pressed", instr.x);
+                        let key = self.v_reg[instr.x as usize] & 0xF;
+                        self.keys_checked |= 1 << key;
+                        if self.keypad[key as usize] {
+                            self.pc += 2;
+                        }
+                    }
+                    0xA1 => {
+                        // skip next instruction if key VX is not pressed
+                        trace!(self, "Skipping instruction if key V{:X} is not pressed", instr.x);
+                        let key = self.v_reg[instr.x as usize] & 0xF;
+                        self.keys_checked |= 1 << key;
+                        if !self.keypad[key as usize] {
+                            self.pc += 2;
+                        }
+                    }
+                    _ => {
+                        trace!(self, "UNKOWN INSTRUCTION: {:X}", instr.opcode);
+                    }
+                }
             }
             0xF000 => {
                 match instr.nn {
+                    0x07 => {
+                        // store delay timer in vx
+                        trace!(self, "Storing delay timer in V{:X}", instr.x);
+                        self.v_reg[instr.x as usize] = self.dly_timer;
+                    }
+                    0x0A => {
+                        // block until a key is pressed and released, store it in vx
+                        trace!(self, "Waiting for key press into V{:X}", instr.x);
+                        // any key will do
+                        self.keys_checked = 0xFFFF;
+                        match self.key_wait {
+                            Some(key) if !self.keypad[key as usize] => {
+                                self.v_reg[instr.x as usize] = key;
+                                self.key_wait = None;
+                            }
+                            Some(_) => self.repeat_instruction(),
+                            None => {
+                                self.key_wait = self.keypad.iter().position(|pressed| *pressed).map(|key| key as u8);
+                                self.repeat_instruction();
+                            }
+                        }
+                    }
+                    0x15 => {
+                        // set delay timer to vx
+                        trace!(self, "Setting delay timer to V{:X}", instr.x);
+                        self.dly_timer = self.v_reg[instr.x as usize];
+                    }
+                    0x18 => {
+                        // set sound timer to vx
+                        trace!(self, "Setting sound timer to V{:X}", instr.x);
+                        self.snd_timer = self.v_reg[instr.x as usize];
+                    }
                     0x1E => {
                         // add vx to index reg
-                        println!("Adding V{:X} to index reg", instr.x);
-                        self.idx += self.v_reg[instr.x as usize] as u16;
+                        trace!(self, "Adding V{:X} to index reg", instr.x);
+                        self.idx = self.idx.wrapping_add(self.v_reg[instr.x as usize] as u32);
+                    }
+                    0x29 => {
+                        // point index reg at the font character for the low nibble of vx
+                        trace!(self, "Pointing index reg at font character V{:X}", instr.x);
+                        self.idx = self.font_address as u32 + (self.v_reg[instr.x as usize] & 0xF) as u32 * 5;
+                    }
+                    0x33 => {
+                        // store the decimal digits of vx at I, I + 1 and I + 2
+                        trace!(self, "Storing BCD of V{:X}", instr.x);
+                        let value = self.v_reg[instr.x as usize];
+                        let mut memory = self.memory_ref.borrow_mut();
+                        memory.write(self.idx, value / 100);
+                        memory.write(self.idx.wrapping_add(1), value / 10 % 10);
+                        memory.write(self.idx.wrapping_add(2), value % 10);
+                    }
+                    0x55 => {
+                        // store registers V0 through VX in memory starting at location I
+                        trace!(self, "Storing registers V0 through V{:X} in memory", instr.x);
+                        let mut memory = self.memory_ref.borrow_mut();
+                        for i in 0..instr.x + 1 {
+                            memory.write(self.idx.wrapping_add(i as u32), self.v_reg[i as usize]);
+                        }
+
+                        if self.quirks.memory_increment {
+                            self.idx += instr.x as u32 + 1;
+                        }
                     }
                     0x65 => {
                         // load registers V0 through VX from memory starting at location I
-                        println!("Loading registers V0 through V{:X} from memory", instr.x);
+                        trace!(self, "Loading registers V0 through V{:X} from memory", instr.x);
+                        let memory = self.memory_ref.borrow();
                         for i in 0..instr.x + 1 {
-                            self.v_reg[i as usize] =
-                                self.memory_ref.borrow()[(self.idx + i as u16) as usize];
+                            self.v_reg[i as usize] = memory.read(self.idx.wrapping_add(i as u32));
+                        }
+
+                        if self.quirks.memory_increment {
+                            self.idx += instr.x as u32 + 1;
                         }
                     }
                     _ => {
-                        println!("UNKOWN INSTRUCTION: {:X}", instr.opcode);
+                        trace!(self, "UNKOWN INSTRUCTION: {:X}", instr.opcode);
                     }
                 }
-                // Add the value stored in register VX to register I
-                println!("Adding V{:X} to index reg", instr.x);
-                self.idx += self.v_reg[instr.x as usize] as u16;
             }
             0xD000 => {
-                println!(
+                trace!(
+                    self,
                     "Drawing sprite at ({:X}, {:X}) with height {:X}",
                     self.v_reg[instr.x as usize], self.v_reg[instr.y as usize], instr.n
                 );
@@ -295,33 +463,393 @@ impl CPU {
                 self.v_reg[0xF] = 0;
 
                 // get coords
-                let mut coords: (u8, u8) =
+                let coords: (u8, u8) =
                     (self.v_reg[instr.x as usize], self.v_reg[instr.y as usize]);
 
-                let sprite: &[u8] = &self.memory_ref.borrow()[self.idx as usize..(self.idx + instr.n as u16) as usize];
+                let memory = self.memory_ref.borrow();
+                let sprite: Vec<u8> = (0..instr.n as u32)
+                    .map(|row| memory.read(self.idx.wrapping_add(row)))
+                    .collect();
+                drop(memory);
 
-                if self.gpu_ref.borrow_mut().draw(coords, sprite) {
+                if self.gpu_ref.borrow_mut().draw(coords, &sprite, self.quirks.clipping) {
                     self.v_reg[0xF] = 1;
                 }
                 
-                println!("VF: {}", self.v_reg[0xF]);
+                trace!(self, "VF: {}", self.v_reg[0xF]);
             }
             0xC000 => {
                 // set VX to rand() & NN
-                self.v_reg[instr.x as usize] = rand::random::<u8>() & instr.nn;
-                println!(
+                self.v_reg[instr.x as usize] = self.rng.next_u8() & instr.nn;
+                trace!(
+                    self,
                     "Random V{:X} to {:X}",
                     instr.x, self.v_reg[instr.x as usize]
                 );
             }
             _ => {
                 //TODO: panic!("Unknown opcode: {:X}", instr.opcode);
-                println!("UNKOWN INSTRUCTION: {:X}", instr.opcode);
+                trace!(self, "UNKOWN INSTRUCTION: {:X}", instr.opcode);
+            }
+        }
+    }
+}
+
+impl CPU {
+    /// Runs the instructions the platform adds or changes, returns false for the ones that
+    /// work like plain CHIP-8
+    fn execute_platform(&mut self, instr: &Instruction) -> bool {
+        match self.platform {
+            Platform::Chip8 => false,
+            Platform::Hires => self.execute_hires(instr),
+            Platform::Chip8X => self.execute_chip8x(instr),
+            Platform::Chip8E => self.execute_chip8e(instr),
+            Platform::MegaChip => self.execute_megachip(instr),
+        }
+    }
+
+    fn execute_hires(&mut self, instr: &Instruction) -> bool {
+        match instr.opcode {
+            0x0230 => {
+                // clear the 64x64 screen
+                trace!(self, "Clearing hires screen");
+                self.gpu_ref.borrow_mut().clear();
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Stops at the instruction that went wrong, so it's the one the debugger shows
+    fn fail(&mut self, error: CpuError) {
+        trace!(self, "{}", error);
+        self.repeat_instruction();
+        self.error = Some(error);
+    }
+
+    /// Points the PC back at the instruction that just ran, for instructions that wait
+    fn repeat_instruction(&mut self) {
+        self.pc = self.pc.wrapping_sub(2) & 0xFFF;
+    }
+
+    fn execute_chip8x(&mut self, instr: &Instruction) -> bool {
+        let x = instr.x as usize;
+        let y = instr.y as usize;
+
+        match (instr.opcode & 0xF000, instr.nn, instr.n) {
+            (0x0000, _, _) if instr.opcode == 0x02A0 => {
+                // step the background color
+                trace!(self, "Cycling background color");
+                self.gpu_ref.borrow_mut().cycle_background();
+            }
+            (0x5000, _, 0x1) => {
+                // add the nibbles of vy to those of vx, each wrapping at 8
+                trace!(self, "Adding nibbles of V{:X} to V{:X}", instr.y, instr.x);
+                let (vx, vy) = (self.v_reg[x], self.v_reg[y]);
+                let high = ((vx >> 4) + (vy >> 4)) & 0x7;
+                let low = ((vx & 0xF) + (vy & 0xF)) & 0x7;
+                self.v_reg[x] = high << 4 | low;
+            }
+            (0xB000, _, 0x0) => {
+                // color 8x4 pixel zones: vx is column and extra width, vx+1 row and extra height
+                trace!(self, "Coloring zones V{:X}, V{:X} with V{:X}", instr.x, (x + 1) & 0xF, instr.y);
+                let (horizontal, vertical) = (self.v_reg[x] as usize, self.v_reg[(x + 1) & 0xF] as usize);
+                let columns = (horizontal & 0xF) * 8..((horizontal & 0xF) + (horizontal >> 4) + 1) * 8;
+                let rows = (vertical & 0xF) * 4..((vertical & 0xF) + (vertical >> 4) + 1) * 4;
+                let color = self.v_reg[y];
+                self.gpu_ref.borrow_mut().set_foreground(columns, rows, color);
+            }
+            (0xB000, _, _) => {
+                // color the 8 pixel column holding vx for n rows from vx+1
+                trace!(self, "Coloring {} rows at V{:X}, V{:X} with V{:X}", instr.n, instr.x, (x + 1) & 0xF, instr.y);
+                let column = self.v_reg[x] as usize & !0x7;
+                let row = self.v_reg[(x + 1) & 0xF] as usize;
+                let color = self.v_reg[y];
+                self.gpu_ref
+                    .borrow_mut()
+                    .set_foreground(column..column + 8, row..row + instr.n as usize, color);
+            }
+            (0xE000, 0xF2, _) => {
+                // skip if key vx is held on the second keypad, which isn't connected
+                trace!(self, "Skipping instruction if keypad 2 key V{:X} is pressed", instr.x);
+            }
+            (0xE000, 0xF5, _) => {
+                // skip if key vx isn't held on the second keypad
+                trace!(self, "Skipping instruction if keypad 2 key V{:X} is not pressed", instr.x);
+                self.pc += 2;
+            }
+            (0xF000, 0xF8, _) => {
+                // vx to the output port, the tone of the sound
+                trace!(self, "Writing V{:X} to the output port", instr.x);
+            }
+            (0xF000, 0xFB, _) => {
+                // wait for the input port, nothing is connected so it reads as 0
+                trace!(self, "Reading the input port into V{:X}", instr.x);
+                self.v_reg[x] = 0;
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn execute_chip8e(&mut self, instr: &Instruction) -> bool {
+        let x = instr.x as usize;
+        let y = instr.y as usize;
+
+        match (instr.opcode & 0xF000, instr.nn, instr.n) {
+            (0x0000, _, _) => match instr.opcode {
+                0x00ED => {
+                    // stop
+                    trace!(self, "Stopping");
+                    self.repeat_instruction();
+                }
+                0x00F2 => {
+                    trace!(self, "No operation");
+                }
+                0x0151 => {
+                    // wait for the delay timer to run out
+                    trace!(self, "Waiting for the delay timer");
+                    if self.dly_timer != 0 {
+                        self.repeat_instruction();
+                    }
+                }
+                0x0188 => {
+                    // skip the next instruction
+                    trace!(self, "Skipping instruction");
+                    self.pc += 2;
+                }
+                _ => return false,
+            },
+            (0x5000, _, 0x1) => {
+                // skip if vx > vy
+                trace!(self, "Skipping instruction if V{:X} > V{:X}", instr.x, instr.y);
+                if self.v_reg[x] > self.v_reg[y] {
+                    self.pc += 2;
+                }
+            }
+            (0x5000, _, 0x2) => {
+                // store vx through vy at I, I is left past the last one
+                trace!(self, "Storing registers V{:X} through V{:X} in memory", instr.x, instr.y);
+                let mut memory = self.memory_ref.borrow_mut();
+                for register in x..=y {
+                    memory.write(self.idx, self.v_reg[register]);
+                    self.idx = self.idx.wrapping_add(1);
+                }
+            }
+            (0x5000, _, 0x3) => {
+                // load vx through vy from I, I is left past the last one
+                trace!(self, "Loading registers V{:X} through V{:X} from memory", instr.x, instr.y);
+                let memory = self.memory_ref.borrow();
+                for register in x..=y {
+                    self.v_reg[register] = memory.read(self.idx);
+                    self.idx = self.idx.wrapping_add(1);
+                }
+            }
+            (0xB000, _, _) if x == 0xB => {
+                // jump back nn bytes from the next instruction
+                trace!(self, "Jumping back {:X} bytes", instr.nn);
+                self.pc = self.pc.wrapping_sub(instr.nn as u16) & 0xFFF;
+            }
+            (0xB000, _, _) if x == 0xF => {
+                // jump forward nn bytes from the next instruction
+                trace!(self, "Jumping forward {:X} bytes", instr.nn);
+                self.pc = self.pc.wrapping_add(instr.nn as u16) & 0xFFF;
+            }
+            (0xF000, 0x03, _) => {
+                // vx to output port 3
+                trace!(self, "Writing V{:X} to port 3", instr.x);
+            }
+            (0xF000, 0x1B, _) => {
+                // skip vx bytes
+                trace!(self, "Skipping V{:X} bytes", instr.x);
+                self.pc = self.pc.wrapping_add(self.v_reg[x] as u16) & 0xFFF;
+            }
+            (0xF000, 0x4F, _) => {
+                // set the delay timer to vx and wait for it to run out
+                trace!(self, "Delaying for V{:X}", instr.x);
+                if !self.delay_wait {
+                    self.dly_timer = self.v_reg[x];
+                    self.delay_wait = true;
+                }
+
+                if self.dly_timer == 0 {
+                    self.delay_wait = false;
+                } else {
+                    self.repeat_instruction();
+                }
+            }
+            (0xF000, 0xE3 | 0xE7, _) => {
+                // read input port 3, nothing is connected so it reads as 0
+                trace!(self, "Reading port 3 into V{:X}", instr.x);
+                self.v_reg[x] = 0;
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn execute_megachip(&mut self, instr: &Instruction) -> bool {
+        let x = instr.x as usize;
+        let y = instr.y as usize;
+
+        match instr.opcode {
+            0x0010 | 0x0011 => {
+                // leave or enter MEGA-CHIP mode
+                let enabled = instr.opcode == 0x0011;
+                trace!(self, "Turning MEGA-CHIP mode {}", if enabled { "on" } else { "off" });
+                self.gpu_ref.borrow_mut().set_megachip(enabled);
+                return true;
+            }
+            0x0700 => {
+                trace!(self, "Stopping sound");
+                self.sample = None;
+                return true;
+            }
+            _ => {}
+        }
+
+        match instr.opcode & 0xFF00 {
+            0x0100 => {
+                // I gets 24 bits, the low 16 from the next word
+                let memory = self.memory_ref.borrow();
+                let low = (memory.read(self.pc as u32) as u32) << 8 | memory.read(self.pc.wrapping_add(1) as u32) as u32;
+                drop(memory);
+
+                self.idx = (instr.nn as u32) << 16 | low;
+                self.pc = self.pc.wrapping_add(2) & 0xFFF;
+                trace!(self, "Storing {:06X} in index reg", self.idx);
+                return true;
+            }
+            0x0600 if instr.nn & 0xF0 == 0 => {
+                // play the sound at I, looping unless n is 1
+                trace!(self, "Playing sound at {:06X}", self.idx);
+                let memory = self.memory_ref.borrow();
+                let header: Vec<u8> = (0..6).map(|i| memory.read(self.idx.wrapping_add(i))).collect();
+                let data: Vec<u8> = (0..SamplePlayback::header_length(&header) as u32)
+                    .map(|i| memory.read(self.idx.wrapping_add(i)))
+                    .collect();
+                self.sample = Some(SamplePlayback::from_header(&data, instr.n == 0));
+                return true;
+            }
+            _ => {}
+        }
+
+        let mut gpu = self.gpu_ref.borrow_mut();
+        // everything else only exists in MEGA-CHIP mode
+        let Some(screen) = gpu.megachip() else {
+            return false;
+        };
+
+        match instr.opcode & 0xFF00 {
+            0x0000 => match instr.opcode & 0xFFF0 {
+                0x00E0 if instr.opcode == 0x00E0 => {
+                    trace!(self, "Showing the frame");
+                    screen.present();
+                }
+                0x00B0 => {
+                    trace!(self, "Scrolling up {} rows", instr.n);
+                    screen.scroll_up(instr.n as usize);
+                }
+                0x00C0 => {
+                    trace!(self, "Scrolling down {} rows", instr.n);
+                    screen.scroll_down(instr.n as usize);
+                }
+                0x00F0 if instr.opcode == 0x00FB => {
+                    trace!(self, "Scrolling right");
+                    screen.scroll_right(4);
+                }
+                0x00F0 if instr.opcode == 0x00FC => {
+                    trace!(self, "Scrolling left");
+                    screen.scroll_left(4);
+                }
+                _ => return false,
+            },
+            0x0200 => {
+                // nn palette entries from I, ARGB
+                trace!(self, "Loading {} colors from {:06X}", instr.nn, self.idx);
+                let memory = self.memory_ref.borrow();
+                let colors: Vec<u8> = (0..instr.nn as u32 * 4)
+                    .map(|i| memory.read(self.idx.wrapping_add(i)))
+                    .collect();
+                screen.load_palette(&colors);
             }
+            0x0300 => {
+                trace!(self, "Setting sprite width to {}", instr.nn);
+                screen.sprite_width = instr.nn as usize;
+            }
+            0x0400 => {
+                trace!(self, "Setting sprite height to {}", instr.nn);
+                screen.sprite_height = instr.nn as usize;
+            }
+            0x0500 => {
+                trace!(self, "Setting screen alpha to {:X}", instr.nn);
+                screen.screen_alpha = instr.nn;
+            }
+            0x0800 => {
+                trace!(self, "Setting blend mode {}", instr.n);
+                if let Some(mode) = BlendMode::from_nibble(instr.n) {
+                    screen.blend_mode = mode;
+                }
+            }
+            0x0900 => {
+                trace!(self, "Setting collision color to {:X}", instr.nn);
+                screen.collision_color = instr.nn;
+            }
+            _ if instr.opcode & 0xF000 == 0xD000 => {
+                trace!(
+                    self,
+                    "Drawing color sprite at ({:X}, {:X})",
+                    self.v_reg[x], self.v_reg[y]
+                );
+                let coords = (self.v_reg[x], self.v_reg[y]);
+                let memory = self.memory_ref.borrow();
+
+                // the font is still plain 1 bit sprites
+                let collided = if self.idx < PROGRAM_START as u32 {
+                    let sprite: Vec<u8> = (0..instr.n as u32).map(|row| memory.read(self.idx.wrapping_add(row))).collect();
+                    screen.draw_glyph(coords, &sprite)
+                } else {
+                    let (width, height) = screen.sprite_size();
+                    let sprite: Vec<u8> = (0..(width * height) as u32)
+                        .map(|i| memory.read(self.idx.wrapping_add(i)))
+                        .collect();
+                    screen.draw(coords, &sprite)
+                };
+
+                self.v_reg[0xF] = collided as u8;
+            }
+            _ => return false,
         }
+
+        true
     }
 }
 
+/// Something the program did that would crash the real interpreter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CpuError {
+    /// `2NNN` with `depth` return addresses already on the stack
+    StackOverflow { pc: u16, depth: usize },
+    /// `00EE` with nothing on the stack
+    StackUnderflow { pc: u16 },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CpuError::StackOverflow { pc, depth } => {
+                write!(f, "Stack overflow at {:03X}: the stack only holds {} return addresses", pc, depth)
+            }
+            CpuError::StackUnderflow { pc } => write!(f, "Stack underflow at {:03X}: returning with nothing to return to", pc),
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
 pub struct Instruction {
     opcode: u16,
     nnn: u16,
@@ -348,4 +876,482 @@ impl Instruction {
             y,
         }
     }
+
+    /// The pattern this instruction matches, like "8XY4", or "????" if it isn't one
+    pub fn pattern(&self) -> &'static str {
+        match self.opcode & 0xF000 {
+            0x0000 => match self.opcode {
+                0x00E0 => "00E0",
+                0x00EE => "00EE",
+                0x00ED => "00ED",
+                0x00F2 => "00F2",
+                0x0151 => "0151",
+                0x0188 => "0188",
+                0x0230 => "0230",
+                0x02A0 => "02A0",
+                0x0010 => "0010",
+                0x0011 => "0011",
+                0x00FB => "00FB",
+                0x00FC => "00FC",
+                0x0700 => "0700",
+                _ => match self.opcode & 0xFFF0 {
+                    0x00B0 => "00BN",
+                    0x00C0 => "00CN",
+                    0x0600 => "060N",
+                    0x0800 => "080N",
+                    _ => match self.opcode & 0xFF00 {
+                        0x0100 => "01NN",
+                        0x0200 => "02NN",
+                        0x0300 => "03NN",
+                        0x0400 => "04NN",
+                        0x0500 => "05NN",
+                        0x0900 => "09NN",
+                        _ => "0NNN",
+                    },
+                },
+            },
+            0x1000 => "1NNN",
+            0x2000 => "2NNN",
+            0x3000 => "3XNN",
+            0x4000 => "4XNN",
+            0x5000 => match self.n {
+                0x0 => "5XY0",
+                0x1 => "5XY1",
+                0x2 => "5XY2",
+                0x3 => "5XY3",
+                _ => "????",
+            },
+            0x6000 => "6XNN",
+            0x7000 => "7XNN",
+            0x8000 => match self.n {
+                0x0 => "8XY0",
+                0x1 => "8XY1",
+                0x2 => "8XY2",
+                0x3 => "8XY3",
+                0x4 => "8XY4",
+                0x5 => "8XY5",
+                0x6 => "8XY6",
+                0x7 => "8XY7",
+                0xE => "8XYE",
+                _ => "????",
+            },
+            0x9000 if self.n == 0 => "9XY0",
+            0xA000 => "ANNN",
+            0xB000 => "BNNN",
+            0xC000 => "CXNN",
+            0xD000 => "DXYN",
+            0xE000 => match self.nn {
+                0x9E => "EX9E",
+                0xA1 => "EXA1",
+                0xF2 => "EXF2",
+                0xF5 => "EXF5",
+                _ => "????",
+            },
+            0xF000 => match self.nn {
+                0x03 => "FX03",
+                0x07 => "FX07",
+                0x0A => "FX0A",
+                0x15 => "FX15",
+                0x18 => "FX18",
+                0x1B => "FX1B",
+                0x1E => "FX1E",
+                0x29 => "FX29",
+                0x33 => "FX33",
+                0x55 => "FX55",
+                0x4F => "FX4F",
+                0x65 => "FX65",
+                0xE3 => "FXE3",
+                0xE7 => "FXE7",
+                0xF8 => "FXF8",
+                0xFB => "FXFB",
+                _ => "????",
+            },
+            _ => "????",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::display::FramebufferDisplay;
+    use crate::emulator::rng::FixedSequenceRng;
+
+    struct Machine {
+        cpu: CPU,
+        memory: Rc<RefCell<[u8; 4096]>>,
+        display: Rc<RefCell<FramebufferDisplay>>,
+    }
+
+    impl Machine {
+        /// A CPU at 0x200 with the program loaded there and the given quirks
+        fn new(program: &[u16], quirks: Quirks) -> Machine {
+            let memory = Rc::new(RefCell::new([0; 4096]));
+            let display = Rc::new(RefCell::new(FramebufferDisplay::new()));
+
+            for (i, opcode) in program.iter().enumerate() {
+                let address = 0x200 + i * 2;
+                memory.borrow_mut()[address..address + 2].copy_from_slice(&opcode.to_be_bytes());
+            }
+
+            let mut cpu = CPU::new(memory.clone(), display.clone(), 0);
+            cpu.reset(0x200, 0);
+            cpu.quirks = quirks;
+
+            Machine { cpu, memory, display }
+        }
+
+        fn run(program: &[u16]) -> Machine {
+            let mut machine = Machine::new(program, Quirks::default());
+            machine.step_n(program.len());
+            machine
+        }
+
+        fn step(&mut self) {
+            self.cpu.fetch();
+            self.cpu.decode_execute();
+        }
+
+        fn step_n(&mut self, count: usize) {
+            for _ in 0..count {
+                self.step();
+            }
+        }
+
+        fn lit(&self, x: usize, y: usize) -> bool {
+            self.display.borrow().pixels.is_lit(x, y)
+        }
+    }
+
+    #[test]
+    fn clear_screen_00e0() {
+        let mut machine = Machine::new(&[0x00E0], Quirks::default());
+        machine.display.borrow_mut().pixels.set(5, 5, 255);
+        machine.step();
+        assert!(!machine.lit(5, 5));
+    }
+
+    #[test]
+    fn call_2nnn_and_return_00ee() {
+        let mut machine = Machine::new(&[0x2206, 0x0000, 0x0000, 0x00EE], Quirks::default());
+
+        machine.step();
+        assert_eq!(machine.cpu.pc, 0x206);
+        assert_eq!(machine.cpu.stack, vec![0x202]);
+
+        machine.step();
+        assert_eq!(machine.cpu.pc, 0x202);
+        assert!(machine.cpu.stack.is_empty());
+    }
+
+    #[test]
+    fn jump_1nnn() {
+        let machine = Machine::run(&[0x1ABC]);
+        assert_eq!(machine.cpu.pc, 0xABC);
+    }
+
+    #[test]
+    fn skip_if_equal_3xnn() {
+        assert_eq!(Machine::run(&[0x6142, 0x3142]).cpu.pc, 0x206);
+        assert_eq!(Machine::run(&[0x6142, 0x3143]).cpu.pc, 0x204);
+    }
+
+    #[test]
+    fn skip_if_not_equal_4xnn() {
+        assert_eq!(Machine::run(&[0x6142, 0x4142]).cpu.pc, 0x204);
+        assert_eq!(Machine::run(&[0x6142, 0x4143]).cpu.pc, 0x206);
+    }
+
+    #[test]
+    fn skip_if_registers_equal_5xy0() {
+        assert_eq!(Machine::run(&[0x6107, 0x6207, 0x5120]).cpu.pc, 0x208);
+        assert_eq!(Machine::run(&[0x6107, 0x6208, 0x5120]).cpu.pc, 0x206);
+    }
+
+    #[test]
+    fn set_6xnn() {
+        assert_eq!(Machine::run(&[0x6A5C]).cpu.v_reg[0xA], 0x5C);
+    }
+
+    #[test]
+    fn add_7xnn_wraps_without_carry() {
+        let machine = Machine::run(&[0x61FF, 0x6F07, 0x7102]);
+        assert_eq!(machine.cpu.v_reg[1], 0x01);
+        assert_eq!(machine.cpu.v_reg[0xF], 0x07);
+    }
+
+    #[test]
+    fn copy_8xy0() {
+        assert_eq!(Machine::run(&[0x6233, 0x8120]).cpu.v_reg[1], 0x33);
+    }
+
+    #[test]
+    fn logic_8xy1_8xy2_8xy3() {
+        assert_eq!(Machine::run(&[0x610C, 0x620A, 0x8121]).cpu.v_reg[1], 0x0E);
+        assert_eq!(Machine::run(&[0x610C, 0x620A, 0x8122]).cpu.v_reg[1], 0x08);
+        assert_eq!(Machine::run(&[0x610C, 0x620A, 0x8123]).cpu.v_reg[1], 0x06);
+    }
+
+    #[test]
+    fn logic_vf_reset_quirk() {
+        let program = [0x6F05, 0x8121];
+
+        let mut vip = Machine::new(&program, Quirks::cosmac_vip());
+        vip.step_n(2);
+        assert_eq!(vip.cpu.v_reg[0xF], 0);
+
+        let mut schip = Machine::new(&program, Quirks::schip());
+        schip.step_n(2);
+        assert_eq!(schip.cpu.v_reg[0xF], 5);
+    }
+
+    #[test]
+    fn add_8xy4_sets_carry() {
+        let machine = Machine::run(&[0x61F0, 0x6220, 0x8124]);
+        assert_eq!(machine.cpu.v_reg[1], 0x10);
+        assert_eq!(machine.cpu.v_reg[0xF], 1);
+
+        let machine = Machine::run(&[0x6110, 0x6220, 0x8124]);
+        assert_eq!(machine.cpu.v_reg[1], 0x30);
+        assert_eq!(machine.cpu.v_reg[0xF], 0);
+    }
+
+    #[test]
+    fn flag_wins_when_vf_is_the_target() {
+        let machine = Machine::run(&[0x6FF0, 0x6220, 0x8F24]);
+        assert_eq!(machine.cpu.v_reg[0xF], 1);
+    }
+
+    #[test]
+    fn subtract_8xy5() {
+        let machine = Machine::run(&[0x6130, 0x6210, 0x8125]);
+        assert_eq!(machine.cpu.v_reg[1], 0x20);
+        assert_eq!(machine.cpu.v_reg[0xF], 1);
+
+        let machine = Machine::run(&[0x6110, 0x6230, 0x8125]);
+        assert_eq!(machine.cpu.v_reg[1], 0xE0);
+        assert_eq!(machine.cpu.v_reg[0xF], 0);
+
+        // equal values don't borrow
+        assert_eq!(Machine::run(&[0x6110, 0x6210, 0x8125]).cpu.v_reg[0xF], 1);
+    }
+
+    #[test]
+    fn subtract_reversed_8xy7() {
+        let machine = Machine::run(&[0x6110, 0x6230, 0x8127]);
+        assert_eq!(machine.cpu.v_reg[1], 0x20);
+        assert_eq!(machine.cpu.v_reg[0xF], 1);
+
+        let machine = Machine::run(&[0x6130, 0x6210, 0x8127]);
+        assert_eq!(machine.cpu.v_reg[1], 0xE0);
+        assert_eq!(machine.cpu.v_reg[0xF], 0);
+    }
+
+    #[test]
+    fn shift_right_8xy6() {
+        let machine = Machine::run(&[0x6100, 0x6205, 0x8126]);
+        assert_eq!(machine.cpu.v_reg[1], 0x02);
+        assert_eq!(machine.cpu.v_reg[0xF], 1);
+
+        let mut schip = Machine::new(&[0x6105, 0x6200, 0x8126], Quirks::schip());
+        schip.step_n(3);
+        assert_eq!(schip.cpu.v_reg[1], 0x02);
+        assert_eq!(schip.cpu.v_reg[0xF], 1);
+    }
+
+    #[test]
+    fn shift_left_8xye() {
+        let machine = Machine::run(&[0x6100, 0x6281, 0x812E]);
+        assert_eq!(machine.cpu.v_reg[1], 0x02);
+        assert_eq!(machine.cpu.v_reg[0xF], 1);
+
+        let mut schip = Machine::new(&[0x6141, 0x6200, 0x812E], Quirks::schip());
+        schip.step_n(3);
+        assert_eq!(schip.cpu.v_reg[1], 0x82);
+        assert_eq!(schip.cpu.v_reg[0xF], 0);
+    }
+
+    #[test]
+    fn skip_if_registers_differ_9xy0() {
+        assert_eq!(Machine::run(&[0x6107, 0x6208, 0x9120]).cpu.pc, 0x208);
+        assert_eq!(Machine::run(&[0x6107, 0x6207, 0x9120]).cpu.pc, 0x206);
+    }
+
+    #[test]
+    fn set_index_annn() {
+        assert_eq!(Machine::run(&[0xA123]).cpu.idx, 0x123);
+    }
+
+    #[test]
+    fn jump_with_offset_bnnn() {
+        let program = [0x6004, 0x6210, 0xB220];
+
+        let mut vip = Machine::new(&program, Quirks::cosmac_vip());
+        vip.step_n(3);
+        assert_eq!(vip.cpu.pc, 0x224);
+
+        let mut schip = Machine::new(&program, Quirks::schip());
+        schip.step_n(3);
+        assert_eq!(schip.cpu.pc, 0x230);
+    }
+
+    #[test]
+    fn random_cxnn_masks_the_rng() {
+        let mut machine = Machine::new(&[0xC10F, 0xC1F0], Quirks::default());
+        machine.cpu.rng = Box::new(FixedSequenceRng::new(vec![0xAB, 0xCD]));
+
+        machine.step();
+        assert_eq!(machine.cpu.v_reg[1], 0x0B);
+        machine.step();
+        assert_eq!(machine.cpu.v_reg[1], 0xC0);
+    }
+
+    #[test]
+    fn draw_dxyn_and_collision() {
+        // font character 0 at 2,3
+        let mut machine = Machine::new(&[0x6002, 0x6103, 0xA000, 0xD015, 0xD015], Quirks::default());
+        machine.memory.borrow_mut()[0..5].copy_from_slice(&[0xF0, 0x90, 0x90, 0x90, 0xF0]);
+
+        machine.step_n(4);
+        assert!(machine.lit(2, 3));
+        assert!(machine.lit(5, 7));
+        assert!(!machine.lit(3, 4));
+        assert_eq!(machine.cpu.v_reg[0xF], 0);
+
+        machine.step();
+        assert!(!machine.lit(2, 3));
+        assert_eq!(machine.cpu.v_reg[0xF], 1);
+    }
+
+    #[test]
+    fn draw_dxyn_clips_or_wraps() {
+        let program = [0x603C, 0x611E, 0xA300, 0xD014];
+
+        let mut clipped = Machine::new(&program, Quirks::cosmac_vip());
+        clipped.memory.borrow_mut()[0x300..0x304].copy_from_slice(&[0xFF; 4]);
+        clipped.step_n(4);
+        assert!(clipped.lit(63, 31));
+        assert!(!clipped.lit(0, 31));
+        assert!(!clipped.lit(63, 0));
+
+        let mut wrapped = Machine::new(&program, Quirks::xo_chip());
+        wrapped.memory.borrow_mut()[0x300..0x304].copy_from_slice(&[0xFF; 4]);
+        wrapped.step_n(4);
+        assert!(wrapped.lit(0, 31));
+        assert!(wrapped.lit(63, 0));
+    }
+
+    #[test]
+    fn skip_if_key_ex9e_exa1() {
+        let mut machine = Machine::new(&[0x6105, 0xE19E], Quirks::default());
+        machine.cpu.keypad[5] = true;
+        machine.step_n(2);
+        assert_eq!(machine.cpu.pc, 0x206);
+
+        let mut machine = Machine::new(&[0x6105, 0xE1A1], Quirks::default());
+        machine.cpu.keypad[5] = true;
+        machine.step_n(2);
+        assert_eq!(machine.cpu.pc, 0x204);
+        assert_eq!(machine.cpu.keys_checked, 1 << 5);
+    }
+
+    #[test]
+    fn timers_fx07_fx15_fx18() {
+        let mut machine = Machine::new(&[0x6103, 0xF115, 0xF118, 0xF207], Quirks::default());
+        machine.step_n(3);
+        assert_eq!(machine.cpu.dly_timer, 3);
+        assert_eq!(machine.cpu.snd_timer, 3);
+
+        machine.cpu.tick_timers();
+        machine.step();
+        assert_eq!(machine.cpu.v_reg[2], 2);
+
+        for _ in 0..5 {
+            machine.cpu.tick_timers();
+        }
+        assert_eq!(machine.cpu.dly_timer, 0);
+        assert_eq!(machine.cpu.snd_timer, 0);
+    }
+
+    #[test]
+    fn wait_for_key_fx0a_finishes_on_release() {
+        let mut machine = Machine::new(&[0xF30A], Quirks::default());
+
+        machine.step();
+        assert_eq!(machine.cpu.pc, 0x200);
+
+        machine.cpu.keypad[7] = true;
+        machine.step();
+        assert_eq!(machine.cpu.pc, 0x200);
+
+        machine.cpu.keypad[7] = false;
+        machine.step();
+        assert_eq!(machine.cpu.pc, 0x202);
+        assert_eq!(machine.cpu.v_reg[3], 7);
+    }
+
+    #[test]
+    fn wait_for_key_fx0a_at_the_end_of_memory() {
+        let mut machine = Machine::new(&[], Quirks::default());
+        machine.memory.borrow_mut()[0xFFE..].copy_from_slice(&[0xF3, 0x0A]);
+        machine.cpu.pc = 0xFFE;
+
+        // the PC wraps to 0 after the fetch and has to go back to 0xFFE
+        machine.step();
+        assert_eq!(machine.cpu.pc, 0xFFE);
+    }
+
+    #[test]
+    fn chip8e_waits_at_the_end_of_memory() {
+        for opcode in [0x00ED, 0x0151, 0xF14F] {
+            let mut machine = Machine::new(&[], Quirks::default());
+            machine.cpu.platform = Platform::Chip8E;
+            machine.cpu.dly_timer = 5;
+            machine.cpu.v_reg[1] = 5;
+            machine.memory.borrow_mut()[0xFFE..].copy_from_slice(&u16::to_be_bytes(opcode));
+            machine.cpu.pc = 0xFFE;
+
+            machine.step();
+            assert_eq!(machine.cpu.pc, 0xFFE, "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn add_to_index_fx1e() {
+        assert_eq!(Machine::run(&[0xA100, 0x6120, 0xF11E]).cpu.idx, 0x120);
+    }
+
+    #[test]
+    fn font_character_fx29() {
+        assert_eq!(Machine::run(&[0x610A, 0xF129]).cpu.idx, 50);
+
+        let mut machine = Machine::new(&[0x610A, 0xF129], Quirks::cosmac_vip());
+        machine.cpu.font_address = 0x050;
+        machine.step_n(2);
+        assert_eq!(machine.cpu.idx, 0x050 + 50);
+    }
+
+    #[test]
+    fn decimal_digits_fx33() {
+        let machine = Machine::run(&[0x61FE, 0xA300, 0xF133]);
+        assert_eq!(machine.memory.borrow()[0x300..0x303], [2, 5, 4]);
+    }
+
+    #[test]
+    fn store_and_load_fx55_fx65() {
+        let program = [0x6011, 0x6122, 0x6233, 0xA300, 0xF255, 0xA300, 0x6000, 0xF165];
+
+        let mut vip = Machine::new(&program, Quirks::cosmac_vip());
+        vip.step_n(5);
+        assert_eq!(vip.memory.borrow()[0x300..0x304], [0x11, 0x22, 0x33, 0x00]);
+        assert_eq!(vip.cpu.idx, 0x303);
+        vip.step_n(3);
+        assert_eq!(vip.cpu.v_reg[0], 0x11);
+        assert_eq!(vip.cpu.idx, 0x302);
+
+        let mut schip = Machine::new(&program, Quirks::schip());
+        schip.step_n(8);
+        assert_eq!(schip.cpu.v_reg[0], 0x11);
+        assert_eq!(schip.cpu.idx, 0x300);
+    }
 }
@@ -0,0 +1,64 @@
+use std::collections::BTreeSet;
+
+use super::CpuState;
+
+/// One return address on the stack
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StackFrame {
+    pub return_address: u16,
+    /// What the `2NNN` just before the return address called, None if it's something else
+    pub subroutine: Option<u16>,
+}
+
+/// Breakpoints, pausing and single stepping
+#[derive(Clone, Debug, Default)]
+pub struct Debugger {
+    pub breakpoints: BTreeSet<u16>,
+    pub paused: bool,
+    /// Where `run_to` stops, a breakpoint that goes away once it's hit
+    run_to: Option<u16>,
+    /// Set when running again, so the breakpoint the CPU is sitting on doesn't stop it at once
+    resuming: bool,
+    /// The registers before the last single step
+    pub before_step: Option<CpuState>,
+}
+
+impl Debugger {
+    /// Whether to stop before running the instruction at `pc`, pausing if so
+    pub fn should_break(&mut self, pc: u16) -> bool {
+        if std::mem::take(&mut self.resuming) || self.breakpoints.is_empty() && self.run_to.is_none() {
+            return false;
+        }
+
+        if self.breakpoints.contains(&pc) || self.run_to == Some(pc) {
+            self.paused = true;
+            self.run_to = None;
+            return true;
+        }
+
+        false
+    }
+
+    pub fn toggle_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.remove(&address) {
+            self.breakpoints.insert(address);
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.run_to = None;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.resuming = true;
+        self.before_step = None;
+    }
+
+    /// Runs until the instruction at `address`, or an earlier breakpoint
+    pub fn run_to(&mut self, address: u16) {
+        self.resume();
+        self.run_to = Some(address);
+    }
+}
@@ -0,0 +1,207 @@
+//! Turns memory back into instructions for the debugger, in the usual CHIP-8 assembler
+//! mnemonics
+
+use std::collections::BTreeMap;
+
+use super::cpu::Instruction;
+use super::platform::Platform;
+use super::quirks::Quirks;
+
+/// One decoded instruction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub address: u16,
+    pub opcode: u16,
+    /// In bytes, MEGA-CHIP's `01NN` takes up two words
+    pub length: u16,
+    pub text: String,
+    /// Where a jump or call goes
+    pub target: Option<Target>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Jump(u16),
+    Call(u16),
+}
+
+impl Target {
+    pub fn address(&self) -> u16 {
+        match self {
+            Target::Jump(address) | Target::Call(address) => *address,
+        }
+    }
+}
+
+/// Decodes `code`, which was read from `start`, one instruction after another. Addresses
+/// `name` knows are written as names.
+pub fn disassemble(
+    code: &[u8],
+    start: u16,
+    platform: Platform,
+    quirks: Quirks,
+    name: &dyn Fn(u16) -> Option<String>,
+) -> Vec<Line> {
+    let word = |offset: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([*code.get(offset)?, *code.get(offset + 1)?]))
+    };
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+
+    while let Some(opcode) = word(offset) {
+        let address = start.wrapping_add(offset as u16);
+        let long_index = platform == Platform::MegaChip && opcode & 0xFF00 == 0x0100;
+
+        let line = match word(offset + 2).filter(|_| long_index) {
+            Some(low) => {
+                let index = ((opcode as u32) & 0xFF) << 16 | low as u32;
+                Line {
+                    address,
+                    opcode,
+                    length: 4,
+                    text: format!("LD I, {}", address_text(index, name)),
+                    target: None,
+                }
+            }
+            None => Line {
+                address,
+                opcode,
+                length: 2,
+                text: mnemonic(opcode, platform, quirks, name),
+                target: target(opcode),
+            },
+        };
+
+        offset += line.length as usize;
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Names for the jump and call targets among `lines`, for addresses without a better one
+pub fn auto_labels(lines: &[Line]) -> BTreeMap<u16, String> {
+    let mut labels = BTreeMap::new();
+
+    for target in lines.iter().filter_map(|line| line.target) {
+        let label = match target {
+            Target::Call(address) => format!("sub_{:03X}", address),
+            Target::Jump(address) => format!("L{:03X}", address),
+        };
+        // a call says more than a jump
+        let entry = labels.entry(target.address()).or_insert_with(|| label.clone());
+        if matches!(target, Target::Call(_)) {
+            *entry = label;
+        }
+    }
+
+    labels
+}
+
+fn target(opcode: u16) -> Option<Target> {
+    match opcode & 0xF000 {
+        0x1000 => Some(Target::Jump(opcode & 0xFFF)),
+        0x2000 => Some(Target::Call(opcode & 0xFFF)),
+        _ => None,
+    }
+}
+
+fn address_text(address: u32, name: &dyn Fn(u16) -> Option<String>) -> String {
+    u16::try_from(address)
+        .ok()
+        .and_then(name)
+        .unwrap_or_else(|| format!("0x{:03X}", address))
+}
+
+/// The instruction as assembler, anything that isn't one comes out as a data word. The quirks
+/// decide which register `BNNN` adds.
+pub fn mnemonic(opcode: u16, platform: Platform, quirks: Quirks, name: &dyn Fn(u16) -> Option<String>) -> String {
+    let instr = Instruction::parse_u16(opcode);
+    let (x, y, n, nn) = (opcode >> 8 & 0xF, opcode >> 4 & 0xF, opcode & 0xF, opcode & 0xFF);
+    let nnn = address_text((opcode & 0xFFF) as u32, name);
+
+    let platform_specific = match (platform, instr.pattern()) {
+        (Platform::Hires, "0230") => Some("CLS".to_string()),
+        (Platform::Chip8X, "02A0") => Some("BGCOL".to_string()),
+        (Platform::Chip8X, "5XY1") => Some(format!("ADDN V{:X}, V{:X}", x, y)),
+        (Platform::Chip8X, "BNNN") if n == 0 => Some(format!("COLZ V{:X}, V{:X}", x, y)),
+        (Platform::Chip8X, "BNNN") => Some(format!("COL V{:X}, V{:X}, {}", x, y, n)),
+        (Platform::Chip8X, "EXF2") => Some(format!("SKP2 V{:X}", x)),
+        (Platform::Chip8X, "EXF5") => Some(format!("SKNP2 V{:X}", x)),
+        (Platform::Chip8X, "FXF8") => Some(format!("OUT V{:X}", x)),
+        (Platform::Chip8X, "FXFB") => Some(format!("IN V{:X}", x)),
+        (Platform::Chip8E, "00ED") => Some("STOP".to_string()),
+        (Platform::Chip8E, "00F2") => Some("NOP".to_string()),
+        (Platform::Chip8E, "0151") => Some("WAITD".to_string()),
+        (Platform::Chip8E, "0188") => Some("SKIP".to_string()),
+        (Platform::Chip8E, "5XY1") => Some(format!("SGT V{:X}, V{:X}", x, y)),
+        (Platform::Chip8E, "5XY2") => Some(format!("LD [I], V{:X}-V{:X}", x, y)),
+        (Platform::Chip8E, "5XY3") => Some(format!("LD V{:X}-V{:X}, [I]", x, y)),
+        (Platform::Chip8E, "BNNN") if x == 0xB => Some(format!("JB 0x{:02X}", nn)),
+        (Platform::Chip8E, "BNNN") if x == 0xF => Some(format!("JF 0x{:02X}", nn)),
+        (Platform::Chip8E, "FX03") => Some(format!("OUT V{:X}", x)),
+        (Platform::Chip8E, "FX1B") => Some(format!("SKIP V{:X}", x)),
+        (Platform::Chip8E, "FX4F") => Some(format!("DELAY V{:X}", x)),
+        (Platform::Chip8E, "FXE3" | "FXE7") => Some(format!("IN V{:X}", x)),
+        (Platform::MegaChip, "0010") => Some("MEGAOFF".to_string()),
+        (Platform::MegaChip, "0011") => Some("MEGAON".to_string()),
+        (Platform::MegaChip, "00BN") => Some(format!("SCU {}", n)),
+        (Platform::MegaChip, "00CN") => Some(format!("SCD {}", n)),
+        (Platform::MegaChip, "00FB") => Some("SCR".to_string()),
+        (Platform::MegaChip, "00FC") => Some("SCL".to_string()),
+        (Platform::MegaChip, "02NN") => Some(format!("LDPAL {}", nn)),
+        (Platform::MegaChip, "03NN") => Some(format!("SPRW {}", nn)),
+        (Platform::MegaChip, "04NN") => Some(format!("SPRH {}", nn)),
+        (Platform::MegaChip, "05NN") => Some(format!("ALPHA 0x{:02X}", nn)),
+        (Platform::MegaChip, "060N") => Some(format!("DIGISND {}", n)),
+        (Platform::MegaChip, "0700") => Some("STOPSND".to_string()),
+        (Platform::MegaChip, "080N") => Some(format!("BMODE {}", n)),
+        (Platform::MegaChip, "09NN") => Some(format!("CCOL {}", nn)),
+        _ => None,
+    };
+    if let Some(text) = platform_specific {
+        return text;
+    }
+
+    match instr.pattern() {
+        "00E0" => "CLS".to_string(),
+        "00EE" => "RET".to_string(),
+        "0NNN" if opcode == 0 => "NOP".to_string(),
+        "0NNN" => format!("SYS {}", nnn),
+        "1NNN" => format!("JP {}", nnn),
+        "2NNN" => format!("CALL {}", nnn),
+        "3XNN" => format!("SE V{:X}, 0x{:02X}", x, nn),
+        "4XNN" => format!("SNE V{:X}, 0x{:02X}", x, nn),
+        "5XY0" => format!("SE V{:X}, V{:X}", x, y),
+        "6XNN" => format!("LD V{:X}, 0x{:02X}", x, nn),
+        "7XNN" => format!("ADD V{:X}, 0x{:02X}", x, nn),
+        "8XY0" => format!("LD V{:X}, V{:X}", x, y),
+        "8XY1" => format!("OR V{:X}, V{:X}", x, y),
+        "8XY2" => format!("AND V{:X}, V{:X}", x, y),
+        "8XY3" => format!("XOR V{:X}, V{:X}", x, y),
+        "8XY4" => format!("ADD V{:X}, V{:X}", x, y),
+        "8XY5" => format!("SUB V{:X}, V{:X}", x, y),
+        "8XY6" => format!("SHR V{:X}, V{:X}", x, y),
+        "8XY7" => format!("SUBN V{:X}, V{:X}", x, y),
+        "8XYE" => format!("SHL V{:X}, V{:X}", x, y),
+        "9XY0" => format!("SNE V{:X}, V{:X}", x, y),
+        "ANNN" => format!("LD I, {}", nnn),
+        "BNNN" if quirks.jump_vx => format!("JP V{:X}, {}", x, nnn),
+        "BNNN" => format!("JP V0, {}", nnn),
+        "CXNN" => format!("RND V{:X}, 0x{:02X}", x, nn),
+        "DXYN" => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        "EX9E" => format!("SKP V{:X}", x),
+        "EXA1" => format!("SKNP V{:X}", x),
+        "FX07" => format!("LD V{:X}, DT", x),
+        "FX0A" => format!("LD V{:X}, K", x),
+        "FX15" => format!("LD DT, V{:X}", x),
+        "FX18" => format!("LD ST, V{:X}", x),
+        "FX1E" => format!("ADD I, V{:X}", x),
+        "FX29" => format!("LD F, V{:X}", x),
+        "FX33" => format!("LD B, V{:X}", x),
+        "FX55" => format!("LD [I], V{:X}", x),
+        "FX65" => format!("LD V{:X}, [I]", x),
+        _ => format!("DW 0x{:04X}", opcode),
+    }
+}
@@ -0,0 +1,195 @@
+use std::ops::Range;
+
+use super::megachip::MegaChipScreen;
+
+/// Where the CPU draws its sprites
+pub trait Display {
+    fn clear(&mut self);
+
+    /// Draws sprite at coords, returns true if any pixels were erased.
+    /// With `clip` set, pixels past the screen edges are dropped instead of wrapping.
+    fn draw(&mut self, coords: (u8, u8), sprite: &[u8], clip: bool) -> bool;
+
+    /// CHIP-8X `02A0`, steps the background through blue, black, green and red.
+    /// Displays without color ignore it.
+    fn cycle_background(&mut self) {}
+
+    /// CHIP-8X `BXYN`, sets the color lit pixels take in an area, in pixels.
+    /// Displays without color ignore it.
+    fn set_foreground(&mut self, _columns: Range<usize>, _rows: Range<usize>, _color: u8) {}
+
+    /// MEGA-CHIP `0011` and `0010`, switches between the 256x192 color screen and the plain one.
+    /// Displays without color ignore it.
+    fn set_megachip(&mut self, _enabled: bool) {}
+
+    /// The color screen while MEGA-CHIP mode is on
+    fn megachip(&mut self) -> Option<&mut MegaChipScreen> {
+        None
+    }
+}
+
+/// A screen's worth of pixels, top row first, 255 for lit pixels
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    /// The color of every pixel, lit or not, on platforms with color
+    colors: Option<Vec<[u8; 3]>>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+            colors: None,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    pub fn is_lit(&self, x: usize, y: usize) -> bool {
+        self.get(x, y) != 0
+    }
+
+    pub fn row(&self, y: usize) -> &[u8] {
+        &self.pixels[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.pixels.chunks_exact(self.width)
+    }
+
+    /// Every pixel, row after row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+    }
+
+    /// The pixel's own color, None when the frontend's palette applies
+    pub fn color(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+        self.colors.as_ref().map(|colors| colors[y * self.width + x])
+    }
+
+    pub fn colors(&self) -> Option<&[[u8; 3]]> {
+        self.colors.as_deref()
+    }
+
+    /// Fills `out` with RGBA bytes, top row first. Pixels without a color of their own take the
+    /// foreground when lit and the background when not.
+    pub fn write_rgba(&self, out: &mut Vec<u8>, foreground: [u8; 3], background: [u8; 3]) {
+        out.clear();
+        out.reserve(self.pixels.len() * 4);
+
+        for (i, &pixel) in self.pixels.iter().enumerate() {
+            let [r, g, b] = match &self.colors {
+                Some(colors) => colors[i],
+                None if pixel != 0 => foreground,
+                None => background,
+            };
+            out.extend_from_slice(&[r, g, b, 255]);
+        }
+    }
+
+    /// Gives every pixel its own color, row after row
+    pub fn set_colors(&mut self, colors: Option<Vec<[u8; 3]>>) {
+        debug_assert!(colors.as_ref().is_none_or(|colors| colors.len() == self.pixels.len()));
+        self.colors = colors;
+    }
+}
+
+impl Default for Framebuffer {
+    /// The plain CHIP-8 64x32 screen
+    fn default() -> Self {
+        Self::new(64, 32)
+    }
+}
+
+/// A display that only keeps the pixels, for tests and headless runs
+pub struct FramebufferDisplay {
+    pub pixels: Framebuffer,
+}
+
+impl FramebufferDisplay {
+    pub fn new() -> Self {
+        Self {
+            pixels: Framebuffer::default(),
+        }
+    }
+}
+
+impl Default for FramebufferDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for FramebufferDisplay {
+    fn clear(&mut self) {
+        self.pixels.clear();
+    }
+
+    fn draw(&mut self, coords: (u8, u8), sprite: &[u8], clip: bool) -> bool {
+        xor_sprite(&mut self.pixels, coords, sprite, clip)
+    }
+}
+
+/// XORs a sprite into a framebuffer, returns true if any pixels were erased.
+/// The starting position always wraps, the rest wraps unless `clip` is set.
+pub fn xor_sprite(pixels: &mut Framebuffer, coords: (u8, u8), sprite: &[u8], clip: bool) -> bool {
+    let (width, height) = (pixels.width(), pixels.height());
+    let origin_x = coords.0 as usize % width;
+    let origin_y = coords.1 as usize % height;
+
+    let mut erased = false;
+
+    for (row, sprite_byte) in sprite.iter().enumerate() {
+        let y = origin_y + row;
+
+        if clip && y >= height {
+            break;
+        }
+
+        let y = y % height;
+
+        for col in 0..8 {
+            let x = origin_x + col;
+
+            if clip && x >= width {
+                break;
+            }
+
+            let x = x % width;
+
+            if (sprite_byte >> (7 - col)) & 0x1 == 1 {
+                if pixels.is_lit(x, y) {
+                    erased = true;
+                    pixels.set(x, y, 0);
+                } else {
+                    pixels.set(x, y, 255);
+                }
+            }
+        }
+    }
+
+    erased
+}
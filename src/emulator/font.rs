@@ -0,0 +1,159 @@
+/// Bytes in a font: sixteen 4x5 hex digits, five rows each
+pub const FONT_SIZE: usize = 0x50;
+
+/// Where the font goes unless configured otherwise
+pub const DEFAULT_FONT_ADDRESS: u16 = 0x000;
+
+/// The hex digits `FX29` points at, as drawn by the interpreter a ROM was written for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Font {
+    pub glyphs: [u8; FONT_SIZE],
+}
+
+impl Font {
+    pub const NAMES: [&'static str; 5] = ["vip", "dream6800", "eti660", "schip", "octo"];
+
+    /// Looks up a built-in font by the name used on the command line and in config files
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "vip" | "cosmac-vip" => Some(Self::cosmac_vip()),
+            "dream6800" => Some(Self::dream6800()),
+            "eti660" => Some(Self::eti660()),
+            "schip" => Some(Self::schip()),
+            "octo" => Some(Self::octo()),
+            _ => None,
+        }
+    }
+
+    /// Name of the built-in font these glyphs match, if any. SCHIP and Octo share their small
+    /// font, so that one is reported as Octo.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        ["octo", "vip", "dream6800", "eti660"]
+            .into_iter()
+            .find(|name| Font::preset(name) == Some(*self))
+    }
+
+    /// A built-in font by name, or else the file at that path holding the 80 bytes of a font
+    pub fn from_name_or_file(name: &str) -> Result<Self, String> {
+        if let Some(font) = Font::preset(name) {
+            return Ok(font);
+        }
+
+        let bytes = std::fs::read(name).map_err(|err| {
+            format!("font {:?} is not one of {} and couldn't be read: {}", name, Font::NAMES.join(", "), err)
+        })?;
+
+        let glyphs = bytes
+            .try_into()
+            .map_err(|bytes: Vec<u8>| format!("font file {} is {} bytes, expected {}", name, bytes.len(), FONT_SIZE))?;
+
+        Ok(Font { glyphs })
+    }
+
+    /// The font in the COSMAC VIP's interpreter
+    pub fn cosmac_vip() -> Self {
+        Self {
+            glyphs: [
+                0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+                0x60, 0x20, 0x20, 0x20, 0x70, // 1
+                0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+                0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+                0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+                0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+                0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+                0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+                0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+                0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+                0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+                0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+                0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+                0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+                0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+                0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+            ],
+        }
+    }
+
+    /// CHIPOS on the DREAM 6800, three pixels wide
+    pub fn dream6800() -> Self {
+        Self {
+            glyphs: [
+                0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+                0x40, 0x40, 0x40, 0x40, 0x40, // 1
+                0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+                0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+                0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+                0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+                0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+                0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+                0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+                0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+                0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+                0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+                0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+                0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+                0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+                0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+            ],
+        }
+    }
+
+    /// The ETI-660 monitor, three pixels wide with lowercase b and d
+    pub fn eti660() -> Self {
+        Self {
+            glyphs: [
+                0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+                0x20, 0x20, 0x20, 0x20, 0x20, // 1
+                0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+                0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+                0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+                0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+                0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+                0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+                0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+                0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+                0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+                0x80, 0x80, 0xE0, 0xA0, 0xE0, // B
+                0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+                0x20, 0x20, 0xE0, 0xA0, 0xE0, // D
+                0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+                0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+            ],
+        }
+    }
+
+    /// The small font of SUPER-CHIP on the HP48
+    pub fn schip() -> Self {
+        Self::octo()
+    }
+
+    /// Octo's font, the one most modern ROMs are drawn against
+    pub fn octo() -> Self {
+        Self {
+            glyphs: [
+                0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+                0x20, 0x60, 0x20, 0x20, 0x70, // 1
+                0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+                0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+                0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+                0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+                0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+                0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+                0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+                0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+                0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+                0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+                0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+                0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+                0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+                0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+            ],
+        }
+    }
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self::octo()
+    }
+}
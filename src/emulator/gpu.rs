@@ -1,120 +1,155 @@
-use crate::{DisplayBuffer, DebugInfo};
-use fixedstep::FixedStep;
-use std::{
-    cell::RefCell,
-    rc::Rc,
-    sync::{Arc, Mutex}, fmt::Debug,
-};
+use super::display::{xor_sprite, Display, Framebuffer};
+use super::megachip::MegaChipScreen;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Background colors of the VP-590 color board, stepped through by `02A0`
+const CHIP8X_BACKGROUNDS: [[u8; 3]; 4] = [[0x00, 0x00, 0x80], [0x00, 0x00, 0x00], [0x00, 0x80, 0x00], [0x80, 0x00, 0x00]];
+
+/// Foreground colors of the VP-590 color board, picked by `BXYN`
+const CHIP8X_FOREGROUNDS: [[u8; 3]; 8] = [
+    [0x00, 0x00, 0x00],
+    [0xFF, 0x00, 0x00],
+    [0x00, 0x00, 0xFF],
+    [0xFF, 0x00, 0xFF],
+    [0x00, 0xFF, 0x00],
+    [0xFF, 0xFF, 0x00],
+    [0x00, 0xFF, 0xFF],
+    [0xFF, 0xFF, 0xFF],
+];
+
+/// The color board's colors are set for 8 pixel wide columns, one pixel row at a time
+const COLOR_ZONE_WIDTH: usize = 8;
+
+/// What the CHIP-8X color board has set
+struct ColorBoard {
+    background: usize,
+    /// Foreground color per 8 pixel column of every row, top row first
+    zones: Vec<u8>,
+}
 
+/// The emulator's screen. Completed frames are handed to the `FrameSink`s by the emulator.
 pub struct GPU {
-    memory_ref: Rc<RefCell<[u8; 4096]>>,
-    local_display_buffer: Arc<Mutex<[[u8; 64]; 32]>>,
-    display_buffer: Arc<Mutex<DisplayBuffer>>,
-    debug_info: Arc<Mutex<DebugInfo>>,
+    pixels: Framebuffer,
+    /// Only on the CHIP-8X
+    color_board: Option<ColorBoard>,
+    /// Takes over from `pixels` while MEGA-CHIP mode is on
+    megachip: Option<Box<MegaChipScreen>>,
+    /// Time spent in `draw` since the emulator last collected its stats
+    pub draw_time: Duration,
 }
 
 impl GPU {
-    pub fn new(
-        memory_ref: Rc<RefCell<[u8; 4096]>>,
-        refresh_rate: f32,
-        display_buffer: Arc<Mutex<DisplayBuffer>>,
-        debug_info: Arc<Mutex<DebugInfo>>,
-    ) -> GPU {
-        let gpu = GPU {
-            memory_ref,
-            local_display_buffer: Arc::new(Mutex::new([[0; 64]; 32])),
-            display_buffer,
-            debug_info,
-        };
-
-        gpu.display_refresh_thread(
-            refresh_rate,
-            gpu.local_display_buffer.clone(),
-            gpu.display_buffer.clone(),
-        );
-
-        gpu
+    pub fn new() -> GPU {
+        GPU {
+            pixels: Framebuffer::default(),
+            color_board: None,
+            megachip: None,
+            draw_time: Duration::ZERO,
+        }
     }
 
-    /// Separate thread for refreshing the display
-    pub fn display_refresh_thread(
-        &self,
-        refresh_rate: f32,
-        local_display_buffer: Arc<Mutex<[[u8; 64]; 32]>>,
-        display_buffer: Arc<Mutex<DisplayBuffer>>,
-    ) {
-        let mut timer = FixedStep::start(refresh_rate as f64).unlimit();
-        let display_buffer = display_buffer.clone();
-        let local_display_buffer = local_display_buffer.clone();
-        let debug_info = self.debug_info.clone();
-
-        let mut last_frame = std::time::Instant::now();
-
-        //TODO: error detection?
-        std::thread::spawn(move || {
-            loop {
-                if timer.update() {
-                    let frame_time = last_frame.elapsed().as_secs_f32() * 1000.0;
-                    debug_info.lock().unwrap().frame_time = frame_time;
-                    last_frame = std::time::Instant::now();
-
-                    let mut display_buffer = display_buffer.lock().unwrap();
-
-                    // set inactive buffer to local buffer
-                    display_buffer
-                        .set_buffer(-2, *local_display_buffer.lock().unwrap());
-
-                    // swap buffers
-                    display_buffer.swap_buffers();
-                }
-            }
+    /// Switches to a new screen size, cleared. With `color` the CHIP-8X color board is fitted.
+    pub fn configure(&mut self, width: usize, height: usize, color: bool) {
+        self.pixels = Framebuffer::new(width, height);
+        self.color_board = color.then(|| ColorBoard {
+            background: 0,
+            // red, the color board's power-on foreground
+            zones: vec![1; width.div_ceil(COLOR_ZONE_WIDTH) * height],
         });
+        self.megachip = None;
+    }
+
+    /// The pixels drawn so far, with their colors on the CHIP-8X. In MEGA-CHIP mode the frame
+    /// the ROM last finished.
+    pub fn framebuffer(&self) -> Framebuffer {
+        if let Some(screen) = &self.megachip {
+            return screen.frame().clone();
+        }
+
+        let mut framebuffer = self.pixels.clone();
+
+        if let Some(board) = &self.color_board {
+            let columns = self.pixels.width().div_ceil(COLOR_ZONE_WIDTH);
+            let background = CHIP8X_BACKGROUNDS[board.background];
+
+            let colors = (0..self.pixels.height())
+                .flat_map(|y| (0..self.pixels.width()).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    if self.pixels.is_lit(x, y) {
+                        CHIP8X_FOREGROUNDS[board.zones[y * columns + x / COLOR_ZONE_WIDTH] as usize & 7]
+                    } else {
+                        background
+                    }
+                })
+                .collect();
+            framebuffer.set_colors(Some(colors));
+        }
+
+        framebuffer
     }
 
     pub fn clear_screen(&mut self) {
-        *self.local_display_buffer.lock().unwrap() = [[0; 64]; 32];
+        self.pixels.clear();
     }
 
-    /// Draws sprite at coords, returns true if any pixels were erased
-    pub fn draw(&mut self, mut coords: (u8, u8), sprite: &[u8]) -> bool {
-        // if coords are over the screen, wrap them
-        coords.0 = coords.0 % 64;
-        coords.1 = 31 - (coords.1 % 32); // flip y axis
+    /// Draws sprite at coords, returns true if any pixels were erased.
+    /// With `clip` set, pixels past the screen edges are dropped instead of wrapping.
+    pub fn draw(&mut self, coords: (u8, u8), sprite: &[u8], clip: bool) -> bool {
+        let start = Instant::now();
 
-        let mut erased = false;
+        let erased = xor_sprite(&mut self.pixels, coords, sprite, clip);
 
-        for y in 0..sprite.len() {
-            let mut sprite_byte = sprite[y];
+        self.draw_time += start.elapsed();
+        erased
+    }
+}
 
-            for x in 0..8 {
-                let sprite_pixel = (sprite_byte >> (7 - x)) & 0x1;
+impl Default for GPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-                let x = (coords.0 + x) % 64;
-                let y = (coords.1 - y as u8) % 32;
+impl Display for GPU {
+    fn clear(&mut self) {
+        self.clear_screen();
+    }
 
-                if x >= 64 {
-                    continue;
-                }
+    fn draw(&mut self, coords: (u8, u8), sprite: &[u8], clip: bool) -> bool {
+        GPU::draw(self, coords, sprite, clip)
+    }
 
-                if y >= 32 {
-                    continue;
-                }
+    fn cycle_background(&mut self) {
+        if let Some(board) = &mut self.color_board {
+            board.background = (board.background + 1) % CHIP8X_BACKGROUNDS.len();
+        }
+    }
 
-                let mut display_buffer = self.local_display_buffer.lock().unwrap();
+    fn set_megachip(&mut self, enabled: bool) {
+        let (width, height) = (self.pixels.width(), self.pixels.height());
+        self.configure(width, height, false);
 
-                let pixel = display_buffer[y as usize][x as usize];
+        if enabled {
+            self.megachip = Some(Box::default());
+        }
+    }
 
-                if sprite_pixel == 1 {
-                    if pixel != 0 {
-                        erased = true;
-                        display_buffer[y as usize][x as usize] = 0;
-                    } else {
-                        display_buffer[y as usize][x as usize] = 255;
-                    }
+    fn megachip(&mut self) -> Option<&mut MegaChipScreen> {
+        self.megachip.as_deref_mut()
+    }
+
+    fn set_foreground(&mut self, columns: Range<usize>, rows: Range<usize>, color: u8) {
+        let (width, height) = (self.pixels.width(), self.pixels.height());
+
+        if let Some(board) = &mut self.color_board {
+            let zone_columns = width.div_ceil(COLOR_ZONE_WIDTH);
+
+            for y in rows.map(|y| y % height) {
+                for x in columns.clone().step_by(COLOR_ZONE_WIDTH).map(|x| x % width) {
+                    board.zones[y * zone_columns + x / COLOR_ZONE_WIDTH] = color & 7;
                 }
             }
         }
-
-        erased
     }
 }
@@ -0,0 +1,288 @@
+use super::display::Framebuffer;
+
+/// Size of the screen while MEGA-CHIP mode is on
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 192;
+
+/// How `DXYN` mixes a sprite's colors into the screen, set by `080N`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The palette's own alpha
+    #[default]
+    Normal,
+    /// A quarter of the palette's alpha
+    Alpha25,
+    /// Half the palette's alpha
+    Alpha50,
+    Add,
+    Multiply,
+}
+
+impl BlendMode {
+    pub fn from_nibble(n: u8) -> Option<BlendMode> {
+        match n {
+            0 => Some(BlendMode::Normal),
+            1 => Some(BlendMode::Alpha25),
+            2 => Some(BlendMode::Alpha50),
+            3 => Some(BlendMode::Add),
+            4 => Some(BlendMode::Multiply),
+            _ => None,
+        }
+    }
+
+    fn blend(&self, source: [u8; 4], dest: [u8; 4]) -> [u8; 4] {
+        let alpha = match self {
+            BlendMode::Normal => source[3] as u32,
+            BlendMode::Alpha25 => source[3] as u32 / 4,
+            BlendMode::Alpha50 => source[3] as u32 / 2,
+            BlendMode::Add => return opaque([0, 1, 2].map(|i| source[i].saturating_add(dest[i]))),
+            BlendMode::Multiply => return opaque([0, 1, 2].map(|i| (source[i] as u32 * dest[i] as u32 / 255) as u8)),
+        };
+
+        opaque([0, 1, 2].map(|i| ((source[i] as u32 * alpha + dest[i] as u32 * (255 - alpha)) / 255) as u8))
+    }
+}
+
+fn opaque(rgb: [u8; 3]) -> [u8; 4] {
+    [rgb[0], rgb[1], rgb[2], 255]
+}
+
+/// The 256x192 color screen. Sprites are drawn into a back buffer that `00E0` shows and
+/// clears, so a frame only appears once the ROM has finished it.
+pub struct MegaChipScreen {
+    /// RGBA, index 0 is transparent
+    palette: [[u8; 4]; 256],
+    /// Set by `03NN`, 0 means 256
+    pub sprite_width: usize,
+    /// Set by `04NN`, 0 means 256
+    pub sprite_height: usize,
+    pub blend_mode: BlendMode,
+    /// Drawing over a pixel last drawn in this palette index sets VF
+    pub collision_color: u8,
+    /// Fades the whole screen, 255 is fully visible
+    pub screen_alpha: u8,
+    /// What is being drawn, RGBA, top row first
+    back: Vec<[u8; 4]>,
+    /// The palette index last drawn at every pixel
+    indices: Vec<u8>,
+    /// What the last `00E0` showed
+    front: Framebuffer,
+}
+
+impl MegaChipScreen {
+    pub fn new() -> Self {
+        let mut palette = [[0; 4]; 256];
+        // the font and other 1 bit sprites are drawn in white
+        palette[255] = [0xFF; 4];
+
+        Self {
+            palette,
+            sprite_width: 0,
+            sprite_height: 0,
+            blend_mode: BlendMode::Normal,
+            collision_color: 0,
+            screen_alpha: 255,
+            back: vec![[0, 0, 0, 255]; WIDTH * HEIGHT],
+            indices: vec![0; WIDTH * HEIGHT],
+            front: Framebuffer::new(WIDTH, HEIGHT),
+        }
+    }
+
+    /// `02NN`, sets palette entries 1 onwards from ARGB colors, 4 bytes each
+    pub fn load_palette(&mut self, colors: &[u8]) {
+        for (entry, argb) in self.palette[1..].iter_mut().zip(colors.chunks_exact(4)) {
+            *entry = [argb[1], argb[2], argb[3], argb[0]];
+        }
+    }
+
+    pub fn palette(&self, index: u8) -> [u8; 4] {
+        self.palette[index as usize]
+    }
+
+    /// Width and height of the sprites `draw` takes
+    pub fn sprite_size(&self) -> (usize, usize) {
+        let size = |value: usize| if value == 0 { 256 } else { value };
+        (size(self.sprite_width), size(self.sprite_height))
+    }
+
+    /// Draws a sprite of one palette index per byte, `sprite_size` of them row after row.
+    /// Pixels past the edges are dropped. Returns true if it covered the collision color.
+    pub fn draw(&mut self, coords: (u8, u8), sprite: &[u8]) -> bool {
+        let (width, _) = self.sprite_size();
+        let mut collided = false;
+
+        for (row, colors) in sprite.chunks(width).enumerate() {
+            for (col, &index) in colors.iter().enumerate() {
+                if index != 0 {
+                    collided |= self.plot(coords.0 as usize + col, coords.1 as usize + row, index);
+                }
+            }
+        }
+
+        collided
+    }
+
+    /// Draws a plain 8 pixel wide sprite in palette index 255, for the font
+    pub fn draw_glyph(&mut self, coords: (u8, u8), sprite: &[u8]) -> bool {
+        let mut collided = false;
+
+        for (row, byte) in sprite.iter().enumerate() {
+            for col in 0..8 {
+                if (byte >> (7 - col)) & 0x1 == 1 {
+                    collided |= self.plot(coords.0 as usize + col, coords.1 as usize + row, 255);
+                }
+            }
+        }
+
+        collided
+    }
+
+    fn plot(&mut self, x: usize, y: usize, index: u8) -> bool {
+        if x >= WIDTH || y >= HEIGHT {
+            return false;
+        }
+
+        let pixel = y * WIDTH + x;
+        // nothing drawn yet never collides
+        let collided = self.indices[pixel] != 0 && self.indices[pixel] == self.collision_color;
+
+        self.back[pixel] = self.blend_mode.blend(self.palette[index as usize], self.back[pixel]);
+        self.indices[pixel] = index;
+
+        collided
+    }
+
+    /// `00BN`
+    pub fn scroll_up(&mut self, rows: usize) {
+        self.shift(-(rows.min(HEIGHT) as isize) * WIDTH as isize);
+    }
+
+    /// `00CN`
+    pub fn scroll_down(&mut self, rows: usize) {
+        self.shift(rows.min(HEIGHT) as isize * WIDTH as isize);
+    }
+
+    /// `00FC`
+    pub fn scroll_left(&mut self, columns: usize) {
+        for row in 0..HEIGHT {
+            let range = row * WIDTH..(row + 1) * WIDTH;
+            self.back[range.clone()].rotate_left(columns);
+            self.back[range.end - columns..range.end].fill([0, 0, 0, 255]);
+            self.indices[range.clone()].rotate_left(columns);
+            self.indices[range.end - columns..range.end].fill(0);
+        }
+    }
+
+    /// `00FB`
+    pub fn scroll_right(&mut self, columns: usize) {
+        for row in 0..HEIGHT {
+            let range = row * WIDTH..(row + 1) * WIDTH;
+            self.back[range.clone()].rotate_right(columns);
+            self.back[range.start..range.start + columns].fill([0, 0, 0, 255]);
+            self.indices[range.clone()].rotate_right(columns);
+            self.indices[range.start..range.start + columns].fill(0);
+        }
+    }
+
+    /// Moves every pixel by `offset` places, filling the gap with black
+    fn shift(&mut self, offset: isize) {
+        let len = self.back.len();
+        let amount = offset.unsigned_abs();
+
+        if offset > 0 {
+            self.back.rotate_right(amount);
+            self.back[..amount].fill([0, 0, 0, 255]);
+            self.indices.rotate_right(amount);
+            self.indices[..amount].fill(0);
+        } else {
+            self.back.rotate_left(amount);
+            self.back[len - amount..].fill([0, 0, 0, 255]);
+            self.indices.rotate_left(amount);
+            self.indices[len - amount..].fill(0);
+        }
+    }
+
+    /// `00E0`, shows what was drawn and starts a new frame
+    pub fn present(&mut self) {
+        let alpha = self.screen_alpha as u32;
+        let colors: Vec<[u8; 3]> = self
+            .back
+            .iter()
+            .map(|rgba| [0, 1, 2].map(|i| (rgba[i] as u32 * alpha / 255) as u8))
+            .collect();
+
+        for (pixel, color) in colors.iter().enumerate() {
+            let lit = if *color == [0, 0, 0] { 0 } else { 255 };
+            self.front.set(pixel % WIDTH, pixel / WIDTH, lit);
+        }
+        self.front.set_colors(Some(colors));
+
+        self.back.fill([0, 0, 0, 255]);
+        self.indices.fill(0);
+    }
+
+    /// The screen as of the last `00E0`
+    pub fn frame(&self) -> &Framebuffer {
+        &self.front
+    }
+}
+
+impl Default for MegaChipScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A sound started by `060N`, 8 bit unsigned samples
+#[derive(Clone, Debug, PartialEq)]
+pub struct SamplePlayback {
+    pub samples: Vec<u8>,
+    /// Samples per second
+    pub rate: u32,
+    pub looping: bool,
+    /// Samples played so far
+    position: f64,
+}
+
+impl SamplePlayback {
+    /// Reads the sound at `data`: a 16 bit sample rate, a 24 bit length, and the samples
+    /// after 6 bytes of header
+    pub fn from_header(data: &[u8], looping: bool) -> SamplePlayback {
+        let rate = (data[0] as u32) << 8 | data[1] as u32;
+        let length = (data[2] as usize) << 16 | (data[3] as usize) << 8 | data[4] as usize;
+        let end = (6 + length).min(data.len());
+
+        SamplePlayback {
+            samples: data[6.min(end)..end].to_vec(),
+            rate,
+            looping,
+            position: 0.0,
+        }
+    }
+
+    /// The header's 24 bit length plus the header, the most `from_header` will look at
+    pub fn header_length(header: &[u8]) -> usize {
+        6 + ((header[2] as usize) << 16 | (header[3] as usize) << 8 | header[4] as usize)
+    }
+
+    pub fn finished(&self) -> bool {
+        !self.looping && self.position as usize >= self.samples.len()
+    }
+
+    /// Fills `out` with -1.0 to 1.0 samples at the output rate, silence once it has finished
+    pub fn fill(&mut self, out: &mut [f32], output_rate: u32) {
+        let step = self.rate as f64 / output_rate as f64;
+
+        for sample in out.iter_mut() {
+            if self.looping && self.position as usize >= self.samples.len() {
+                self.position = 0.0;
+            }
+
+            *sample = match self.samples.get(self.position as usize) {
+                Some(&value) => (value as f32 - 128.0) / 128.0,
+                None => 0.0,
+            };
+            self.position += step;
+        }
+    }
+}
@@ -1,79 +1,862 @@
+pub mod bus;
+use bus::{Bus, Memory};
+pub mod cartridge;
+pub mod display;
+use display::Framebuffer;
+pub mod font;
+use font::{Font, FONT_SIZE};
 pub mod gpu;
-use std::{cell::RefCell, rc::Rc, sync::{Arc, Mutex}};
+pub mod megachip;
+pub mod platform;
+use platform::Platform;
+use std::{cell::RefCell, rc::Rc, sync::{Arc, Mutex}, time::{Duration, Instant}};
 
 use gpu::GPU;
 
 pub mod cpu;
-use cpu::CPU;
+use cpu::{CpuError, CPU};
 
-use crate::{DisplayBuffer, DebugInfo};
+pub mod debugger;
+use debugger::{Debugger, StackFrame};
+
+pub mod disasm;
+
+pub mod movie;
+use movie::{Movie, MovieError, MovieState};
+
+pub mod quirks;
+use quirks::Quirks;
+
+pub mod rng;
+use rng::Rng;
+
+pub mod rom;
+use rom::{RomError, RomWarning, PROGRAM_START};
+
+pub mod sink;
+use sink::{Frame, FrameSink};
+
+use crate::romdb::RomInfo;
+
+pub mod stats;
+use stats::EmulatorStats;
+
+/// Picks the platform for a ROM from its SHA-1 and what the ROM file says about itself, see
+/// `Emulator::set_platform_lookup`
+pub type PlatformLookup = Box<dyn Fn(&str, Option<&RomInfo>) -> Option<Platform>>;
+
+pub mod symbols;
+use symbols::{SymbolError, Symbols};
+
+pub mod timing;
+use timing::{Speed, TimingModel};
+
+/// Real time the CPU may use per frame when running unlimited with real-time timers,
+/// the rest of the 60hz frame is left for the emulator thread's own work
+const REALTIME_FRAME_BUDGET: Duration = Duration::from_micros(15_000);
+
+/// Bytes of memory around the PC handed to the debugger each frame
+const CODE_WINDOW: u16 = 128;
+
+/// Bytes of memory from `DebugInfo::memory_address` handed to the memory viewer each frame
+const MEMORY_WINDOW: u16 = 256;
+
+use crate::DebugInfo;
+
+/// Requests sent from the frontend to the emulator thread
+pub enum EmulatorCommand {
+    /// Restart the ROM and record input from the first frame
+    StartRecording,
+    /// Stop recording and save the movie to the given path
+    StopRecording(String),
+    /// Load a movie from the given path and replay it from a fresh start
+    PlayMovie(String),
+    SetTiming(TimingModel),
+    /// With `realtime_timers` the frames stay at 60hz and only the CPU speeds up
+    SetSpeed { speed: Speed, realtime_timers: bool },
+    /// Load the ROM at the given path and start it, the current ROM keeps running if it fails.
+    /// A symbol file next to it with the `.sym` extension is loaded too.
+    LoadRom(String),
+    /// Load names for addresses from a symbol file
+    LoadSymbols(String),
+    Pause,
+    Resume,
+    /// Run one instruction and pause
+    Step,
+    /// Run until the instruction at the address, or an earlier breakpoint
+    RunTo(u16),
+    ToggleBreakpoint(u16),
+}
+
+/// Copy of the CPU registers, for frontends to display
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CpuState {
+    pub pc: u16,
+    pub idx: u32,
+    pub v_reg: [u8; 16],
+    pub stack: Vec<u16>,
+    pub dly_timer: u8,
+    pub snd_timer: u8,
+}
+
+impl CpuState {
+    /// The registers that differ in `after`, like `V3: 05 -> 07`. The PC is left out.
+    pub fn changes(&self, after: &CpuState) -> Vec<String> {
+        let mut changes: Vec<String> = (0..16)
+            .filter(|&i| self.v_reg[i] != after.v_reg[i])
+            .map(|i| format!("V{:X}: {:02X} -> {:02X}", i, self.v_reg[i], after.v_reg[i]))
+            .collect();
+
+        if self.idx != after.idx {
+            changes.push(format!("I: {:03X} -> {:03X}", self.idx, after.idx));
+        }
+        if self.dly_timer != after.dly_timer {
+            changes.push(format!("DT: {:02X} -> {:02X}", self.dly_timer, after.dly_timer));
+        }
+        if self.snd_timer != after.snd_timer {
+            changes.push(format!("ST: {:02X} -> {:02X}", self.snd_timer, after.snd_timer));
+        }
+        if self.stack != after.stack {
+            changes.push(format!("stack: {} -> {} deep", self.stack.len(), after.stack.len()));
+        }
+
+        changes
+    }
+}
 
 pub struct Emulator {
     cpu: Rc<RefCell<CPU>>,
-    memory: Rc<RefCell<[u8; 4096]>>,
+    memory: Rc<RefCell<Memory>>,
     gpu: Rc<RefCell<GPU>>,
     debug_info: Arc<Mutex<DebugInfo>>,
-    pub clock_speed: u32,
+    pub timing: TimingModel,
+    pub speed: Speed,
+    /// Keep the timers at 60hz and only change how much code runs per frame
+    pub realtime_timers: bool,
+    rom: Vec<u8>,
+    rom_sha1: String,
+    /// Settings saved in the ROM file, like an Octo cartridge's
+    rom_file_info: Option<RomInfo>,
+    seed: u64,
+    /// Machine cycles left in the current frame in COSMAC VIP timing, negative when the last
+    /// instruction overran the frame
+    cycle_budget: i32,
+    /// Fraction of an instruction left over when a speed multiplier doesn't divide evenly
+    instruction_carry: f64,
+    recording: Option<Movie>,
+    playback: Option<(Movie, usize)>,
+    stats: EmulatorStats,
+    /// Frames completed since the last reset
+    frame_number: u64,
+    sinks: Vec<Box<dyn FrameSink>>,
+    font: Font,
+    platform: Platform,
+    platform_lookup: Option<PlatformLookup>,
+    debugger: Debugger,
 }
 
 impl Emulator {
-    pub fn new(display_buffer: Arc<Mutex<DisplayBuffer>>, debug_info: Arc<Mutex<DebugInfo>>) -> Emulator {
-        let memory = Rc::new(RefCell::new([0; 4096]));
-        let gpu = Rc::new(RefCell::new(GPU::new(memory.clone(), 60.0, display_buffer, debug_info.clone())));
-        let cpu = Rc::new(RefCell::new(CPU::new(memory.clone(), gpu.clone())));
-        let clock_speed = 10;
-
-        // Font to memory
-        let font = [
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-        ];
-
-        memory.borrow_mut()[0x000..0x050].copy_from_slice(&font[0..0x050]);
-
-        Emulator {
+    pub fn new(debug_info: Arc<Mutex<DebugInfo>>) -> Emulator {
+        // Unseeded runs still need a seed so they can be recorded
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+
+        Emulator::with_seed(debug_info, seed)
+    }
+
+    /// Same as `new`, but `CXNN` produces the same sequence on every run
+    pub fn with_seed(debug_info: Arc<Mutex<DebugInfo>>, seed: u64) -> Emulator {
+        let memory = Rc::new(RefCell::new(Memory::new(Platform::default().memory_size())));
+        let gpu = Rc::new(RefCell::new(GPU::new()));
+        let cpu = Rc::new(RefCell::new(CPU::new(memory.clone(), gpu.clone(), seed)));
+
+        let emulator = Emulator {
             cpu,
             memory,
             gpu,
             debug_info,
-            clock_speed,
+            timing: TimingModel::default(),
+            speed: Speed::Normal,
+            realtime_timers: false,
+            rom: Vec::new(),
+            rom_sha1: String::new(),
+            rom_file_info: None,
+            seed,
+            cycle_budget: 0,
+            instruction_carry: 0.0,
+            recording: None,
+            playback: None,
+            stats: EmulatorStats::new(),
+            frame_number: 0,
+            sinks: Vec::new(),
+            font: Font::default(),
+            platform: Platform::default(),
+            platform_lookup: None,
+            debugger: Debugger::default(),
+        };
+
+        emulator.write_font();
+        emulator
+    }
+
+    fn write_font(&self) {
+        let address = self.font_address() as usize;
+        self.memory.borrow_mut()[address..address + FONT_SIZE].copy_from_slice(&self.font.glyphs);
+    }
+
+    pub fn load_rom_from_file(&mut self, path: &str) -> Result<Vec<RomWarning>, RomError> {
+        let (rom, info) = rom::read_rom_and_info(path)?;
+        self.load_rom_with_info(rom, info)
+    }
+
+    /// Puts the ROM in memory, leaving the loaded one alone if it doesn't fit. The platform
+    /// lookup gets to switch platforms first.
+    pub fn load_rom(&mut self, rom: Vec<u8>) -> Result<Vec<RomWarning>, RomError> {
+        self.load_rom_with_info(rom, None)
+    }
+
+    fn load_rom_with_info(&mut self, rom: Vec<u8>, info: Option<RomInfo>) -> Result<Vec<RomWarning>, RomError> {
+        let rom_sha1 = sha1_smol::Sha1::from(&rom).digest().to_string();
+        let platform = self
+            .platform_lookup
+            .as_ref()
+            .and_then(|lookup| lookup(&rom_sha1, info.as_ref()))
+            .unwrap_or(self.platform);
+        let warnings = rom::check_rom(&rom, platform.max_rom_size())?;
+
+        self.rom_sha1 = rom_sha1;
+        self.rom_file_info = info;
+        self.rom = rom;
+        if platform != self.platform {
+            self.set_platform(platform)?;
+        } else {
+            let address = self.platform.load_address() as usize;
+            self.memory.borrow_mut()[address..address + self.rom.len()].copy_from_slice(&self.rom);
+        }
+
+        Ok(warnings)
+    }
+
+    /// Decides the platform of every ROM loaded from now on, before it goes in memory, so a
+    /// ROM that is only small enough for its own platform still loads. A lookup that returns
+    /// None keeps the current platform.
+    pub fn set_platform_lookup(&mut self, lookup: PlatformLookup) {
+        self.platform_lookup = Some(lookup);
+    }
+
+    pub fn initialize(&mut self, rom_path: &str, timing: TimingModel) -> Result<Vec<RomWarning>, RomError> {
+        self.cpu.borrow_mut().pc = self.platform.start_address();
+        self.timing = timing;
+        self.load_rom_from_file(rom_path)
+    }
+
+    /// SHA-1 of the loaded ROM as lowercase hex
+    pub fn rom_sha1(&self) -> &str {
+        &self.rom_sha1
+    }
+
+    /// What the loaded ROM's file says about it, Octo cartridges carry their quirks, colors
+    /// and speed. It takes the place of a ROM database entry.
+    pub fn rom_file_info(&self) -> Option<&RomInfo> {
+        self.rom_file_info.as_ref()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Takes effect on the next reset
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Replaces the random source for `CXNN`, it is reseeded with the emulator's seed
+    pub fn set_rng(&mut self, mut rng: Box<dyn Rng>) {
+        rng.reseed(self.seed);
+        self.cpu.borrow_mut().rng = rng;
+    }
+
+    pub fn rng_name(&self) -> &'static str {
+        self.cpu.borrow().rng.name()
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.cpu.borrow().quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.cpu.borrow_mut().quirks = quirks;
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    /// Switches to another machine, clearing memory and the screen. The registers follow on the
+    /// next reset. Fails if the loaded ROM doesn't fit in the platform's memory.
+    pub fn set_platform(&mut self, platform: Platform) -> Result<(), RomError> {
+        if !self.rom.is_empty() {
+            rom::check_rom(&self.rom, platform.max_rom_size())?;
         }
+
+        self.platform = platform;
+        self.cpu.borrow_mut().platform = platform;
+        self.reload_memory();
+        self.configure_display();
+        Ok(())
+    }
+
+    /// Clears memory to the platform's size and puts the font and ROM back
+    fn reload_memory(&mut self) {
+        {
+            let mut memory = self.memory.borrow_mut();
+            memory.reset(self.platform.memory_size());
+            let address = self.platform.load_address() as usize;
+            memory[address..address + self.rom.len()].copy_from_slice(&self.rom);
+        }
+        self.write_font();
+    }
+
+    /// A cleared screen the size of the platform's
+    fn configure_display(&mut self) {
+        let platform = self.platform;
+        self.gpu
+            .borrow_mut()
+            .configure(platform.width(), platform.height(), platform == Platform::Chip8X);
     }
 
-    pub fn load_rom_from_file(&mut self, path: &str) {
-        let rom = std::fs::read(path).unwrap();
-        println!("Loaded rom: {:?}", path);
-        self.load_rom(rom);
+    pub fn font(&self) -> Font {
+        self.font
+    }
+
+    /// Where the font starts in memory, `FX29` points into it
+    pub fn font_address(&self) -> u16 {
+        self.cpu.borrow().font_address
+    }
+
+    /// Replaces the font in memory. It has to end before the program starts.
+    pub fn set_font(&mut self, font: Font, address: u16) -> Result<(), String> {
+        check_font_address(address)?;
+
+        let old_address = self.font_address() as usize;
+        self.memory.borrow_mut()[old_address..old_address + FONT_SIZE].fill(0);
+
+        self.font = font;
+        self.cpu.borrow_mut().font_address = address;
+        self.write_font();
+        Ok(())
+    }
+
+    /// Print every instruction to stdout as it executes
+    pub fn set_trace(&mut self, trace: bool) {
+        self.cpu.borrow_mut().trace = trace;
+    }
+
+    pub fn symbols(&self) -> Arc<Symbols> {
+        self.cpu.borrow().symbols.clone()
+    }
+
+    /// Names addresses in the trace and the debugger, and sets the breakpoints the file asks for
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.debugger.breakpoints.extend(symbols.breakpoints());
+
+        let symbols = Arc::new(symbols);
+        self.cpu.borrow_mut().symbols = symbols.clone();
+        self.debug_info.lock().unwrap().symbols = symbols;
+        self.publish_debug_state();
+    }
+
+    pub fn load_symbols(&mut self, path: &str) -> Result<(), SymbolError> {
+        self.set_symbols(Symbols::load(path)?);
+        Ok(())
+    }
+
+    /// The current screen contents, top row first, 255 for lit pixels
+    pub fn framebuffer(&self) -> Framebuffer {
+        self.gpu.borrow().framebuffer()
+    }
+
+    pub fn cpu_state(&self) -> CpuState {
+        let cpu = self.cpu.borrow();
+
+        CpuState {
+            pc: cpu.pc,
+            idx: cpu.idx,
+            v_reg: cpu.v_reg,
+            stack: cpu.stack.clone(),
+            dly_timer: cpu.dly_timer,
+            snd_timer: cpu.snd_timer,
+        }
+    }
+
+    /// The buzzer sounds while this is above zero
+    pub fn sound_timer(&self) -> u8 {
+        self.cpu.borrow().snd_timer
+    }
+
+    /// Fills `out` with the MEGA-CHIP sound `060N` started, -1.0 to 1.0 at the output rate.
+    /// Returns false and leaves `out` alone when nothing is playing.
+    pub fn fill_sample_audio(&mut self, out: &mut [f32], output_rate: u32) -> bool {
+        let mut cpu = self.cpu.borrow_mut();
+
+        match &mut cpu.sample {
+            Some(sample) if !sample.finished() => {
+                sample.fill(out, output_rate);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Restarts the loaded ROM from power-on state
+    pub fn reset(&mut self) {
+        // an error paused it, a fresh start can run
+        if self.cpu.borrow().error.is_some() {
+            self.debugger.resume();
+        }
+
+        self.reload_memory();
+
+        self.cpu.borrow_mut().reset(self.platform.start_address(), self.seed);
+        self.configure_display();
+        self.cycle_budget = 0;
+        self.instruction_carry = 0.0;
+        self.frame_number = 0;
+        self.debugger.before_step = None;
     }
 
-    pub fn load_rom(&mut self, rom: Vec<u8>) {
-        self.memory.borrow_mut()[0x200..0x200 + rom.len()].copy_from_slice(&rom);
-        //println!("Memory slice at {:#03x} <-> {:#03x}:\n{:?}", 0x200, 0x200 + rom.len(), &self.memory.borrow()[0x200..0x200 + rom.len()]);
+    /// Subscribes a sink to every frame completed from now on
+    pub fn add_sink(&mut self, sink: Box<dyn FrameSink>) {
+        self.sinks.push(sink);
     }
 
-    pub fn initialize(&mut self, rom_path: &str, clock_speed: u32) {
-        self.cpu.borrow_mut().pc = 0x200;
-        self.load_rom_from_file(rom_path);
-        self.clock_speed = clock_speed;
+    pub fn clear_sinks(&mut self) {
+        self.sinks.clear();
     }
 
     pub fn cycle(&mut self) {
         let mut cpu = self.cpu.borrow_mut();
         cpu.fetch();
+        self.stats.instructions += 1;
+        self.stats.opcodes[cpu.cir as usize] += 1;
+        cpu.decode_execute();
+    }
+
+    /// Runs a single instruction and returns what it costs on a COSMAC VIP
+    fn cycle_vip(&mut self) -> i32 {
+        let mut cpu = self.cpu.borrow_mut();
+        cpu.fetch();
+
+        let opcode = cpu.cir;
+        let next_pc = cpu.pc;
+        self.stats.instructions += 1;
+        self.stats.opcodes[opcode as usize] += 1;
         cpu.decode_execute();
+
+        timing::vip_cycles(opcode, cpu.pc == next_pc + 2)
+    }
+
+    /// Runs one 60hz frame with the given keypad state and returns how many instructions ran.
+    /// While a movie is playing its recorded keypad state is used instead.
+    pub fn run_frame(&mut self, keypad: u16) -> u32 {
+        if self.debugger.paused {
+            self.publish_debug_state();
+            return 0;
+        }
+
+        let frame_start = Instant::now();
+        let keypad = self.next_movie_frame(keypad);
+
+        {
+            let mut cpu = self.cpu.borrow_mut();
+            for (key, pressed) in cpu.keypad.iter_mut().enumerate() {
+                *pressed = keypad & (1 << key) != 0;
+            }
+        }
+
+        let cpu_start = Instant::now();
+        let cycles = match self.cpu_speed() {
+            Some(multiplier) => self.run_cpu(multiplier),
+            None => self.run_cpu_until(frame_start + REALTIME_FRAME_BUDGET),
+        };
+        self.stats.cpu_time += cpu_start.elapsed();
+
+        // The display interrupt at the end of every frame
+        let timer_start = Instant::now();
+        self.cpu.borrow_mut().tick_timers();
+        self.stats.timer_time += timer_start.elapsed();
+
+        self.stats.frames += 1;
+        self.present_frame();
+        self.publish_debug_state();
+
+        cycles
+    }
+
+    /// Hands the screen as it is at the end of the frame to every sink
+    fn present_frame(&mut self) {
+        let frame = Frame {
+            number: self.frame_number,
+            pixels: self.gpu.borrow().framebuffer(),
+        };
+        self.frame_number += 1;
+
+        for sink in self.sinks.iter_mut() {
+            sink.present(&frame);
+        }
+    }
+
+    fn should_break(&mut self) -> bool {
+        let cpu = self.cpu.borrow();
+        if cpu.error.is_some() {
+            self.debugger.pause();
+            return true;
+        }
+
+        self.debugger.should_break(cpu.pc)
+    }
+
+    /// Why the program stopped, if it did
+    pub fn cpu_error(&self) -> Option<CpuError> {
+        self.cpu.borrow().error
+    }
+
+    /// The return addresses on the stack, innermost call first
+    pub fn call_stack(&self) -> Vec<StackFrame> {
+        let memory = self.memory.borrow();
+
+        self.cpu
+            .borrow()
+            .stack
+            .iter()
+            .rev()
+            .map(|&return_address| {
+                let call = return_address.wrapping_sub(2) as u32;
+                let opcode = (memory.read(call) as u16) << 8 | memory.read(call + 1) as u16;
+                StackFrame {
+                    return_address,
+                    subroutine: (opcode & 0xF000 == 0x2000).then_some(opcode & 0xFFF),
+                }
+            })
+            .collect()
+    }
+
+    /// Runs one instruction and pauses, remembering the registers from before it
+    pub fn step(&mut self) {
+        self.debugger.before_step = Some(self.cpu_state());
+        self.cycle();
+        self.debugger.pause();
+        self.present_frame();
+        self.publish_debug_state();
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    pub fn debugger_mut(&mut self) -> &mut Debugger {
+        &mut self.debugger
+    }
+
+    /// Copies the registers, breakpoints and the code the debugger shows into `debug_info`
+    fn publish_debug_state(&self) {
+        let mut debug_info = self.debug_info.lock().unwrap();
+        let cpu = self.cpu_state();
+
+        // the code around the PC, or where the debugger is looking
+        let center = debug_info.view_address.unwrap_or(cpu.pc);
+        let start = center - (center.min(CODE_WINDOW / 2) & !1);
+        let memory = self.memory.borrow();
+        debug_info.code_start = start;
+        debug_info.code = (0..CODE_WINDOW)
+            .map(|offset| memory.read(start.wrapping_add(offset) as u32))
+            .collect();
+        let start = debug_info.memory_address;
+        debug_info.memory = (0..MEMORY_WINDOW)
+            .map(|offset| memory.read(start.wrapping_add(offset) as u32))
+            .collect();
+
+        debug_info.paused = self.debugger.paused;
+        debug_info.breakpoints = self.debugger.breakpoints.iter().copied().collect();
+        debug_info.before_step = self.debugger.before_step.clone();
+        debug_info.platform = self.platform;
+        debug_info.cpu = cpu;
+        debug_info.call_stack = self.call_stack();
+        debug_info.cpu_error = self.cpu_error().map(|err| err.to_string());
+    }
+
+    /// Returns the counters collected since the last call and starts new ones
+    pub fn take_stats(&mut self) -> EmulatorStats {
+        let mut stats = std::mem::take(&mut self.stats);
+
+        // draw time was measured inside the CPU time
+        stats.draw_time = std::mem::take(&mut self.gpu.borrow_mut().draw_time);
+        stats.cpu_time = stats.cpu_time.saturating_sub(stats.draw_time);
+
+        stats
+    }
+
+    /// How often the frontend should call `run_frame`, None means as fast as it can
+    pub fn frame_rate(&self) -> Option<f64> {
+        if self.speeds_up_cpu_only() {
+            Some(60.0)
+        } else {
+            self.speed.multiplier().map(|multiplier| 60.0 * multiplier)
+        }
+    }
+
+    /// How many times its normal work the CPU does per frame, None to keep going until the
+    /// frame's real time is used up
+    fn cpu_speed(&self) -> Option<f64> {
+        if self.speeds_up_cpu_only() {
+            self.speed.multiplier()
+        } else {
+            Some(1.0)
+        }
+    }
+
+    /// Speeding up only the CPU changes what happens within a frame, which would break movies
+    fn speeds_up_cpu_only(&self) -> bool {
+        self.realtime_timers && self.movie_state() == MovieState::Idle
     }
-}
\ No newline at end of file
+
+    fn run_cpu(&mut self, multiplier: f64) -> u32 {
+        let mut cycles = 0;
+
+        match self.timing {
+            TimingModel::InstructionsPerFrame(count) => {
+                self.instruction_carry += count as f64 * multiplier;
+                cycles = self.instruction_carry as u32;
+                self.instruction_carry -= cycles as f64;
+
+                for _ in 0..cycles {
+                    if self.should_break() {
+                        break;
+                    }
+                    self.cycle();
+                }
+            }
+            TimingModel::CosmacVip => {
+                let frame_cycles = timing::VIP_CYCLES_PER_FRAME - timing::VIP_DISPLAY_CYCLES;
+                self.cycle_budget += (frame_cycles as f64 * multiplier) as i32;
+
+                while self.cycle_budget > 0 {
+                    if self.should_break() {
+                        break;
+                    }
+                    self.cycle_budget -= self.cycle_vip();
+                    cycles += 1;
+
+                    // DXYN waits for the display interrupt, nothing else runs this frame
+                    if self.cpu.borrow().cir & 0xF000 == 0xD000 {
+                        self.cycle_budget = 0;
+                    }
+                }
+            }
+        }
+
+        cycles
+    }
+
+    /// Runs instructions in batches until the deadline, ignoring the timing model
+    fn run_cpu_until(&mut self, deadline: Instant) -> u32 {
+        let mut cycles = 0;
+
+        while Instant::now() < deadline && !self.debugger.paused {
+            for _ in 0..64 {
+                if self.should_break() {
+                    break;
+                }
+                self.cycle();
+                cycles += 1;
+            }
+        }
+
+        cycles
+    }
+
+    pub fn handle_command(&mut self, command: EmulatorCommand) {
+        let result = match command {
+            EmulatorCommand::StartRecording => self.start_recording(),
+            EmulatorCommand::StopRecording(path) => match self.stop_recording() {
+                Some(movie) => movie.save(&path),
+                None => Ok(()),
+            },
+            EmulatorCommand::PlayMovie(path) => Movie::load(&path).and_then(|movie| self.play_movie(movie)),
+            EmulatorCommand::SetTiming(timing) => {
+                self.timing = timing;
+                self.cycle_budget = 0;
+                Ok(())
+            }
+            EmulatorCommand::SetSpeed { speed, realtime_timers } => {
+                self.speed = speed;
+                self.realtime_timers = realtime_timers;
+                Ok(())
+            }
+            EmulatorCommand::LoadRom(path) => {
+                let result = self.load_rom_from_file(&path);
+                if result.is_ok() {
+                    self.recording = None;
+                    self.playback = None;
+                    self.reset();
+
+                    // the old ROM's names don't fit the new one
+                    let symbols = Symbols::path_for_rom(&path).map_or(Ok(Symbols::default()), Symbols::load);
+                    self.debug_info.lock().unwrap().symbols_error = symbols.as_ref().err().map(ToString::to_string);
+                    self.set_symbols(symbols.unwrap_or_default());
+                }
+
+                self.debug_info.lock().unwrap().record_rom_load(&result);
+                return;
+            }
+            EmulatorCommand::LoadSymbols(path) => {
+                let result = self.load_symbols(&path);
+                self.debug_info.lock().unwrap().symbols_error = result.err().map(|err| err.to_string());
+                return;
+            }
+            EmulatorCommand::Pause => {
+                self.debugger.pause();
+                self.publish_debug_state();
+                return;
+            }
+            EmulatorCommand::Resume => {
+                self.debugger.resume();
+                return;
+            }
+            EmulatorCommand::Step => {
+                self.step();
+                return;
+            }
+            EmulatorCommand::RunTo(address) => {
+                self.debugger.run_to(address);
+                return;
+            }
+            EmulatorCommand::ToggleBreakpoint(address) => {
+                self.debugger.toggle_breakpoint(address);
+                self.publish_debug_state();
+                return;
+            }
+        };
+
+        let mut debug_info = self.debug_info.lock().unwrap();
+        debug_info.movie_error = result.err().map(|err| err.to_string());
+    }
+
+    /// Restarts the ROM and starts recording input. Fails with a random source that a movie
+    /// couldn't rebuild, like the fixed sequence used in tests.
+    pub fn start_recording(&mut self) -> Result<(), MovieError> {
+        if rng::rng_from_name(self.rng_name(), self.seed).is_none() {
+            return Err(MovieError::UnrecordableRng(self.rng_name().to_string()));
+        }
+
+        self.playback = None;
+        self.reset();
+        self.recording = Some(Movie {
+            rom_sha1: self.rom_sha1.clone(),
+            seed: self.seed,
+            rng: self.rng_name().to_string(),
+            timing: self.timing,
+            quirks: self.quirks(),
+            platform: self.platform,
+            font: self.font,
+            font_address: self.font_address(),
+            frames: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Movie> {
+        self.recording.take()
+    }
+
+    /// Restarts the ROM with the movie's settings and replays its input
+    pub fn play_movie(&mut self, movie: Movie) -> Result<(), MovieError> {
+        if movie.rom_sha1 != self.rom_sha1 {
+            return Err(MovieError::RomMismatch {
+                expected: movie.rom_sha1,
+                loaded: self.rom_sha1.clone(),
+            });
+        }
+
+        self.set_platform(movie.platform)
+            .map_err(|err| MovieError::Setup(err.to_string()))?;
+        self.set_font(movie.font, movie.font_address).map_err(MovieError::Setup)?;
+
+        if movie.rng != self.rng_name() {
+            match rng::rng_from_name(&movie.rng, movie.seed) {
+                Some(rng) => self.set_rng(rng),
+                None => return Err(MovieError::Parse(format!("unknown rng {:?}", movie.rng))),
+            }
+        }
+
+        self.recording = None;
+        self.seed = movie.seed;
+        self.timing = movie.timing;
+        self.set_quirks(movie.quirks);
+        self.reset();
+
+        if !movie.frames.is_empty() {
+            self.playback = Some((movie, 0));
+        }
+
+        Ok(())
+    }
+
+    /// Keypad bitmask the CPU last ran with, a movie's during playback
+    pub fn keypad(&self) -> u16 {
+        let cpu = self.cpu.borrow();
+        (0..16).filter(|key| cpu.keypad[*key]).fold(0, |keypad, key| keypad | 1 << key)
+    }
+
+    /// The keys the program has looked at since the last call, bit N for key N
+    pub fn take_keys_checked(&mut self) -> u16 {
+        std::mem::take(&mut self.cpu.borrow_mut().keys_checked)
+    }
+
+    pub fn movie_state(&self) -> MovieState {
+        if let Some(movie) = &self.recording {
+            MovieState::Recording { frames: movie.frames.len() }
+        } else if let Some((movie, frame)) = &self.playback {
+            MovieState::Playing { frame: *frame, total: movie.frames.len() }
+        } else {
+            MovieState::Idle
+        }
+    }
+
+    /// Swaps in the movie's keypad state during playback and records it while recording
+    fn next_movie_frame(&mut self, keypad: u16) -> u16 {
+        let keypad = match &mut self.playback {
+            Some((movie, frame)) => {
+                let recorded = movie.frames[*frame];
+                *frame += 1;
+
+                if *frame == movie.frames.len() {
+                    self.playback = None;
+                }
+
+                recorded
+            }
+            None => keypad,
+        };
+
+        if let Some(movie) = &mut self.recording {
+            movie.frames.push(keypad);
+        }
+
+        keypad
+    }
+}
+
+/// The font goes between the start of memory and the program
+pub fn check_font_address(address: u16) -> Result<(), String> {
+    if address as usize + FONT_SIZE > PROGRAM_START {
+        return Err(format!(
+            "font at {:#05X} would run into the program at {:#05X}",
+            address, PROGRAM_START
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,227 @@
+use std::fmt;
+
+use super::font::{Font, FONT_SIZE};
+use super::platform::Platform;
+use super::quirks::Quirks;
+use super::timing::TimingModel;
+
+const MAGIC: &str = "CHIP8MOVIE 1";
+
+/// A recorded session: everything needed to replay it plus the keypad state of every frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct Movie {
+    pub rom_sha1: String,
+    pub seed: u64,
+    /// Name of the `CXNN` random source, see `Rng::name`
+    pub rng: String,
+    pub timing: TimingModel,
+    pub quirks: Quirks,
+    pub platform: Platform,
+    pub font: Font,
+    pub font_address: u16,
+    /// Keypad bitmask per frame, bit N set means key N is held
+    pub frames: Vec<u16>,
+}
+
+#[derive(Debug)]
+pub enum MovieError {
+    Io(std::io::Error),
+    Parse(String),
+    /// The movie was recorded against a different ROM than the one loaded
+    RomMismatch { expected: String, loaded: String },
+    /// The random source can't be rebuilt from its name and seed, so a movie couldn't replay it
+    UnrecordableRng(String),
+    /// The movie's settings can't be applied, like a platform the ROM doesn't fit
+    Setup(String),
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::Io(err) => write!(f, "{}", err),
+            MovieError::Parse(msg) => write!(f, "Invalid movie file: {}", msg),
+            MovieError::RomMismatch { expected, loaded } => write!(
+                f,
+                "Movie was recorded with ROM {}, but {} is loaded",
+                expected, loaded
+            ),
+            MovieError::UnrecordableRng(rng) => write!(
+                f,
+                "Can't record with the {} random source, a movie has no way to recreate it",
+                rng
+            ),
+            MovieError::Setup(msg) => write!(f, "Can't replay the movie: {}", msg),
+        }
+    }
+}
+
+impl From<std::io::Error> for MovieError {
+    fn from(err: std::io::Error) -> Self {
+        MovieError::Io(err)
+    }
+}
+
+/// What the emulator is doing with movies, shown in the GUI
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovieState {
+    Idle,
+    Recording { frames: usize },
+    Playing { frame: usize, total: usize },
+}
+
+impl Movie {
+    pub fn save(&self, path: &str) -> Result<(), MovieError> {
+        std::fs::write(path, self.serialize())?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Movie, MovieError> {
+        Movie::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Plain text so movies attached to bug reports stay readable and diffable
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str(MAGIC);
+        out.push('\n');
+        out.push_str(&format!("rom_sha1 {}\n", self.rom_sha1));
+        out.push_str(&format!("seed {}\n", self.seed));
+        out.push_str(&format!("rng {}\n", self.rng));
+        out.push_str(&format!("timing {}\n", self.timing));
+        out.push_str(&format!(
+            "quirks vf_reset={} memory_increment={} shift_vx={} jump_vx={} clipping={} stack_depth={}\n",
+            self.quirks.vf_reset as u8,
+            self.quirks.memory_increment as u8,
+            self.quirks.shift_vx as u8,
+            self.quirks.jump_vx as u8,
+            self.quirks.clipping as u8,
+            self.quirks.stack_depth,
+        ));
+        out.push_str(&format!("platform {}\n", self.platform));
+        out.push_str(&format!("font_address {:03X}\n", self.font_address));
+        let glyphs: Vec<String> = self.font.glyphs.iter().map(|byte| format!("{:02X}", byte)).collect();
+        out.push_str(&format!("font {}\n", glyphs.concat()));
+        out.push_str(&format!("frames {}\n", self.frames.len()));
+
+        for keypad in &self.frames {
+            out.push_str(&format!("{:04X}\n", keypad));
+        }
+
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<Movie, MovieError> {
+        let mut lines = text.lines();
+
+        if lines.next() != Some(MAGIC) {
+            return Err(MovieError::Parse("missing header".to_string()));
+        }
+
+        let rom_sha1 = header_value(lines.next(), "rom_sha1")?.to_string();
+        let seed = parse_number(header_value(lines.next(), "seed")?, "seed")?;
+        let rng = header_value(lines.next(), "rng")?.to_string();
+        let timing = header_value(lines.next(), "timing")?
+            .parse()
+            .map_err(MovieError::Parse)?;
+        let quirks = parse_quirks(header_value(lines.next(), "quirks")?)?;
+        let platform = header_value(lines.next(), "platform")?
+            .parse()
+            .map_err(MovieError::Parse)?;
+        let font_address = header_value(lines.next(), "font_address")?;
+        let font_address = u16::from_str_radix(font_address, 16)
+            .map_err(|_| MovieError::Parse(format!("bad font_address {:?}", font_address)))?;
+        let font = parse_font(header_value(lines.next(), "font")?)?;
+        let frame_count: usize = parse_number(header_value(lines.next(), "frames")?, "frames")?;
+
+        let frames = lines
+            .map(|line| {
+                u16::from_str_radix(line.trim(), 16)
+                    .map_err(|_| MovieError::Parse(format!("bad keypad state {:?}", line)))
+            })
+            .collect::<Result<Vec<u16>, MovieError>>()?;
+
+        if frames.len() != frame_count {
+            return Err(MovieError::Parse(format!(
+                "expected {} frames, found {}",
+                frame_count,
+                frames.len()
+            )));
+        }
+
+        Ok(Movie {
+            rom_sha1,
+            seed,
+            rng,
+            timing,
+            quirks,
+            platform,
+            font,
+            font_address,
+            frames,
+        })
+    }
+}
+
+fn header_value<'a>(line: Option<&'a str>, key: &str) -> Result<&'a str, MovieError> {
+    line.and_then(|line| line.strip_prefix(key))
+        .and_then(|rest| rest.strip_prefix(' '))
+        .ok_or_else(|| MovieError::Parse(format!("missing {}", key)))
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, key: &str) -> Result<T, MovieError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| MovieError::Parse(format!("bad {} {:?}", key, value)))
+}
+
+fn parse_font(value: &str) -> Result<Font, MovieError> {
+    let bad_font = || MovieError::Parse(format!("bad font {:?}", value));
+
+    if value.len() != FONT_SIZE * 2 || !value.is_ascii() {
+        return Err(bad_font());
+    }
+
+    let mut glyphs = [0; FONT_SIZE];
+    for (i, byte) in glyphs.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&value[i * 2..i * 2 + 2], 16).map_err(|_| bad_font())?;
+    }
+
+    Ok(Font { glyphs })
+}
+
+/// Every quirk has to be there, one left out would replay with a default and desync
+fn parse_quirks(value: &str) -> Result<Quirks, MovieError> {
+    const NAMES: [&str; 6] = ["vf_reset", "memory_increment", "shift_vx", "jump_vx", "clipping", "stack_depth"];
+    let mut values: [Option<&str>; 6] = [None; 6];
+
+    for pair in value.split_whitespace() {
+        let (name, value) = pair
+            .split_once('=')
+            .ok_or_else(|| MovieError::Parse(format!("bad quirk {:?}", pair)))?;
+        let index = NAMES
+            .iter()
+            .position(|known| *known == name)
+            .ok_or_else(|| MovieError::Parse(format!("unknown quirk {:?}", name)))?;
+        values[index] = Some(value);
+    }
+
+    let value = |index: usize| values[index].ok_or_else(|| MovieError::Parse(format!("missing quirk {}", NAMES[index])));
+    let flag = |index: usize| match value(index)? {
+        "0" => Ok(false),
+        "1" => Ok(true),
+        other => Err(MovieError::Parse(format!("bad quirk {}={:?}", NAMES[index], other))),
+    };
+
+    Ok(Quirks {
+        vf_reset: flag(0)?,
+        memory_increment: flag(1)?,
+        shift_vx: flag(2)?,
+        jump_vx: flag(3)?,
+        clipping: flag(4)?,
+        stack_depth: value(5)?
+            .parse()
+            .map_err(|_| MovieError::Parse(format!("bad quirk stack_depth={:?}", values[5])))?,
+    })
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// The machine a ROM was written for. Decides the screen size, where the ROM goes in memory
+/// and the instructions on top of plain CHIP-8.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Platform {
+    #[default]
+    Chip8,
+    /// The two-page display interpreter for the COSMAC VIP, 64x64 pixels. The ROM starts with
+    /// the patched interpreter, the program itself at 0x2C0.
+    Hires,
+    /// The COSMAC VIP with the VP-590 color board: colored zones, a background color and a
+    /// second keypad, with the interpreter taking memory up to 0x300
+    Chip8X,
+    /// Gilles Detillieux's CHIP-8E, with relative jumps, register ranges and waits
+    Chip8E,
+    /// Revival Studios' MEGA-CHIP: a 256x192 color screen once the ROM turns it on with `0011`,
+    /// 24 bit addresses and sampled sound
+    MegaChip,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 5] = [
+        Platform::Chip8,
+        Platform::Hires,
+        Platform::Chip8X,
+        Platform::Chip8E,
+        Platform::MegaChip,
+    ];
+
+    /// Width of the screen the machine starts with
+    pub fn width(&self) -> usize {
+        64
+    }
+
+    /// Height of the screen the machine starts with
+    pub fn height(&self) -> usize {
+        match self {
+            Platform::Hires => 64,
+            _ => 32,
+        }
+    }
+
+    /// Where the ROM goes in memory
+    pub fn load_address(&self) -> u16 {
+        match self {
+            Platform::Chip8X => 0x300,
+            _ => 0x200,
+        }
+    }
+
+    /// Where the program starts running
+    pub fn start_address(&self) -> u16 {
+        match self {
+            Platform::Hires => 0x2C0,
+            _ => self.load_address(),
+        }
+    }
+
+    /// Bytes of RAM, always a power of two
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Platform::MegaChip => 1 << 24,
+            _ => 4096,
+        }
+    }
+
+    /// Largest ROM that fits between the load address and the end of memory
+    pub fn max_rom_size(&self) -> usize {
+        self.memory_size() - self.load_address() as usize
+    }
+
+    /// The platform for an id from the chip-8-database, None for ones this emulator treats
+    /// as plain CHIP-8 with quirks
+    pub fn from_database_id(id: &str) -> Option<Platform> {
+        match id {
+            "chip8x" => Some(Platform::Chip8X),
+            "chip8e" => Some(Platform::Chip8E),
+            "hires" | "hiresChip8" => Some(Platform::Hires),
+            "megachip8" => Some(Platform::MegaChip),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Platform::Chip8 => write!(f, "chip8"),
+            Platform::Hires => write!(f, "hires"),
+            Platform::Chip8X => write!(f, "chip8x"),
+            Platform::Chip8E => write!(f, "chip8e"),
+            Platform::MegaChip => write!(f, "megachip"),
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Platform::ALL
+            .into_iter()
+            .find(|platform| platform.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                let names: Vec<String> = Platform::ALL.iter().map(ToString::to_string).collect();
+                format!("unknown platform {:?}, expected one of {}", s, names.join(", "))
+            })
+    }
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+/// Behaviour differences between CHIP-8 interpreters that ROMs may depend on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quirks {
+    /// `8XY1`, `8XY2` and `8XY3` reset VF to 0
+    pub vf_reset: bool,
+    /// `FX55` and `FX65` leave the index register pointing past the last register
+    pub memory_increment: bool,
+    /// `8XY6` and `8XYE` shift VX in place instead of shifting VY into VX
+    pub shift_vx: bool,
+    /// `BNNN` jumps to XNN + VX instead of NNN + V0
+    pub jump_vx: bool,
+    /// Sprites are clipped at the screen edges instead of wrapping around
+    pub clipping: bool,
+    /// How many return addresses fit on the stack, calling deeper is an error
+    pub stack_depth: usize,
+}
+
+impl Quirks {
+    /// Looks up a preset by the name used on the command line and in config files
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "vip" | "cosmac-vip" => Some(Self::cosmac_vip()),
+            "schip" => Some(Self::schip()),
+            "xo-chip" => Some(Self::xo_chip()),
+            _ => None,
+        }
+    }
+
+    /// Name of the preset these quirks match, if any
+    pub fn preset_name(&self) -> Option<&'static str> {
+        ["vip", "schip", "xo-chip"]
+            .into_iter()
+            .find(|name| Quirks::preset(name) == Some(*self))
+    }
+
+    /// The original interpreter on the COSMAC VIP
+    pub fn cosmac_vip() -> Self {
+        Self {
+            vf_reset: true,
+            memory_increment: true,
+            shift_vx: false,
+            jump_vx: false,
+            clipping: true,
+            stack_depth: 12,
+        }
+    }
+
+    /// SUPER-CHIP 1.1 on the HP48
+    pub fn schip() -> Self {
+        Self {
+            vf_reset: false,
+            memory_increment: false,
+            shift_vx: true,
+            jump_vx: true,
+            clipping: true,
+            stack_depth: 16,
+        }
+    }
+
+    /// XO-CHIP as implemented by Octo
+    pub fn xo_chip() -> Self {
+        Self {
+            vf_reset: false,
+            memory_increment: true,
+            shift_vx: false,
+            jump_vx: false,
+            clipping: false,
+            stack_depth: 16,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::cosmac_vip()
+    }
+}
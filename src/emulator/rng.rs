@@ -0,0 +1,134 @@
+/// Source of the random bytes used by `CXNN`
+pub trait Rng {
+    fn next_u8(&mut self) -> u8;
+
+    /// Restarts the sequence, called when the emulator is reset
+    fn reseed(&mut self, seed: u64);
+
+    /// Called on every 60hz timer tick, for generators that depend on timing
+    fn tick(&mut self) {}
+
+    /// Identifies the generator in movie files
+    fn name(&self) -> &'static str;
+}
+
+/// Builds one of the built-in generators from the name it reports
+pub fn rng_from_name(name: &str, seed: u64) -> Option<Box<dyn Rng>> {
+    match name {
+        "xorshift" => Some(Box::new(XorShiftRng::new(seed))),
+        "cosmac-vip" => Some(Box::new(VipRng::new(seed))),
+        _ => None,
+    }
+}
+
+/// Seedable xorshift64* generator, the default
+pub struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.reseed(seed);
+        rng
+    }
+}
+
+impl Rng for XorShiftRng {
+    fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        // xorshift gets stuck on an all zero state
+        self.state = if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed };
+    }
+
+    fn name(&self) -> &'static str {
+        "xorshift"
+    }
+}
+
+/// Hands out a fixed list of bytes in order, looping around at the end. For tests.
+pub struct FixedSequenceRng {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl FixedSequenceRng {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        assert!(!bytes.is_empty(), "FixedSequenceRng needs at least one byte");
+
+        Self { bytes, position: 0 }
+    }
+}
+
+impl Rng for FixedSequenceRng {
+    fn next_u8(&mut self) -> u8 {
+        let byte = self.bytes[self.position];
+        self.position = (self.position + 1) % self.bytes.len();
+        byte
+    }
+
+    fn reseed(&mut self, _seed: u64) {
+        self.position = 0;
+    }
+
+    fn name(&self) -> &'static str {
+        "fixed"
+    }
+}
+
+/// Modelled on the `RND` routine of the COSMAC VIP interpreter. The VIP keeps a 16 bit
+/// counter, bumps it on every call and on every display interrupt, uses the low byte to index
+/// into the interpreter's own code page and adds the byte found there to the high byte, which
+/// is the result. The interpreter code is not part of this emulator, so a table derived from
+/// the seed stands in for that page; the coupling to frame timing is kept.
+pub struct VipRng {
+    counter: u16,
+    page: [u8; 256],
+}
+
+impl VipRng {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self {
+            counter: 0,
+            page: [0; 256],
+        };
+        rng.reseed(seed);
+        rng
+    }
+}
+
+impl Rng for VipRng {
+    fn next_u8(&mut self) -> u8 {
+        self.counter = self.counter.wrapping_add(1);
+
+        let [high, low] = self.counter.to_be_bytes();
+        let high = high.wrapping_add(self.page[low as usize]);
+        self.counter = u16::from_be_bytes([high, low]);
+
+        high
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        let mut table = XorShiftRng::new(seed);
+        for byte in self.page.iter_mut() {
+            *byte = table.next_u8();
+        }
+
+        self.counter = (seed >> 48) as u16;
+    }
+
+    fn tick(&mut self) {
+        self.counter = self.counter.wrapping_add(1);
+    }
+
+    fn name(&self) -> &'static str {
+        "cosmac-vip"
+    }
+}
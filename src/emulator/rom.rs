@@ -0,0 +1,213 @@
+use std::fmt;
+use std::io::{Cursor, ErrorKind, Read};
+use std::path::Path;
+
+use super::cartridge;
+use crate::romdb::RomInfo;
+
+/// Where programs are loaded and start running
+pub const PROGRAM_START: usize = 0x200;
+
+/// Largest ROM that fits between the program start and the end of memory
+pub const MAX_ROM_SIZE: usize = 4096 - PROGRAM_START;
+
+/// Extensions of the ROMs picked out of zip archives
+pub const ROM_EXTENSIONS: [&str; 4] = ["ch8", "sc8", "xo8", "mc8"];
+
+/// Why a ROM couldn't be loaded
+#[derive(Debug)]
+pub enum RomError {
+    NotFound(String),
+    Io { path: String, source: std::io::Error },
+    Empty,
+    /// More bytes than fit in memory after the program start
+    TooLarge { size: usize, max: usize },
+    /// A zip archive that couldn't be read, or lacks the entry asked for
+    Archive { path: String, message: String },
+    NoRomInArchive(String),
+    /// A zip archive with several ROMs, load one with `<archive>#<entry>`
+    ChooseEntry { path: String, entries: Vec<String> },
+    /// A GIF that isn't an Octo cartridge
+    Cartridge { path: String, message: String },
+    /// An Octo cartridge without the `.ch8` exported from it next to it. The cartridge only
+    /// holds Octo source and there's no Octo assembler here, so it can't run on its own.
+    OctoSource { path: String },
+}
+
+/// Something odd about a ROM that still loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RomWarning {
+    /// Instructions are two bytes, so an odd size usually means a truncated or padded file.
+    /// ROMs ending in sprite data can be odd on purpose.
+    OddLength(usize),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::NotFound(path) => write!(f, "ROM not found: {}", path),
+            RomError::Io { path, source } => write!(f, "Couldn't read ROM {}: {}", path, source),
+            RomError::Empty => write!(f, "ROM is empty"),
+            RomError::TooLarge { size, max } => {
+                write!(f, "ROM is {} bytes, only {} fit in memory", size, max)
+            }
+            RomError::Archive { path, message } => write!(f, "Couldn't read archive {}: {}", path, message),
+            RomError::NoRomInArchive(path) => {
+                write!(f, "No .{} file in {}", ROM_EXTENSIONS.join(", ."), path)
+            }
+            RomError::ChooseEntry { path, entries } => {
+                write!(f, "{} holds several ROMs, load one as {}#<name>: {}", path, path, entries.join(", "))
+            }
+            RomError::Cartridge { path, message } => write!(f, "{} is not an Octo cartridge: {}", path, message),
+            RomError::OctoSource { path } => write!(
+                f,
+                "{} is an Octo cartridge, which holds Octo source code that this emulator can't assemble: \
+                 open it in Octo and export the ROM next to it as {}",
+                path,
+                Path::new(path).with_extension("ch8").display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl fmt::Display for RomWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomWarning::OddLength(size) => {
+                write!(f, "ROM is an odd number of bytes ({}), it may be truncated", size)
+            }
+        }
+    }
+}
+
+impl RomError {
+    /// Paths that load each ROM when an archive holds several
+    pub fn choices(&self) -> Vec<String> {
+        match self {
+            RomError::ChooseEntry { path, entries } => {
+                entries.iter().map(|entry| format!("{}#{}", path, entry)).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Reads a ROM file. Zip archives are searched for a ROM, `<archive>.zip#<entry>` picks one
+/// when there are several.
+pub fn read_rom(path: &str) -> Result<Vec<u8>, RomError> {
+    read_rom_and_info(path).map(|(rom, _)| rom)
+}
+
+/// Like `read_rom`, with the settings an Octo cartridge was saved with. Loading `game.gif`
+/// reads the program from the `game.ch8` exported next to it.
+pub fn read_rom_and_info(path: &str) -> Result<(Vec<u8>, Option<RomInfo>), RomError> {
+    if let Some((archive, entry)) = path.rsplit_once('#').filter(|(archive, _)| is_zip(archive)) {
+        return Ok((read_zip(archive, Some(entry))?, None));
+    }
+
+    if is_zip(path) {
+        return Ok((read_zip(path, None)?, None));
+    }
+
+    let rom = read_file(path)?;
+
+    if has_extension(path, "gif") {
+        let cartridge = cartridge::read_cartridge(&rom).map_err(|message| RomError::Cartridge {
+            path: path.to_string(),
+            message,
+        })?;
+
+        let exported = Path::new(path).with_extension("ch8");
+        if !exported.is_file() {
+            return Err(RomError::OctoSource { path: path.to_string() });
+        }
+
+        let title = Path::new(path).file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+        let rom = read_file(&exported.to_string_lossy())?;
+        return Ok((rom, Some(cartridge.options.rom_info(&title))));
+    }
+
+    Ok((rom, None))
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, RomError> {
+    std::fs::read(Path::new(path)).map_err(|source| match source.kind() {
+        ErrorKind::NotFound => RomError::NotFound(path.to_string()),
+        _ => RomError::Io {
+            path: path.to_string(),
+            source,
+        },
+    })
+}
+
+fn read_zip(path: &str, entry: Option<&str>) -> Result<Vec<u8>, RomError> {
+    let archive_error = |message: String| RomError::Archive {
+        path: path.to_string(),
+        message,
+    };
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(read_file(path)?)).map_err(|err| archive_error(err.to_string()))?;
+
+    let name = match entry {
+        Some(entry) => entry.to_string(),
+        None => {
+            let mut roms: Vec<String> = archive
+                .file_names()
+                .filter(|name| ROM_EXTENSIONS.iter().any(|extension| has_extension(name, extension)))
+                .map(String::from)
+                .collect();
+            roms.sort();
+
+            match roms.len() {
+                0 => return Err(RomError::NoRomInArchive(path.to_string())),
+                1 => roms.remove(0),
+                _ => {
+                    return Err(RomError::ChooseEntry {
+                        path: path.to_string(),
+                        entries: roms,
+                    })
+                }
+            }
+        }
+    };
+
+    let mut file = archive.by_name(&name).map_err(|err| archive_error(format!("{}: {}", name, err)))?;
+    let mut rom = Vec::new();
+    file.read_to_end(&mut rom).map_err(|err| archive_error(format!("{}: {}", name, err)))?;
+
+    Ok(rom)
+}
+
+fn is_zip(path: &str) -> bool {
+    has_extension(path, "zip")
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .is_some_and(|found| found.eq_ignore_ascii_case(extension))
+}
+
+/// Checks that a ROM fits in `max_size` bytes, returning what looks wrong with it but doesn't
+/// stop it from loading
+pub fn check_rom(rom: &[u8], max_size: usize) -> Result<Vec<RomWarning>, RomError> {
+    if rom.is_empty() {
+        return Err(RomError::Empty);
+    }
+
+    if rom.len() > max_size {
+        return Err(RomError::TooLarge {
+            size: rom.len(),
+            max: max_size,
+        });
+    }
+
+    let mut warnings = Vec::new();
+    if !rom.len().is_multiple_of(2) {
+        warnings.push(RomWarning::OddLength(rom.len()));
+    }
+
+    Ok(warnings)
+}
@@ -0,0 +1,190 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use super::display::Framebuffer;
+
+/// A completed 60hz frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Counts up from 0 after every reset
+    pub number: u64,
+    pub pixels: Framebuffer,
+}
+
+/// Receives every frame the emulator completes. Any number of sinks can be subscribed with
+/// `Emulator::add_sink`, they are called in the order they were added.
+pub trait FrameSink {
+    fn present(&mut self, frame: &Frame);
+}
+
+/// Keeps every frame in memory, for tests. Clones share the same list, so keep one to read
+/// the frames back after handing the other to the emulator.
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    frames: Arc<Mutex<Vec<Frame>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn frames(&self) -> Vec<Frame> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    pub fn last_frame(&self) -> Option<Frame> {
+        self.frames.lock().unwrap().last().cloned()
+    }
+}
+
+impl FrameSink for MemorySink {
+    fn present(&mut self, frame: &Frame) {
+        self.frames.lock().unwrap().push(frame.clone());
+    }
+}
+
+/// Draws frames to a terminal with Unicode half blocks, two pixels per character cell
+pub struct TerminalSink<W: Write> {
+    out: W,
+    /// Where the top left pixel goes, row and column counting from 0
+    origin: (u16, u16),
+    last_pixels: Option<Framebuffer>,
+}
+
+impl<W: Write> TerminalSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            origin: (0, 0),
+            last_pixels: None,
+        }
+    }
+
+    /// Draws the screen starting at `row` and `column` instead of the top left corner, to
+    /// leave room for a frame around it
+    pub fn at(mut self, row: u16, column: u16) -> Self {
+        self.origin = (row, column);
+        self
+    }
+}
+
+impl<W: Write> FrameSink for TerminalSink<W> {
+    fn present(&mut self, frame: &Frame) {
+        // most frames don't change anything, and terminals are slow
+        if self.last_pixels.as_ref() == Some(&frame.pixels) {
+            return;
+        }
+        self.last_pixels = Some(frame.pixels.clone());
+
+        // draw over the last frame, moving the cursor to the start of every line
+        let (row, column) = self.origin;
+        let mut text = String::new();
+        for (i, line) in half_block_lines(&frame.pixels).iter().enumerate() {
+            text.push_str(&format!("\x1b[{};{}H{}", row as usize + i + 1, column as usize + 1, line));
+        }
+
+        let _ = self.out.write_all(text.as_bytes());
+        let _ = self.out.flush();
+    }
+}
+
+/// The screen as one line per two rows, each character showing a pixel from an even row
+/// in its upper half and the pixel below it in its lower half
+pub fn half_block_lines(pixels: &Framebuffer) -> Vec<String> {
+    (0..pixels.height())
+        .step_by(2)
+        .map(|y| {
+            let bottom = if y + 1 < pixels.height() { pixels.row(y + 1) } else { &[] };
+
+            pixels
+                .row(y)
+                .iter()
+                .zip(bottom.iter().chain(std::iter::repeat(&0)))
+                .map(|(top, bottom)| match (*top != 0, *bottom != 0) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Saves frames as numbered PNG images in a directory, skipping frames that look the same
+/// as the one before
+pub struct PngRecorder {
+    dir: PathBuf,
+    /// Size of a CHIP-8 pixel in the images
+    scale: u32,
+    last_pixels: Option<Framebuffer>,
+}
+
+impl PngRecorder {
+    pub fn new(dir: impl Into<PathBuf>, scale: u32) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir,
+            scale: scale.max(1),
+            last_pixels: None,
+        })
+    }
+
+    fn save(&self, frame: &Frame) -> Result<(), png::EncodingError> {
+        let path = self.dir.join(format!("frame_{:06}.png", frame.number));
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        let pixels = &frame.pixels;
+        let width = pixels.width() as u32 * self.scale;
+        let height = pixels.height() as u32 * self.scale;
+        let mut encoder = png::Encoder::new(file, width, height);
+        // gray unless the platform has colors of its own
+        let channels = match pixels.colors() {
+            Some(_) => {
+                encoder.set_color(png::ColorType::Rgb);
+                3
+            }
+            None => {
+                encoder.set_color(png::ColorType::Grayscale);
+                1
+            }
+        };
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut data = Vec::with_capacity((width * height) as usize * channels);
+        for y in 0..pixels.height() {
+            let line: Vec<u8> = (0..pixels.width())
+                .flat_map(|x| {
+                    let pixel = match pixels.color(x, y) {
+                        Some(color) => color.to_vec(),
+                        None => vec![pixels.get(x, y)],
+                    };
+                    std::iter::repeat_n(pixel, self.scale as usize).flatten()
+                })
+                .collect();
+
+            for _ in 0..self.scale {
+                data.extend_from_slice(&line);
+            }
+        }
+
+        encoder.write_header()?.write_image_data(&data)
+    }
+}
+
+impl FrameSink for PngRecorder {
+    fn present(&mut self, frame: &Frame) {
+        if self.last_pixels.as_ref() == Some(&frame.pixels) {
+            return;
+        }
+        self.last_pixels = Some(frame.pixels.clone());
+
+        if let Err(err) = self.save(frame) {
+            eprintln!("Couldn't save frame {}: {}", frame.number, err);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+/// Counters collected by the emulator, see `Emulator::take_stats`
+pub struct EmulatorStats {
+    pub instructions: u64,
+    pub frames: u64,
+    /// Executions per opcode, indexed by the opcode itself
+    pub opcodes: Vec<u32>,
+    /// Executing instructions, not counting `draw_time`
+    pub cpu_time: Duration,
+    /// Drawing sprites
+    pub draw_time: Duration,
+    /// Decrementing the timers
+    pub timer_time: Duration,
+}
+
+impl EmulatorStats {
+    pub fn new() -> Self {
+        Self {
+            instructions: 0,
+            frames: 0,
+            opcodes: vec![0; 0x10000],
+            cpu_time: Duration::ZERO,
+            draw_time: Duration::ZERO,
+            timer_time: Duration::ZERO,
+        }
+    }
+}
+
+impl Default for EmulatorStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,208 @@
+//! Names for addresses, loaded from the symbol files assemblers write next to a ROM.
+//!
+//! Two formats are understood:
+//!
+//! - JSON as written by Octo, an object with `labels` mapping names to addresses and
+//!   `breakpoints` mapping addresses to names. A plain object of names to addresses works too.
+//!   Addresses are decimal unless they start with `0x`.
+//! - Text with one `address name` pair per line, the address in hex with or without a `0x` or
+//!   `$` prefix. Lines starting with `#` or `;` are comments.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// How far past a symbol an address can be and still be shown relative to it
+const MAX_OFFSET: u16 = 0x100;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Symbols {
+    /// The first name given to each address
+    names: BTreeMap<u16, String>,
+    addresses: HashMap<String, u16>,
+    /// Where the file asks the debugger to stop, Octo's `:breakpoint`
+    breakpoints: Vec<u16>,
+}
+
+#[derive(Debug)]
+pub enum SymbolError {
+    Io(std::io::Error),
+    /// `line` is 0 for JSON files
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolError::Io(err) => write!(f, "{}", err),
+            SymbolError::Parse { line: 0, message } => write!(f, "Invalid symbol file: {}", message),
+            SymbolError::Parse { line, message } => write!(f, "Invalid symbol file, line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+impl From<std::io::Error> for SymbolError {
+    fn from(err: std::io::Error) -> Self {
+        SymbolError::Io(err)
+    }
+}
+
+impl Symbols {
+    pub fn load(path: impl AsRef<Path>) -> Result<Symbols, SymbolError> {
+        Symbols::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Reads either format, JSON if the text starts with `{`
+    pub fn parse(text: &str) -> Result<Symbols, SymbolError> {
+        if text.trim_start().starts_with('{') {
+            Symbols::parse_json(text)
+        } else {
+            Symbols::parse_text(text)
+        }
+    }
+
+    fn parse_json(text: &str) -> Result<Symbols, SymbolError> {
+        let invalid = |message: String| SymbolError::Parse { line: 0, message };
+
+        let json: Value = serde_json::from_str(text).map_err(|err| invalid(err.to_string()))?;
+        let object = json.as_object().ok_or_else(|| invalid("expected an object".to_string()))?;
+
+        let mut symbols = Symbols::default();
+
+        let no_labels = serde_json::Map::new();
+        let labels = match object.get("labels") {
+            Some(labels) => labels.as_object().ok_or_else(|| invalid("labels should be an object".to_string()))?,
+            None if object.contains_key("breakpoints") => &no_labels,
+            None => object,
+        };
+        for (name, address) in labels {
+            let address = json_address(address).ok_or_else(|| invalid(format!("{:?} has no valid address", name)))?;
+            symbols.insert(address, name);
+        }
+
+        if let Some(breakpoints) = object.get("breakpoints") {
+            let breakpoints = breakpoints
+                .as_object()
+                .ok_or_else(|| invalid("breakpoints should be an object".to_string()))?;
+            for (address, name) in breakpoints {
+                let address = json_address(&Value::String(address.clone()))
+                    .ok_or_else(|| invalid(format!("invalid address {:?}", address)))?;
+                if let Some(name) = name.as_str() {
+                    symbols.insert(address, name);
+                }
+                symbols.breakpoints.push(address);
+            }
+        }
+
+        Ok(symbols)
+    }
+
+    fn parse_text(text: &str) -> Result<Symbols, SymbolError> {
+        let mut symbols = Symbols::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            let invalid = |message: String| SymbolError::Parse { line: number + 1, message };
+
+            let mut words = line.split_whitespace();
+            let (address, name) = match (words.next(), words.next(), words.next()) {
+                (Some(address), Some(name), None) => (address, name),
+                _ => return Err(invalid(format!("expected an address and a name, got {:?}", line))),
+            };
+            let address = parse_address(address).ok_or_else(|| invalid(format!("invalid address {:?}", address)))?;
+
+            symbols.insert(address, name);
+        }
+
+        Ok(symbols)
+    }
+
+    /// Where a ROM's symbol file would be, the ROM's path with a `.sym` extension. None if there
+    /// is no such file.
+    pub fn path_for_rom(rom_path: &str) -> Option<PathBuf> {
+        // archives are `game.zip#game.ch8`, the symbols sit next to the archive
+        let rom_path = rom_path.split_once('#').map_or(rom_path, |(archive, _)| archive);
+        let path = Path::new(rom_path).with_extension("sym");
+
+        path.is_file().then_some(path)
+    }
+
+    /// Names `address`, an address that already has a name keeps it but `name` finds it too
+    pub fn insert(&mut self, address: u16, name: &str) {
+        self.names.entry(address).or_insert_with(|| name.to_string());
+        self.addresses.insert(name.to_string(), address);
+    }
+
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    /// An address typed in by the user, either a symbol's name or a hex number
+    pub fn resolve(&self, text: &str) -> Result<u16, String> {
+        let text = text.trim();
+        self.address(text)
+            .or_else(|| parse_address(text))
+            .ok_or_else(|| format!("{:?} is neither a symbol nor an address", text))
+    }
+
+    /// The address as a name, as an offset from the symbol before it like `sprites+0x8`, or
+    /// as hex when there is no symbol close enough
+    pub fn describe(&self, address: u16) -> String {
+        match self.names.range(..=address).next_back() {
+            Some((&start, name)) if start == address => name.clone(),
+            Some((&start, name)) if address - start < MAX_OFFSET => format!("{}+0x{:X}", name, address - start),
+            _ => format!("{:03X}", address),
+        }
+    }
+
+    /// The symbols that start between `start` and `end`, in order
+    pub fn between(&self, start: u16, end: u16) -> impl Iterator<Item = (u16, &str)> {
+        self.names.range(start..end).map(|(address, name)| (*address, name.as_str()))
+    }
+
+    pub fn breakpoints(&self) -> &[u16] {
+        &self.breakpoints
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Hex, with or without a `0x` or `$` prefix
+fn parse_address(text: &str) -> Option<u16> {
+    let hex = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .or_else(|| text.strip_prefix('$'))
+        .unwrap_or(text);
+
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// JSON addresses are numbers, or strings holding a number. Octo writes them in decimal, so
+/// strings are only hex with a prefix.
+fn json_address(value: &Value) -> Option<u16> {
+    match value {
+        Value::Number(number) => number.as_u64().and_then(|number| u16::try_from(number).ok()),
+        Value::String(text) if text.starts_with("0x") || text.starts_with('$') => parse_address(text),
+        Value::String(text) => text.parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Machine cycles in one 60hz frame on the VIP's 1.76 MHz CDP1802 (8 clocks per machine cycle)
+pub const VIP_CYCLES_PER_FRAME: i32 = 3668;
+
+/// Cycles stolen every frame by the CDP1861 display DMA and the interrupt routine, which is
+/// also where the timers are decremented
+pub const VIP_DISPLAY_CYCLES: i32 = 1104;
+
+/// How much code runs in each 60hz frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimingModel {
+    /// A fixed number of instructions per frame
+    InstructionsPerFrame(u32),
+    /// Every instruction costs the machine cycles it takes on a COSMAC VIP, `DXYN` waits for the
+    /// next frame and the timers are decremented by the display interrupt
+    CosmacVip,
+}
+
+impl Default for TimingModel {
+    fn default() -> Self {
+        TimingModel::InstructionsPerFrame(10)
+    }
+}
+
+impl fmt::Display for TimingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimingModel::InstructionsPerFrame(count) => write!(f, "ipf {}", count),
+            TimingModel::CosmacVip => write!(f, "cosmac-vip"),
+        }
+    }
+}
+
+impl FromStr for TimingModel {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().split_once(' ') {
+            Some(("ipf", count)) => count
+                .trim()
+                .parse()
+                .map(TimingModel::InstructionsPerFrame)
+                .map_err(|_| format!("bad instruction count {:?}", count)),
+            None if value.trim() == "cosmac-vip" => Ok(TimingModel::CosmacVip),
+            _ => Err(format!("unknown timing model {:?}", value)),
+        }
+    }
+}
+
+/// Approximate machine cycles an instruction takes on the VIP interpreter, including the
+/// fetch and decode loop. `skipped` adds the cost of a taken conditional skip.
+/// Based on published measurements of a real VIP; `DXYN` and the memory copies vary with
+/// their operands, the figures here scale linearly with the row or register count.
+pub fn vip_cycles(opcode: u16, skipped: bool) -> i32 {
+    let x = ((opcode & 0x0F00) >> 8) as i32;
+    let n = (opcode & 0x000F) as i32;
+    let skip = if skipped { 2 } else { 0 };
+
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => 24,
+            0x00EE => 23,
+            // machine code calls are not emulated
+            _ => 23,
+        },
+        0x1000 | 0x2000 | 0xB000 => 23,
+        0x3000 | 0x4000 => 12 + skip,
+        0x5000 | 0x9000 => 16 + skip,
+        0x6000 => 6,
+        0x7000 => 10,
+        0x8000 => 44,
+        0xA000 => 12,
+        0xC000 => 36,
+        0xD000 => 26 + 17 * n,
+        0xE000 => 16 + skip,
+        0xF000 => match opcode & 0x00FF {
+            0x07 | 0x0A | 0x15 | 0x18 => 10,
+            0x1E => 19,
+            0x29 => 20,
+            0x33 => 204,
+            0x55 | 0x65 => 32 + 14 * (x + 1),
+            _ => 10,
+        },
+        _ => 10,
+    }
+}
+
+/// Emulation speed relative to real time, can be changed while running
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speed {
+    Quarter,
+    Half,
+    Normal,
+    Double,
+    Quadruple,
+    /// No frame limiter, frames run back to back
+    Unlimited,
+}
+
+impl Speed {
+    pub const ALL: [Speed; 6] = [
+        Speed::Quarter,
+        Speed::Half,
+        Speed::Normal,
+        Speed::Double,
+        Speed::Quadruple,
+        Speed::Unlimited,
+    ];
+
+    /// None for unlimited
+    pub fn multiplier(self) -> Option<f64> {
+        match self {
+            Speed::Quarter => Some(0.25),
+            Speed::Half => Some(0.5),
+            Speed::Normal => Some(1.0),
+            Speed::Double => Some(2.0),
+            Speed::Quadruple => Some(4.0),
+            Speed::Unlimited => None,
+        }
+    }
+
+    pub fn faster(self) -> Speed {
+        let index = Speed::ALL.iter().position(|speed| *speed == self).unwrap();
+        Speed::ALL[(index + 1).min(Speed::ALL.len() - 1)]
+    }
+
+    pub fn slower(self) -> Speed {
+        let index = Speed::ALL.iter().position(|speed| *speed == self).unwrap();
+        Speed::ALL[index.saturating_sub(1)]
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.multiplier() {
+            Some(multiplier) => write!(f, "{}x", multiplier),
+            None => write!(f, "Unlimited"),
+        }
+    }
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    /// Accepts what `Display` writes, ignoring case
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Speed::ALL
+            .iter()
+            .find(|speed| speed.to_string().eq_ignore_ascii_case(value.trim()))
+            .copied()
+            .ok_or_else(|| format!("unknown speed {:?}", value))
+    }
+}
+
+/// Frames a clock that fell further behind than this skips instead of running back to back
+const MAX_FRAME_LAG: Duration = Duration::from_millis(250);
+
+/// When the frontend's next frame is due, so it can sleep until then instead of polling
+#[derive(Clone, Debug)]
+pub struct FrameClock {
+    interval: Duration,
+    next_frame: Instant,
+}
+
+impl FrameClock {
+    /// The first frame is due right away
+    pub fn new(frame_rate: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / frame_rate),
+            next_frame: Instant::now(),
+        }
+    }
+
+    /// Zero once the next frame is due
+    pub fn time_until_next(&self) -> Duration {
+        self.next_frame.saturating_duration_since(Instant::now())
+    }
+
+    /// Moves on to the frame after the one that just ran. A clock left far behind, by a slow
+    /// host or a breakpoint, starts counting from now.
+    pub fn advance(&mut self) {
+        self.next_frame += self.interval;
+
+        let now = Instant::now();
+        if now.saturating_duration_since(self.next_frame) > MAX_FRAME_LAG {
+            self.next_frame = now;
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Passes frames from the emulator thread to the renderer without locking either of them.
+//!
+//! A triple buffer: the publisher writes into a slot only it owns, then swaps it with the
+//! shared middle slot. The reader swaps the middle slot for its own when a new frame is there.
+//! Neither side ever waits, the reader always gets the newest complete frame, and a frame is
+//! never read while it is being written.
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::emulator::display::Framebuffer;
+use crate::emulator::sink::{Frame, FrameSink};
+
+/// Set in `Shared::middle` when the middle slot holds a frame the reader hasn't taken
+const NEW_FRAME: usize = 0b100;
+const SLOT_MASK: usize = 0b011;
+
+/// A frame as the reader sees it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PublishedFrame {
+    /// Counts up from 1 with every published frame, 0 until the first one
+    pub number: u64,
+    pub pixels: Framebuffer,
+}
+
+struct Shared {
+    slots: [UnsafeCell<PublishedFrame>; 3],
+    /// Index of the middle slot, with `NEW_FRAME` set while it holds an unread frame
+    middle: AtomicUsize,
+}
+
+// SAFETY: every slot is owned by exactly one of the publisher, the middle and the reader at a
+// time. Ownership only changes hands through the atomic swap on `middle`, whose acquire and
+// release orderings make the writes to a slot visible to whoever takes it next.
+unsafe impl Sync for Shared {}
+
+/// The emulator thread's half
+pub struct FramePublisher {
+    shared: Arc<Shared>,
+    back: usize,
+    published: u64,
+}
+
+/// The renderer's half
+pub struct FrameReader {
+    shared: Arc<Shared>,
+    front: usize,
+}
+
+/// A connected publisher and reader, the reader starts with an empty 64x32 frame numbered 0
+pub fn frame_handoff() -> (FramePublisher, FrameReader) {
+    let shared = Arc::new(Shared {
+        slots: Default::default(),
+        middle: AtomicUsize::new(1),
+    });
+
+    let publisher = FramePublisher {
+        shared: shared.clone(),
+        back: 0,
+        published: 0,
+    };
+    let reader = FrameReader { shared, front: 2 };
+
+    (publisher, reader)
+}
+
+impl FramePublisher {
+    /// Hands a copy of the pixels to the reader, replacing any frame it hasn't taken yet
+    pub fn publish_frame(&mut self, pixels: &Framebuffer) {
+        self.published += 1;
+
+        // SAFETY: the back slot belongs to the publisher until the swap below
+        let slot = unsafe { &mut *self.shared.slots[self.back].get() };
+        slot.number = self.published;
+        slot.pixels.clone_from(pixels);
+
+        let old_middle = self.shared.middle.swap(self.back | NEW_FRAME, Ordering::AcqRel);
+        self.back = old_middle & SLOT_MASK;
+    }
+
+    /// How many frames have been published
+    pub fn frames_published(&self) -> u64 {
+        self.published
+    }
+}
+
+impl FrameSink for FramePublisher {
+    fn present(&mut self, frame: &Frame) {
+        self.publish_frame(&frame.pixels);
+    }
+}
+
+impl FrameReader {
+    /// The newest frame published, the same one again until another arrives
+    pub fn latest_frame(&mut self) -> &PublishedFrame {
+        if self.shared.middle.load(Ordering::Relaxed) & NEW_FRAME != 0 {
+            let old_middle = self.shared.middle.swap(self.front, Ordering::AcqRel);
+            self.front = old_middle & SLOT_MASK;
+        }
+
+        // SAFETY: the front slot belongs to the reader until its next swap, which needs `&mut self`
+        unsafe { &*self.shared.slots[self.front].get() }
+    }
+}
@@ -0,0 +1,194 @@
+//! Game controllers on the CHIP-8 keypad. The buttons each press a key, and the left stick
+//! can stand in for the D-pad.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The controller buttons that can be mapped, named after their place on the pad so they mean
+/// the same on every brand of controller
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PadButton {
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    /// A on an Xbox pad, cross on a PlayStation one
+    South,
+    East,
+    West,
+    North,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+}
+
+/// Which CHIP-8 key each button presses, and how the left stick behaves
+#[derive(Clone, Debug, PartialEq)]
+pub struct PadMapping {
+    /// Indexed like `PadButton::ALL`, None for buttons that press nothing
+    keys: [Option<u8>; 12],
+    /// Whether the left stick presses the D-pad's keys
+    pub stick: bool,
+    /// How far the stick has to move along an axis before it counts, 0.0 to 1.0
+    pub deadzone: f32,
+}
+
+/// Ready-made mappings for the two ways ROMs usually lay out their controls
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PadLayout {
+    /// D-pad on 2/4/6/8 like a phone's number pad, with 5 in the middle on South
+    Numpad,
+    /// D-pad on 5/7/8/9, the keys under WASD in Octo, with 6 on South
+    Octo,
+}
+
+impl PadButton {
+    pub const ALL: [PadButton; 12] = [
+        PadButton::DPadUp,
+        PadButton::DPadDown,
+        PadButton::DPadLeft,
+        PadButton::DPadRight,
+        PadButton::South,
+        PadButton::East,
+        PadButton::West,
+        PadButton::North,
+        PadButton::LeftTrigger,
+        PadButton::RightTrigger,
+        PadButton::Select,
+        PadButton::Start,
+    ];
+
+    fn index(&self) -> usize {
+        PadButton::ALL.iter().position(|button| button == self).unwrap()
+    }
+}
+
+impl fmt::Display for PadButton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PadButton::DPadUp => write!(f, "dpad-up"),
+            PadButton::DPadDown => write!(f, "dpad-down"),
+            PadButton::DPadLeft => write!(f, "dpad-left"),
+            PadButton::DPadRight => write!(f, "dpad-right"),
+            PadButton::South => write!(f, "south"),
+            PadButton::East => write!(f, "east"),
+            PadButton::West => write!(f, "west"),
+            PadButton::North => write!(f, "north"),
+            PadButton::LeftTrigger => write!(f, "left-trigger"),
+            PadButton::RightTrigger => write!(f, "right-trigger"),
+            PadButton::Select => write!(f, "select"),
+            PadButton::Start => write!(f, "start"),
+        }
+    }
+}
+
+impl FromStr for PadButton {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PadButton::ALL
+            .into_iter()
+            .find(|button| button.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                let names: Vec<String> = PadButton::ALL.iter().map(ToString::to_string).collect();
+                format!("unknown controller button {:?}, expected one of {}", s, names.join(", "))
+            })
+    }
+}
+
+impl Default for PadMapping {
+    fn default() -> Self {
+        PadLayout::Numpad.mapping()
+    }
+}
+
+impl PadMapping {
+    /// A mapping where no button presses anything
+    pub fn empty() -> Self {
+        Self {
+            keys: [None; 12],
+            stick: true,
+            deadzone: 0.3,
+        }
+    }
+
+    /// The CHIP-8 key a button presses
+    pub fn key(&self, button: PadButton) -> Option<u8> {
+        self.keys[button.index()]
+    }
+
+    pub fn set_key(&mut self, button: PadButton, key: Option<u8>) {
+        self.keys[button.index()] = key.map(|key| key & 0xF);
+    }
+
+    /// The buttons that press a key, with the key
+    pub fn buttons(&self) -> impl Iterator<Item = (PadButton, u8)> + '_ {
+        PadButton::ALL
+            .into_iter()
+            .filter_map(|button| self.key(button).map(|key| (button, key)))
+    }
+
+    /// The D-pad directions the stick points in, `y` is positive upwards. Each axis counts on
+    /// its own, so diagonals press two directions.
+    pub fn stick_directions(&self, x: f32, y: f32) -> Vec<PadButton> {
+        let mut directions = Vec::new();
+
+        if self.stick {
+            if x < -self.deadzone {
+                directions.push(PadButton::DPadLeft);
+            } else if x > self.deadzone {
+                directions.push(PadButton::DPadRight);
+            }
+            if y > self.deadzone {
+                directions.push(PadButton::DPadUp);
+            } else if y < -self.deadzone {
+                directions.push(PadButton::DPadDown);
+            }
+        }
+
+        directions
+    }
+
+    /// Keypad bitmask for the buttons held and the left stick, bit N set means key N is down
+    pub fn keypad(&self, held: &[PadButton], stick: (f32, f32)) -> u16 {
+        held.iter()
+            .copied()
+            .chain(self.stick_directions(stick.0, stick.1))
+            .filter_map(|button| self.key(button))
+            .fold(0, |keypad, key| keypad | 1 << key)
+    }
+}
+
+impl PadLayout {
+    pub const ALL: [PadLayout; 2] = [PadLayout::Numpad, PadLayout::Octo];
+
+    pub fn mapping(&self) -> PadMapping {
+        let (up, down, left, right, fire) = match self {
+            PadLayout::Numpad => (0x2, 0x8, 0x4, 0x6, 0x5),
+            PadLayout::Octo => (0x5, 0x8, 0x7, 0x9, 0x6),
+        };
+
+        let mut mapping = PadMapping::empty();
+        mapping.set_key(PadButton::DPadUp, Some(up));
+        mapping.set_key(PadButton::DPadDown, Some(down));
+        mapping.set_key(PadButton::DPadLeft, Some(left));
+        mapping.set_key(PadButton::DPadRight, Some(right));
+        mapping.set_key(PadButton::South, Some(fire));
+        mapping.set_key(PadButton::East, Some(0xA));
+        mapping.set_key(PadButton::West, Some(0xB));
+        mapping.set_key(PadButton::North, Some(0xC));
+        mapping.set_key(PadButton::Select, Some(0xE));
+        mapping.set_key(PadButton::Start, Some(0xF));
+        mapping
+    }
+}
+
+impl fmt::Display for PadLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PadLayout::Numpad => write!(f, "2/4/6/8"),
+            PadLayout::Octo => write!(f, "5/7/8/9"),
+        }
+    }
+}
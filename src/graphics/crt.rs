@@ -0,0 +1,144 @@
+//! Draws the emulator's screen through a CRT shader into a texture egui can show
+
+use notan::draw::*;
+use notan::egui::{self, EguiRegisterTexture};
+use notan::prelude::*;
+
+use crate::crt::CrtEffects;
+use crate::State;
+
+//language=glsl
+const CRT_FRAGMENT: ShaderSource = notan::fragment_shader! {
+    r#"
+    #version 450
+    precision mediump float;
+
+    layout(location = 0) in vec2 v_uvs;
+    layout(location = 1) in vec4 v_color;
+
+    layout(binding = 0) uniform sampler2D u_texture;
+    layout(set = 0, binding = 1) uniform Crt {
+        float u_scanlines;
+        float u_curvature;
+        float u_bloom;
+        float u_vignette;
+    };
+
+    layout(location = 0) out vec4 color;
+
+    void main() {
+        // egui shows render textures upside down
+        vec2 uv = vec2(v_uvs.x, 1.0 - v_uvs.y);
+
+        vec2 centered = uv * 2.0 - 1.0;
+        centered *= 1.0 + u_curvature * centered.yx * centered.yx;
+        uv = centered * 0.5 + 0.5;
+        if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+            color = vec4(0.0, 0.0, 0.0, 1.0);
+            return;
+        }
+
+        vec2 size = vec2(textureSize(u_texture, 0));
+        vec2 texel = 1.0 / size;
+        vec3 rgb = texture(u_texture, uv).rgb;
+
+        vec3 glow = vec3(0.0);
+        for (int x = -1; x <= 1; x++) {
+            for (int y = -1; y <= 1; y++) {
+                glow += texture(u_texture, uv + vec2(x, y) * texel).rgb;
+            }
+        }
+        rgb += u_bloom * glow / 9.0;
+
+        // brightest in the middle of each row
+        float row = fract(uv.y * size.y);
+        rgb *= mix(1.0, sin(row * 3.14159265), u_scanlines);
+
+        float edges = 16.0 * uv.x * uv.y * (1.0 - uv.x) * (1.0 - uv.y);
+        rgb *= mix(1.0, pow(edges, 0.25), u_vignette);
+
+        color = vec4(clamp(rgb, 0.0, 1.0), 1.0) * v_color;
+    }
+    "#
+};
+
+pub struct CrtRenderer {
+    pipeline: Pipeline,
+    uniforms: Buffer,
+    target: RenderTexture,
+    tex_id: egui::TextureId,
+}
+
+impl CrtRenderer {
+    /// Fails on renderers without custom shaders or render textures, software ones among them
+    pub fn new(gfx: &mut Graphics, width: f32, height: f32) -> Result<Self, String> {
+        let pipeline = create_image_pipeline(gfx, Some(&CRT_FRAGMENT))?;
+        let uniforms = gfx
+            .create_uniform_buffer(1, "Crt")
+            .with_data(&CrtEffects::default().uniforms())
+            .build()?;
+        let target = gfx
+            .create_render_texture(width as i32, height as i32)
+            .with_filter(TextureFilter::Linear, TextureFilter::Linear)
+            .build()?;
+        let tex_id = gfx.egui_register_texture(&target);
+
+        Ok(Self {
+            pipeline,
+            uniforms,
+            target,
+            tex_id,
+        })
+    }
+
+    /// Draws `source` with the effects over the whole render texture
+    pub fn render(&mut self, gfx: &mut Graphics, source: &Texture, effects: CrtEffects) {
+        gfx.set_buffer_data(&self.uniforms, &effects.uniforms());
+
+        let (width, height) = self.target.size();
+        let mut draw = self.target.create_draw();
+        draw.clear(Color::BLACK);
+        draw.image_pipeline().pipeline(&self.pipeline).uniform_buffer(&self.uniforms);
+        draw.image(source).size(width, height);
+        draw.image_pipeline().remove();
+
+        gfx.render_to(&self.target, &draw);
+    }
+}
+
+/// Size of the render texture for a screen, big enough to give every row a few pixels of
+/// scanline
+fn target_size(width: f32, height: f32) -> (f32, f32) {
+    let scale = (1024.0 / width).floor().max(4.0);
+    (width * scale, height * scale)
+}
+
+/// Applies the selected preset to the emulator's screen, and returns the texture to show. None
+/// means showing the plain screen, because the preset is off or the renderer can't do it.
+pub fn apply(gfx: &mut Graphics, state: &mut State) -> Option<egui::TextureId> {
+    let effects = state.crt_preset.effects();
+    if effects.is_off() || state.crt_error.is_some() {
+        return None;
+    }
+
+    // the platform decides the screen size, and with it the render texture's
+    let (width, height) = state.emulator_out_texture.size();
+    let size = target_size(width, height);
+    if state.crt.as_ref().map(|crt| crt.target.size()) != Some(size) {
+        if let Some(old) = state.crt.take() {
+            gfx.egui_remove_texture(old.tex_id);
+        }
+
+        match CrtRenderer::new(gfx, size.0, size.1) {
+            Ok(crt) => state.crt = Some(crt),
+            Err(err) => {
+                state.crt_error = Some(format!("CRT effects aren't available on this renderer: {}", err));
+                return None;
+            }
+        }
+    }
+
+    let crt = state.crt.as_mut()?;
+    crt.render(gfx, &state.emulator_out_texture, effects);
+    Some(crt.tex_id)
+}
@@ -9,4 +9,675 @@
 
 // struct GUI {
 
-// }
\ No newline at end of file
+// }
+use notan::egui::{self, *};
+
+use crate::crt::CrtPreset;
+use crate::emulator::movie::MovieState;
+use crate::emulator::timing::{Speed, TimingModel};
+use crate::emulator::disasm;
+use crate::emulator::EmulatorCommand;
+use crate::input::KEYPAD_LAYOUT;
+#[cfg(feature = "gamepad")]
+use crate::config::GamepadConfig;
+#[cfg(feature = "gamepad")]
+use crate::gamepad::{PadButton, PadLayout};
+use crate::{DebugInfo, State};
+
+/// On-screen keypad colors, for the keys and the legend under them
+const KEY_HELD: Color32 = Color32::from_rgb(0x30, 0xA0, 0x40);
+const KEY_CHECKED: Color32 = Color32::from_rgb(0xA0, 0x90, 0x30);
+
+/// Forwards a command to the emulator thread, dropped if the thread has exited
+fn send_command(state: &State, command: EmulatorCommand) {
+    if let Some(commands) = &state.emu_commands {
+        let _ = commands.send(command);
+    }
+}
+
+/// Performance numbers and graphs for the debug window
+pub fn perf_stats(ui: &mut Ui, debug_info: &DebugInfo) {
+    ui.label(format!("Instructions: {:.0}/s", debug_info.instructions_per_second));
+    ui.label(format!("Frames: {:.1}/s", debug_info.frames_per_second));
+    ui.label(format!("Speed: {:.0}%", debug_info.speed_ratio * 100.0));
+    ui.label(format!("Frame time: {:.2}ms", debug_info.frame_time));
+    ui.label(format!("Render time: {:.2}ms", debug_info.render_time));
+
+    perf_graph(ui, "ips_graph", "Instructions/s", debug_info, |sample| sample.instructions_per_second);
+    perf_graph(ui, "speed_graph", "Speed %", debug_info, |sample| sample.speed_ratio * 100.0);
+
+    ui.collapsing("Time per subsystem", |ui| {
+        for (name, millis) in &debug_info.subsystem_times {
+            ui.label(format!("{}: {:.1}ms/s", name, millis));
+        }
+    });
+
+    ui.collapsing("Opcodes (last second)", |ui| {
+        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+            for (pattern, count) in &debug_info.opcode_histogram {
+                ui.monospace(format!("{} {:>10}", pattern, count));
+            }
+        });
+    });
+}
+
+/// Scrolling graph of one value over the performance history, newest sample on the right
+fn perf_graph(
+    ui: &mut Ui,
+    id: &str,
+    name: &str,
+    debug_info: &DebugInfo,
+    value: impl Fn(&crate::PerfSample) -> f64,
+) {
+    let offset = crate::PERF_HISTORY_LEN - debug_info.history.len();
+    let points: egui::plot::PlotPoints = debug_info
+        .history
+        .iter()
+        .enumerate()
+        .map(|(second, sample)| [(offset + second) as f64, value(sample)])
+        .collect();
+
+    ui.label(name);
+    egui::plot::Plot::new(id)
+        .height(60.0)
+        .width(200.0)
+        .include_x(0.0)
+        .include_x(crate::PERF_HISTORY_LEN as f64)
+        .include_y(0.0)
+        .show_axes([false, true])
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .show(ui, |plot_ui| plot_ui.line(egui::plot::Line::new(points)));
+}
+
+/// Recording and playback of input movies
+pub fn movie_window(ctx: &egui::Context, state: &mut State) {
+    egui::Window::new("Movie")
+        .collapsible(true)
+        .resizable(false)
+        .anchor(Align2::LEFT_BOTTOM, (0.0, 0.0))
+        .show(ctx, |ui| {
+            let (movie_state, movie_error) = {
+                let debug_info = state.debug_info.lock().unwrap();
+                (debug_info.movie_state, debug_info.movie_error.clone())
+            };
+
+            ui.add(Label::new(match movie_state {
+                MovieState::Idle => String::from("Idle"),
+                MovieState::Recording { frames } => format!("Recording: {} frames", frames),
+                MovieState::Playing { frame, total } => format!("Playing: {}/{}", frame, total),
+            }));
+
+            ui.text_edit_singleline(&mut state.movie_path);
+
+            ui.horizontal(|ui| {
+                if ui.button("Record").clicked() {
+                    send_command(state, EmulatorCommand::StartRecording);
+                }
+
+                if ui.button("Stop & save").clicked() {
+                    send_command(state, EmulatorCommand::StopRecording(state.movie_path.clone()));
+                }
+
+                if ui.button("Play").clicked() {
+                    send_command(state, EmulatorCommand::PlayMovie(state.movie_path.clone()));
+                }
+            });
+
+            if let Some(error) = movie_error {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+}
+
+/// Loading ROMs, with the title, authors and controls of the loaded one from the ROM database
+pub fn rom_window(ctx: &egui::Context, state: &mut State) {
+    egui::Window::new("ROM")
+        .collapsible(true)
+        .resizable(false)
+        .default_width(240.0)
+        .anchor(Align2::RIGHT_BOTTOM, (0.0, 0.0))
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut state.rom_path);
+
+                if ui.button("Load").clicked() {
+                    send_command(state, EmulatorCommand::LoadRom(state.rom_path.clone()));
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut state.symbols_path);
+
+                if ui.button("Load symbols").clicked() {
+                    send_command(state, EmulatorCommand::LoadSymbols(state.symbols_path.clone()));
+                }
+            });
+
+            let debug_info = state.debug_info.lock().unwrap();
+
+            if let Some(error) = &debug_info.symbols_error {
+                ui.colored_label(Color32::RED, error.as_str());
+            } else if !debug_info.symbols.is_empty() {
+                ui.label(format!("{} symbols loaded", debug_info.symbols.len()));
+            }
+            if let Some(error) = &debug_info.rom_error {
+                ui.colored_label(Color32::RED, error.as_str());
+            }
+            for choice in &debug_info.rom_choices {
+                let entry = choice.rsplit_once('#').map_or(choice.as_str(), |(_, entry)| entry);
+                if ui.button(entry).clicked() {
+                    state.rom_path = choice.clone();
+                    send_command(state, EmulatorCommand::LoadRom(choice.clone()));
+                }
+            }
+            for warning in &debug_info.rom_warnings {
+                ui.colored_label(Color32::YELLOW, warning.as_str());
+            }
+
+            ui.separator();
+
+            let info = match &debug_info.rom_info {
+                Some(info) => info,
+                None => {
+                    ui.label("Not in the ROM database");
+                    ui.small(format!("SHA-1 {}", debug_info.rom_sha1));
+                    return;
+                }
+            };
+
+            ui.heading(info.title.as_str());
+            if !info.authors.is_empty() {
+                ui.label(format!("by {}", info.authors.join(", ")));
+            }
+            if let Some(release) = &info.release {
+                ui.label(format!("Released {}", release));
+            }
+            ui.label(format!("Platform: {}", info.platform));
+
+            if let Some(description) = &info.description {
+                ui.separator();
+                ui.label(description.as_str());
+            }
+
+            if !info.key_hints.is_empty() {
+                ui.separator();
+                for (action, key) in &info.key_hints {
+                    let host_key = state.settings.keys.get(*key as usize).map(String::as_str).unwrap_or("?");
+                    ui.label(format!("{:X} ({}): {}", key, host_key.to_uppercase(), action));
+                }
+            }
+        });
+}
+
+/// Timing model and emulation speed
+pub fn timing_window(ctx: &egui::Context, state: &mut State) {
+    egui::Window::new("Timing")
+        .collapsible(true)
+        .resizable(false)
+        .anchor(Align2::RIGHT_TOP, (0.0, 0.0))
+        .show(ctx, |ui| {
+            let timing = state.debug_info.lock().unwrap().timing;
+
+            let mut cosmac_vip = timing == TimingModel::CosmacVip;
+            let mut instructions_per_frame = match timing {
+                TimingModel::InstructionsPerFrame(count) => count,
+                TimingModel::CosmacVip => 10,
+            };
+
+            let mut changed = ui
+                .checkbox(&mut cosmac_vip, "Cycle-accurate COSMAC VIP")
+                .changed();
+
+            ui.add_enabled_ui(!cosmac_vip, |ui| {
+                changed |= ui
+                    .add(Slider::new(&mut instructions_per_frame, 1..=1000).text("Instructions per frame"))
+                    .changed();
+            });
+
+            if changed {
+                let timing = if cosmac_vip {
+                    TimingModel::CosmacVip
+                } else {
+                    TimingModel::InstructionsPerFrame(instructions_per_frame)
+                };
+
+                send_command(state, EmulatorCommand::SetTiming(timing));
+            }
+
+            ui.separator();
+
+            let (speed, realtime_timers) = {
+                let debug_info = state.debug_info.lock().unwrap();
+                (debug_info.speed, debug_info.realtime_timers)
+            };
+
+            let mut new_speed = speed;
+            let mut new_realtime_timers = realtime_timers;
+
+            ui.horizontal(|ui| {
+                for option in Speed::ALL {
+                    ui.selectable_value(&mut new_speed, option, option.to_string());
+                }
+            });
+
+            ui.checkbox(&mut new_realtime_timers, "Keep timers at 60hz, only speed up the CPU");
+            ui.add(Label::new("Hotkeys: - slower, = faster, Tab toggles the frame limiter"));
+
+            if new_speed != speed || new_realtime_timers != realtime_timers {
+                send_command(
+                    state,
+                    EmulatorCommand::SetSpeed {
+                        speed: new_speed,
+                        realtime_timers: new_realtime_timers,
+                    },
+                );
+            }
+
+            ui.separator();
+
+            if ui.button("Remember for this ROM").clicked() {
+                remember_rom_settings(state);
+            }
+
+            if let Some(status) = &state.config_status {
+                ui.label(status.as_str());
+            }
+        });
+}
+
+/// Picks the CRT look under the screen, kept in the config the next time it's saved
+pub fn crt_picker(ui: &mut Ui, state: &mut State) {
+    ui.horizontal(|ui| {
+        ui.label("CRT:");
+        for preset in CrtPreset::ALL {
+            ui.selectable_value(&mut state.crt_preset, preset, preset.to_string());
+        }
+    });
+    state.config.window.crt = state.crt_preset.to_string();
+
+    if let Some(err) = &state.crt_error {
+        ui.label(err.as_str());
+    }
+}
+
+/// Code around the PC with breakpoints in the gutter. Clicking a line runs to it, and after a
+/// single step the registers it changed are listed.
+pub fn disassembly_window(ctx: &egui::Context, state: &mut State) {
+    let (cpu, before_step, paused, breakpoints, platform, quirks, code, code_start, view_address, cpu_error) = {
+        let debug_info = state.debug_info.lock().unwrap();
+        (
+            debug_info.cpu.clone(),
+            debug_info.before_step.clone(),
+            debug_info.paused,
+            debug_info.breakpoints.clone(),
+            debug_info.platform,
+            debug_info.quirks,
+            debug_info.code.clone(),
+            debug_info.code_start,
+            debug_info.view_address,
+            debug_info.cpu_error.clone(),
+        )
+    };
+    let symbols = state.debug_info.lock().unwrap().symbols.clone();
+
+    egui::Window::new("Disassembly")
+        .collapsible(true)
+        .default_open(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if paused {
+                    if ui.button("Run").clicked() {
+                        send_command(state, EmulatorCommand::Resume);
+                    }
+                } else if ui.button("Pause").clicked() {
+                    send_command(state, EmulatorCommand::Pause);
+                }
+                if ui.button("Step").clicked() {
+                    send_command(state, EmulatorCommand::Step);
+                }
+
+                let mut follow = view_address.is_none();
+                if ui.checkbox(&mut follow, "Follow PC").changed() {
+                    state.debug_info.lock().unwrap().view_address = if follow { None } else { Some(cpu.pc) };
+                }
+
+                if let Some(mut address) = view_address {
+                    if ui.add(DragValue::new(&mut address).hexadecimal(3, false, true)).changed() {
+                        state.debug_info.lock().unwrap().view_address = Some(address);
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Break at");
+                ui.text_edit_singleline(&mut state.breakpoint_text);
+
+                let target = symbols.resolve(&state.breakpoint_text);
+                let add = ui
+                    .add_enabled(target.is_ok(), Button::new("Add"))
+                    .on_disabled_hover_text(target.as_ref().err().map_or("", String::as_str));
+                if add.clicked() {
+                    if let Ok(address) = target {
+                        if !breakpoints.contains(&address) {
+                            send_command(state, EmulatorCommand::ToggleBreakpoint(address));
+                        }
+                        state.breakpoint_text.clear();
+                    }
+                }
+            });
+
+            if !breakpoints.is_empty() {
+                ui.collapsing(format!("Breakpoints ({})", breakpoints.len()), |ui| {
+                    for &address in &breakpoints {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(format!("{:03X}  {}", address, symbols.describe(address))).monospace());
+                            if ui.small_button("Remove").clicked() {
+                                send_command(state, EmulatorCommand::ToggleBreakpoint(address));
+                            }
+                        });
+                    }
+                });
+            }
+
+            if let Some(err) = &cpu_error {
+                ui.colored_label(Color32::RED, err);
+            }
+
+            // name the jumps and calls, then decode again so the operands use the names. The
+            // symbol file's names win over the made up ones.
+            let auto_labels = disasm::auto_labels(&disasm::disassemble(&code, code_start, platform, quirks, &|_| None));
+            let label = |address: u16| {
+                symbols
+                    .name(address)
+                    .map(String::from)
+                    .or_else(|| auto_labels.get(&address).cloned())
+            };
+            let lines = disasm::disassemble(&code, code_start, platform, quirks, &label);
+
+            ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                for line in &lines {
+                    if let Some(label) = label(line.address) {
+                        ui.label(RichText::new(format!("{}:", label)).monospace().color(Color32::LIGHT_BLUE));
+                    }
+
+                    ui.horizontal(|ui| {
+                        let gutter = if breakpoints.contains(&line.address) {
+                            RichText::new("●").color(Color32::RED)
+                        } else {
+                            RichText::new("○").color(Color32::DARK_GRAY)
+                        };
+                        if ui.add(Label::new(gutter.monospace()).sense(Sense::click())).clicked() {
+                            send_command(state, EmulatorCommand::ToggleBreakpoint(line.address));
+                        }
+
+                        let is_pc = line.address == cpu.pc;
+                        let text = format!("{:03X}  {:04X}  {}", line.address, line.opcode, line.text);
+                        let response = ui
+                            .selectable_label(is_pc, RichText::new(text).monospace())
+                            .on_hover_text("Click to run to here");
+                        if response.clicked() {
+                            send_command(state, EmulatorCommand::RunTo(line.address));
+                        }
+                        if is_pc && view_address.is_none() {
+                            response.scroll_to_me(Some(Align::Center));
+                        }
+                    });
+                }
+            });
+
+            ui.separator();
+
+            for (half, registers) in cpu.v_reg.chunks(8).enumerate() {
+                let text: Vec<String> = registers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| format!("V{:X}={:02X}", half * 8 + i, value))
+                    .collect();
+                ui.label(RichText::new(text.join(" ")).monospace());
+            }
+            ui.label(
+                RichText::new(format!(
+                    "PC={:03X} I={:03X} DT={:02X} ST={:02X}",
+                    cpu.pc, cpu.idx, cpu.dly_timer, cpu.snd_timer
+                ))
+                .monospace(),
+            );
+
+            if let Some(before) = &before_step {
+                let changes = before.changes(&cpu);
+                if changes.is_empty() {
+                    ui.label("The last step changed no registers");
+                } else {
+                    ui.label(format!("The last step changed {}", changes.join(", ")));
+                }
+            }
+        });
+}
+
+/// The return addresses on the stack, innermost call first
+pub fn call_stack_window(ctx: &egui::Context, state: &mut State) {
+    let (call_stack, depth, cpu_error, symbols) = {
+        let debug_info = state.debug_info.lock().unwrap();
+        (
+            debug_info.call_stack.clone(),
+            debug_info.quirks.stack_depth,
+            debug_info.cpu_error.clone(),
+            debug_info.symbols.clone(),
+        )
+    };
+
+    egui::Window::new("Call stack")
+        .collapsible(true)
+        .default_open(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("{} / {} return addresses", call_stack.len(), depth));
+            if let Some(err) = &cpu_error {
+                ui.colored_label(Color32::RED, err);
+            }
+
+            ui.separator();
+
+            if call_stack.is_empty() {
+                ui.label("Not in a subroutine");
+            }
+            for frame in &call_stack {
+                let subroutine = match frame.subroutine {
+                    Some(address) => symbols
+                        .name(address)
+                        .map_or_else(|| format!("sub_{:03X}", address), String::from),
+                    None => "???".to_string(),
+                };
+                let text = format!("{:<12} returns to {}", subroutine, symbols.describe(frame.return_address));
+                if ui
+                    .selectable_label(false, RichText::new(text).monospace())
+                    .on_hover_text("Click to show the return address in the disassembly")
+                    .clicked()
+                {
+                    state.debug_info.lock().unwrap().view_address = Some(frame.return_address);
+                }
+            }
+        });
+}
+
+/// Hex dump of memory, with the symbol file's names above the rows they start in
+pub fn memory_window(ctx: &egui::Context, state: &mut State) {
+    let (memory, start, symbols) = {
+        let debug_info = state.debug_info.lock().unwrap();
+        (debug_info.memory.clone(), debug_info.memory_address, debug_info.symbols.clone())
+    };
+
+    egui::Window::new("Memory")
+        .collapsible(true)
+        .default_open(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Address");
+                ui.text_edit_singleline(&mut state.memory_text);
+            });
+
+            // a symbol or hex, follow it as soon as it names something
+            match symbols.resolve(&state.memory_text) {
+                Ok(address) if address != start => state.debug_info.lock().unwrap().memory_address = address,
+                Ok(_) => {}
+                Err(err) => {
+                    ui.colored_label(Color32::YELLOW, err);
+                }
+            }
+
+            ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                for (row, bytes) in memory.chunks(8).enumerate() {
+                    let address = start.wrapping_add(row as u16 * 8);
+
+                    for (_, name) in symbols.between(address, address.saturating_add(8)) {
+                        ui.label(RichText::new(format!("{}:", name)).monospace().color(Color32::LIGHT_BLUE));
+                    }
+
+                    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                    let ascii: String = bytes
+                        .iter()
+                        .map(|&byte| if byte.is_ascii_graphic() { byte as char } else { '.' })
+                        .collect();
+                    ui.label(RichText::new(format!("{:03X}  {}  {}", address, hex.join(" "), ascii)).monospace());
+                }
+            });
+        });
+}
+
+/// The COSMAC VIP keypad, showing which keys are held and which the program is looking at.
+/// Keys can be held down with the mouse or a touch.
+pub fn keypad_window(ctx: &egui::Context, state: &mut State) {
+    let (held, checked) = {
+        let debug_info = state.debug_info.lock().unwrap();
+        (debug_info.keypad | state.virtual_keys, debug_info.keys_checked)
+    };
+    // nothing stays held while the window is collapsed
+    state.virtual_keys = 0;
+
+    egui::Window::new("Keypad")
+        .collapsible(true)
+        .resizable(false)
+        .anchor(Align2::LEFT_CENTER, (0.0, 0.0))
+        .show(ctx, |ui| {
+            let mut virtual_keys = 0;
+
+            egui::Grid::new("keypad").spacing((4.0, 4.0)).show(ui, |ui| {
+                for row in KEYPAD_LAYOUT {
+                    for key in row {
+                        // the host key underneath, to learn the mapping
+                        let text = format!("{:X}\n{}", key, state.settings.keys[key as usize].to_uppercase());
+                        let mut button = egui::Button::new(RichText::new(text).monospace()).min_size(vec2(40.0, 40.0));
+
+                        if held & 1 << key != 0 {
+                            button = button.fill(KEY_HELD);
+                        } else if checked & 1 << key != 0 {
+                            button = button.fill(KEY_CHECKED);
+                        }
+
+                        if ui.add(button).is_pointer_button_down_on() {
+                            virtual_keys |= 1 << key;
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+
+            ui.colored_label(KEY_HELD, "held");
+            ui.colored_label(KEY_CHECKED, "checked by the program");
+
+            state.virtual_keys = virtual_keys;
+        });
+}
+
+/// Edits which CHIP-8 key each controller button presses
+#[cfg(feature = "gamepad")]
+pub fn gamepad_window(ctx: &egui::Context, state: &mut State) {
+    egui::Window::new("Controller")
+        .collapsible(true)
+        .default_open(false)
+        .resizable(false)
+        .anchor(Align2::LEFT_BOTTOM, (0.0, 0.0))
+        .show(ctx, |ui| {
+            if state.gilrs.is_none() {
+                ui.colored_label(Color32::YELLOW, "Game controllers can't be read on this system");
+            }
+
+            let mapping = &mut state.settings.gamepad;
+
+            ui.horizontal(|ui| {
+                ui.label("Layout:");
+                for layout in PadLayout::ALL {
+                    if ui.button(layout.to_string()).clicked() {
+                        let (stick, deadzone) = (mapping.stick, mapping.deadzone);
+                        *mapping = layout.mapping();
+                        mapping.stick = stick;
+                        mapping.deadzone = deadzone;
+                    }
+                }
+            });
+
+            egui::Grid::new("gamepad_mapping").show(ui, |ui| {
+                for button in PadButton::ALL {
+                    // held buttons light up, to find which one is which
+                    let name = if state.gamepad_held.contains(&button) {
+                        RichText::new(button.to_string()).color(Color32::GREEN)
+                    } else {
+                        RichText::new(button.to_string())
+                    };
+                    ui.label(name);
+
+                    let mut key = mapping.key(button);
+                    egui::ComboBox::from_id_source(button)
+                        .selected_text(key.map_or("-".to_string(), |key| format!("{:X}", key)))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut key, None, "-");
+                            for chip8_key in 0..16 {
+                                ui.selectable_value(&mut key, Some(chip8_key), format!("{:X}", chip8_key));
+                            }
+                        });
+                    mapping.set_key(button, key);
+                    ui.end_row();
+                }
+            });
+
+            ui.checkbox(&mut mapping.stick, "Left stick presses the D-pad's keys");
+            ui.add_enabled(mapping.stick, Slider::new(&mut mapping.deadzone, 0.0..=0.9).text("Deadzone"));
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui.button("Remember for this ROM").clicked() {
+                    let rom_sha1 = state.debug_info.lock().unwrap().rom_sha1.clone();
+                    state.config.remember_gamepad(&rom_sha1, &state.settings.gamepad);
+                    save_config(state);
+                }
+                if ui.button("Use for every ROM").clicked() {
+                    state.config.gamepad = GamepadConfig::from(&state.settings.gamepad);
+                    save_config(state);
+                }
+            });
+        });
+}
+
+/// Saves the current speed, timing and quirks as the loaded ROM's overrides in the config
+fn remember_rom_settings(state: &mut State) {
+    let (rom_sha1, speed, timing, quirks) = {
+        let debug_info = state.debug_info.lock().unwrap();
+        (debug_info.rom_sha1.clone(), debug_info.speed, debug_info.timing, debug_info.quirks)
+    };
+
+    state.config.remember_rom(&rom_sha1, speed, timing, quirks);
+    save_config(state);
+}
+
+fn save_config(state: &mut State) {
+    state.config_status = Some(match state.config.save() {
+        Ok(()) => "Saved".to_string(),
+        Err(err) => format!("Couldn't save the config: {}", err),
+    });
+}
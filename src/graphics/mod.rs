@@ -1,37 +1,35 @@
-use notan::draw::*;
 use notan::egui::{self, *};
 use notan::prelude::*;
 
 use super::State;
 
+pub mod crt;
 pub mod gui;
 
 pub fn render(app: &mut App, gfx: &mut Graphics, plugins: &mut Plugins, state: &mut State) {
-    let display_buffer = state.display_buffer.lock().unwrap();
+    let render_start = std::time::Instant::now();
+    // only convert and upload when there is a new frame or the palette changed
+    let latest = state.frames.latest_frame();
+    let frame = (latest.number, state.settings.foreground, state.settings.background);
 
-    // read buffer
-    let buffer = display_buffer.current_buffer().clone();
+    if state.emulator_out_frame != Some(frame) {
+        let (width, height) = (latest.pixels.width(), latest.pixels.height());
+        latest.pixels.write_rgba(&mut state.emulator_out_pixels, frame.1, frame.2);
 
-    // draw the now inactive buffer to the render texture
-    let mut draw = state.emulator_out_texture.create_draw();
-
-    draw.clear(Color {
-        r: 0.0,
-        g: 0.0,
-        b: 0.0,
-        a: 1.0,
-    });
-
-    // draw the buffer to the render texture
-    for (i, row) in buffer.iter().enumerate() {
-        for (j, col) in row.iter().enumerate() {
-            if *col == 255 {
-                draw.rect((j as f32, i as f32), (1.0, 1.0));
-            }
+        // the platform decides the screen size
+        if state.emulator_out_texture.size() != (width as f32, height as f32) {
+            state.resize_emulator_texture(gfx, width, height);
         }
+
+        gfx.update_texture(&mut state.emulator_out_texture)
+            .with_data(&state.emulator_out_pixels)
+            .update()
+            .unwrap();
+        state.emulator_out_frame = Some(frame);
     }
 
-    gfx.render_to(&state.emulator_out_texture, &draw);
+    let (width, height) = state.emulator_out_texture.size();
+    let screen = crt::apply(gfx, state).unwrap_or(state.emulator_out_tex_id);
 
     // create an egui output
     let mut output = plugins.egui(|ctx| {
@@ -39,28 +37,42 @@ pub fn render(app: &mut App, gfx: &mut Graphics, plugins: &mut Plugins, state: &
             .title_bar(false)
             .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
             .show(ctx, |ui| {
-                // draw the render texture to egui
-                let size: egui::Vec2 = state.emulator_out_texture.size().into();
-                ui.image(state.emulator_out_tex_id, (512.0, 256.0));
+                // draw the texture to egui, 512 pixels wide whatever the resolution
+                let scale = 512.0 / width;
+                ui.image(screen, (width * scale, height * scale));
+                gui::crt_picker(ui, state);
             });
 
-        egui::Window::new("Debug")
-            .vscroll(false)
-            .hscroll(false)
-            .collapsible(true)
-            .default_width(100.0)
-            .anchor(Align2::LEFT_TOP, (0.0, 0.0))
-            .resizable(false)
-            .show(ctx, |ui| {
-                ui.add(Label::new(format!(
-                    "Clock speed: {}hz",
-                    state.debug_info.lock().unwrap().clock_speed
-                )));
-                ui.add(Label::new(format!(
-                    "Frame time: {:04}ms",
-                    state.debug_info.lock().unwrap().frame_time
-                )));
-            });
+        if state.config.window.show_debug {
+            egui::Window::new("Debug")
+                .vscroll(false)
+                .hscroll(false)
+                .collapsible(true)
+                .default_width(100.0)
+                .anchor(Align2::LEFT_TOP, (0.0, 0.0))
+                .resizable(false)
+                .show(ctx, |ui| {
+                    gui::perf_stats(ui, &state.debug_info.lock().unwrap());
+                });
+        }
+
+        if state.config.window.show_movie {
+            gui::movie_window(ctx, state);
+        }
+        if state.config.window.show_timing {
+            gui::timing_window(ctx, state);
+        }
+        gui::rom_window(ctx, state);
+        gui::disassembly_window(ctx, state);
+        gui::call_stack_window(ctx, state);
+        gui::memory_window(ctx, state);
+        if state.config.window.show_keypad {
+            gui::keypad_window(ctx, state);
+        }
+        #[cfg(feature = "gamepad")]
+        gui::gamepad_window(ctx, state);
+
+        state.typing = ctx.wants_keyboard_input();
 
         if state.emu_thread_handle.as_ref().unwrap().is_finished() {
             // Modal error message
@@ -77,4 +89,6 @@ pub fn render(app: &mut App, gfx: &mut Graphics, plugins: &mut Plugins, state: &
 
     output.clear_color(Color::BLACK);
     gfx.render(&output);
+
+    state.debug_info.lock().unwrap().render_time = render_start.elapsed().as_secs_f32() * 1000.0;
 }
@@ -0,0 +1,181 @@
+#[cfg(feature = "gui")]
+use notan::prelude::*;
+
+#[cfg(feature = "gui")]
+use crate::emulator::timing::Speed;
+#[cfg(feature = "gamepad")]
+use crate::gamepad::PadButton;
+
+/// The character on each host key for CHIP-8 keys 0-F, the same layout as `KEY_MAP` for
+/// frontends that read characters rather than key codes
+pub const KEY_CHARS: [char; 16] = [
+    'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+];
+
+/// The COSMAC VIP keypad as it looks, top row first
+pub const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// Host key for each CHIP-8 key 0-F, the left side of a QWERTY keyboard laid out like the
+/// COSMAC VIP keypad:
+///
+/// ```text
+/// 1 2 3 4      1 2 3 C
+/// Q W E R  ->  4 5 6 D
+/// A S D F      7 8 9 E
+/// Z X C V      A 0 B F
+/// ```
+#[cfg(feature = "gui")]
+pub const KEY_MAP: [KeyCode; 16] = [
+    KeyCode::X,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Q,
+    KeyCode::W,
+    KeyCode::E,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::Z,
+    KeyCode::C,
+    KeyCode::Key4,
+    KeyCode::R,
+    KeyCode::F,
+    KeyCode::V,
+];
+
+/// The key with the given letter or digit on it
+#[cfg(feature = "gui")]
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    let key = match name.to_ascii_lowercase().as_str() {
+        "0" => KeyCode::Key0,
+        "1" => KeyCode::Key1,
+        "2" => KeyCode::Key2,
+        "3" => KeyCode::Key3,
+        "4" => KeyCode::Key4,
+        "5" => KeyCode::Key5,
+        "6" => KeyCode::Key6,
+        "7" => KeyCode::Key7,
+        "8" => KeyCode::Key8,
+        "9" => KeyCode::Key9,
+        "a" => KeyCode::A,
+        "b" => KeyCode::B,
+        "c" => KeyCode::C,
+        "d" => KeyCode::D,
+        "e" => KeyCode::E,
+        "f" => KeyCode::F,
+        "g" => KeyCode::G,
+        "h" => KeyCode::H,
+        "i" => KeyCode::I,
+        "j" => KeyCode::J,
+        "k" => KeyCode::K,
+        "l" => KeyCode::L,
+        "m" => KeyCode::M,
+        "n" => KeyCode::N,
+        "o" => KeyCode::O,
+        "p" => KeyCode::P,
+        "q" => KeyCode::Q,
+        "r" => KeyCode::R,
+        "s" => KeyCode::S,
+        "t" => KeyCode::T,
+        "u" => KeyCode::U,
+        "v" => KeyCode::V,
+        "w" => KeyCode::W,
+        "x" => KeyCode::X,
+        "y" => KeyCode::Y,
+        "z" => KeyCode::Z,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Key codes for configured key names, keeping the default for any name that isn't a key
+#[cfg(feature = "gui")]
+pub fn key_map(names: &[String; 16]) -> [KeyCode; 16] {
+    let mut map = KEY_MAP;
+
+    for (key, name) in map.iter_mut().zip(names.iter()) {
+        if let Some(code) = key_code(name) {
+            *key = code;
+        }
+    }
+
+    map
+}
+
+/// Keypad bitmask for the keys currently held, bit N set means key N is down
+#[cfg(feature = "gui")]
+pub fn keypad_state(keyboard: &Keyboard, key_map: &[KeyCode; 16]) -> u16 {
+    key_map
+        .iter()
+        .enumerate()
+        .filter(|(_, key)| keyboard.is_down(**key))
+        .fold(0, |keypad, (chip8_key, _)| keypad | 1 << chip8_key)
+}
+
+/// The buttons held on every connected controller, and the left stick pushed furthest
+#[cfg(feature = "gamepad")]
+pub fn gamepad_input(gilrs: &mut gilrs::Gilrs) -> (Vec<PadButton>, (f32, f32)) {
+    // gilrs only updates the button states as its events are taken
+    while gilrs.next_event().is_some() {}
+
+    let mut held = Vec::new();
+    let mut stick = (0.0f32, 0.0f32);
+
+    for (_, pad) in gilrs.gamepads() {
+        for button in PadButton::ALL {
+            if pad.is_pressed(gilrs_button(button)) && !held.contains(&button) {
+                held.push(button);
+            }
+        }
+
+        let pad_stick = (pad.value(gilrs::Axis::LeftStickX), pad.value(gilrs::Axis::LeftStickY));
+        if pad_stick.0.hypot(pad_stick.1) > stick.0.hypot(stick.1) {
+            stick = pad_stick;
+        }
+    }
+
+    (held, stick)
+}
+
+#[cfg(feature = "gamepad")]
+fn gilrs_button(button: PadButton) -> gilrs::Button {
+    match button {
+        PadButton::DPadUp => gilrs::Button::DPadUp,
+        PadButton::DPadDown => gilrs::Button::DPadDown,
+        PadButton::DPadLeft => gilrs::Button::DPadLeft,
+        PadButton::DPadRight => gilrs::Button::DPadRight,
+        PadButton::South => gilrs::Button::South,
+        PadButton::East => gilrs::Button::East,
+        PadButton::West => gilrs::Button::West,
+        PadButton::North => gilrs::Button::North,
+        PadButton::LeftTrigger => gilrs::Button::LeftTrigger,
+        PadButton::RightTrigger => gilrs::Button::RightTrigger,
+        PadButton::Select => gilrs::Button::Select,
+        PadButton::Start => gilrs::Button::Start,
+    }
+}
+
+/// Speed picked with the hotkeys: `-` slower, `=` faster and `Tab` toggles the frame limiter
+#[cfg(feature = "gui")]
+pub fn speed_hotkey(keyboard: &Keyboard, speed: Speed) -> Option<Speed> {
+    if keyboard.was_pressed(KeyCode::Minus) {
+        Some(speed.slower())
+    } else if keyboard.was_pressed(KeyCode::Equals) {
+        Some(speed.faster())
+    } else if keyboard.was_pressed(KeyCode::Tab) {
+        Some(if speed == Speed::Unlimited {
+            Speed::Normal
+        } else {
+            Speed::Unlimited
+        })
+    } else {
+        None
+    }
+}
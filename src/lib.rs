@@ -1,113 +1,369 @@
-use std::sync::{Mutex, Arc};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+#[cfg(feature = "gui")]
+use std::sync::{mpsc::Sender, Arc, Mutex};
+#[cfg(feature = "gui")]
 use std::time::Instant;
+#[cfg(feature = "gui")]
 use notan::egui::{self, *};
+#[cfg(feature = "gui")]
 use notan::prelude::*;
+#[cfg(feature = "gui")]
 use fixedstep::FixedStep;
 
+pub mod audio;
+pub mod config;
+pub mod crt;
 pub mod emulator;
+pub mod frame_handoff;
+pub mod gamepad;
+#[cfg(feature = "gui")]
 pub mod graphics;
+pub mod input;
+pub mod romdb;
+pub mod testing;
 
-pub struct DisplayBuffer {
-    buffer: [[[u8; 64]; 32]; 2],
-    current_buffer: usize,
-}
+#[cfg(feature = "gui")]
+use config::{Config, RomSettings};
+#[cfg(feature = "gui")]
+use crt::CrtPreset;
+#[cfg(feature = "gui")]
+use emulator::EmulatorCommand;
+#[cfg(feature = "gui")]
+use graphics::crt::CrtRenderer;
+#[cfg(feature = "gamepad")]
+use gamepad::PadButton;
+#[cfg(feature = "audio")]
+use audio::AudioOutput;
+#[cfg(feature = "gui")]
+use frame_handoff::{frame_handoff, FramePublisher, FrameReader};
+use emulator::cpu::Instruction;
+use emulator::platform::Platform;
+use emulator::debugger::StackFrame;
+use emulator::CpuState;
+use emulator::movie::MovieState;
+use emulator::quirks::Quirks;
+use emulator::rom::{self, RomError, RomWarning};
+use emulator::stats::EmulatorStats;
+use emulator::symbols::Symbols;
+use emulator::timing::{Speed, TimingModel};
+use romdb::RomInfo;
 
-impl DisplayBuffer {
-    pub fn new() -> Self {
-        DisplayBuffer {
-            buffer: [[[0; 64]; 32]; 2],
-            current_buffer: 0,
-        }
-    }
+/// Seconds of performance history kept for the graphs
+pub const PERF_HISTORY_LEN: usize = 120;
 
-    pub fn swap_buffers(&mut self) {
-        self.current_buffer = 1 - self.current_buffer;
-    }
-    
-    pub fn current_buffer_index(&self) -> usize {
-        self.current_buffer
-    }
+/// One second of performance numbers
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PerfSample {
+    pub instructions_per_second: f64,
+    pub frames_per_second: f64,
+    /// Emulated time over host time, 1.0 is full speed
+    pub speed_ratio: f64,
+}
 
-    pub fn current_buffer(&self) -> &[[u8; 64]; 32] {
-        &self.buffer[self.current_buffer]
-    }
+pub struct DebugInfo {
+    pub instructions_per_second: f64,
+    pub frames_per_second: f64,
+    /// Emulated time over host time, 1.0 is full speed
+    pub speed_ratio: f64,
+    /// Executions per instruction pattern in the last second, most frequent first
+    pub opcode_histogram: Vec<(&'static str, u64)>,
+    /// Milliseconds per second spent in each part of the emulator
+    pub subsystem_times: Vec<(&'static str, f64)>,
+    /// Oldest first, one sample per second
+    pub history: VecDeque<PerfSample>,
+    pub frame_time: f32,
+    /// How long the last GUI frame took to build and render, in milliseconds
+    pub render_time: f32,
+    pub movie_state: MovieState,
+    pub movie_error: Option<String>,
+    pub timing: TimingModel,
+    pub speed: Speed,
+    pub realtime_timers: bool,
+    pub quirks: Quirks,
+    /// Keys held in the last frame, bit N for key N
+    pub keypad: u16,
+    /// Keys the program looked at in the last frame
+    pub keys_checked: u16,
+    /// Registers at the end of the last frame or step
+    pub cpu: CpuState,
+    /// Registers before the last single step, to show what it changed
+    pub before_step: Option<CpuState>,
+    pub paused: bool,
+    pub breakpoints: Vec<u16>,
+    /// Innermost call first
+    pub call_stack: Vec<StackFrame>,
+    /// Why the program stopped, like a stack overflow
+    pub cpu_error: Option<String>,
+    /// What the loaded ROM runs on, decides how code is disassembled
+    pub platform: Platform,
+    /// Memory around `view_address` for the disassembly, starting at `code_start`
+    pub code: Vec<u8>,
+    pub code_start: u16,
+    /// Where the disassembly looks, set by the GUI. None follows the PC.
+    pub view_address: Option<u16>,
+    /// `MEMORY_WINDOW` bytes from `memory_address`, for the memory viewer
+    pub memory: Vec<u8>,
+    /// Where the memory viewer starts, set by the GUI
+    pub memory_address: u16,
+    /// Names for addresses, from the ROM's symbol file
+    pub symbols: std::sync::Arc<Symbols>,
+    /// Why the last symbol file didn't load
+    pub symbols_error: Option<String>,
+    /// SHA-1 of the loaded ROM, see `Emulator::rom_sha1`
+    pub rom_sha1: String,
+    /// What the ROM database knows about the loaded ROM
+    pub rom_info: Option<RomInfo>,
+    /// Why the last ROM didn't load
+    pub rom_error: Option<String>,
+    pub rom_warnings: Vec<String>,
+    /// Paths to pick from when the last ROM was an archive holding several
+    pub rom_choices: Vec<String>,
+}
 
-    pub fn current_buffer_mut(&mut self) -> &mut [[u8; 64]; 32] {
-        &mut self.buffer[self.current_buffer]
+impl Default for DebugInfo {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn inactive_buffer(&self) -> &[[u8; 64]; 32] {
-        &self.buffer[1 - self.current_buffer]
+impl DebugInfo {
+    pub fn new() -> Self {
+        Self {
+            instructions_per_second: 0.0,
+            frames_per_second: 0.0,
+            speed_ratio: 0.0,
+            opcode_histogram: Vec::new(),
+            subsystem_times: Vec::new(),
+            history: VecDeque::with_capacity(PERF_HISTORY_LEN),
+            frame_time: 0.0,
+            render_time: 0.0,
+            movie_state: MovieState::Idle,
+            movie_error: None,
+            timing: TimingModel::default(),
+            speed: Speed::Normal,
+            realtime_timers: false,
+            quirks: Quirks::default(),
+            keypad: 0,
+            keys_checked: 0,
+            cpu: CpuState::default(),
+            before_step: None,
+            paused: false,
+            breakpoints: Vec::new(),
+            call_stack: Vec::new(),
+            cpu_error: None,
+            platform: Platform::default(),
+            code: Vec::new(),
+            code_start: 0,
+            view_address: None,
+            memory: Vec::new(),
+            memory_address: rom::PROGRAM_START as u16,
+            symbols: Default::default(),
+            symbols_error: None,
+            rom_sha1: String::new(),
+            rom_info: None,
+            rom_error: None,
+            rom_warnings: Vec::new(),
+            rom_choices: Vec::new(),
+        }
     }
 
-    pub fn inactive_buffer_mut(&mut self) -> &mut [[u8; 64]; 32] {
-        &mut self.buffer[1 - self.current_buffer]
+    /// Shows the outcome of loading a ROM
+    pub fn record_rom_load(&mut self, result: &Result<Vec<RomWarning>, RomError>) {
+        match result {
+            Ok(warnings) => {
+                self.rom_error = None;
+                self.rom_warnings = warnings.iter().map(ToString::to_string).collect();
+                self.rom_choices.clear();
+            }
+            Err(err) => {
+                self.rom_error = Some(err.to_string());
+                self.rom_warnings.clear();
+                self.rom_choices = err.choices();
+            }
+        }
     }
 
-    /// Set the buffer at the given index, index -1 means current buffer, -2 means inactive buffer
-    pub fn set_buffer(&mut self, index: isize, buffer: [[u8; 64]; 32]) {
-        if index < 0 {
-            self.buffer[self.current_buffer] = buffer;
-            return;
-        }
+    /// Updates the performance numbers from the stats collected over `elapsed`
+    pub fn record_stats(&mut self, stats: &EmulatorStats, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
 
-        if index < -1 {
-            self.buffer[1 - self.current_buffer] = buffer;
-            return;
-        }
+        self.instructions_per_second = stats.instructions as f64 / seconds;
+        self.frames_per_second = stats.frames as f64 / seconds;
+        // every frame is 1/60th of a second of emulated time
+        self.speed_ratio = self.frames_per_second / 60.0;
 
-        self.buffer[index as usize] = buffer;
-    }
+        let mut histogram: Vec<(&'static str, u64)> = Vec::new();
+        for (opcode, count) in stats.opcodes.iter().enumerate().filter(|(_, count)| **count > 0) {
+            let pattern = Instruction::parse_u16(opcode as u16).pattern();
 
-    pub fn clear(&mut self, index: usize) {
-        self.buffer[index] = [[0; 64]; 32];
-    }
-}
+            match histogram.iter_mut().find(|(known, _)| *known == pattern) {
+                Some((_, total)) => *total += *count as u64,
+                None => histogram.push((pattern, *count as u64)),
+            }
+        }
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        self.opcode_histogram = histogram;
 
-pub struct DebugInfo {
-    pub clock_speed: u64,
-    pub frame_time: f32,
-}
+        let per_second = |time: Duration| time.as_secs_f64() * 1000.0 / seconds;
+        self.subsystem_times = vec![
+            ("CPU", per_second(stats.cpu_time)),
+            ("Drawing", per_second(stats.draw_time)),
+            ("Timers", per_second(stats.timer_time)),
+        ];
 
-impl DebugInfo {
-    pub fn new() -> Self {
-        Self {
-            clock_speed: 0,
-            frame_time: 0.0,
+        if self.history.len() == PERF_HISTORY_LEN {
+            self.history.pop_front();
         }
+        self.history.push_back(PerfSample {
+            instructions_per_second: self.instructions_per_second,
+            frames_per_second: self.frames_per_second,
+            speed_ratio: self.speed_ratio,
+        });
     }
 }
 
+#[cfg(feature = "gui")]
 #[derive(AppState)]
 pub struct State {
-    pub emulator_out_texture: RenderTexture,
+    /// The emulator's screen at one texel per pixel, scaled up by egui
+    pub emulator_out_texture: Texture,
     pub emulator_out_tex_id: egui::TextureId,
-    pub display_buffer: Arc<Mutex<DisplayBuffer>>,
+    /// RGBA bytes last uploaded to `emulator_out_texture`, kept to reuse the allocation
+    pub emulator_out_pixels: Vec<u8>,
+    /// The frame number and palette the texture shows, None before the first upload
+    pub emulator_out_frame: Option<(u64, [u8; 3], [u8; 3])>,
+    /// The look selected for the screen
+    pub crt_preset: CrtPreset,
+    /// Draws the look, created the first time a preset other than off is picked
+    pub crt: Option<CrtRenderer>,
+    /// Why the renderer can't draw the look, the plain screen is shown instead
+    pub crt_error: Option<String>,
+    /// Frames from the emulator thread
+    pub frames: FrameReader,
+    /// Taken by the emulator thread when it starts
+    pub frame_publisher: Option<FramePublisher>,
     pub debug_info: Arc<Mutex<DebugInfo>>,
+    /// Keypad bitmask read by the emulator thread every frame
+    pub keypad: Arc<Mutex<u16>>,
+    /// Keys held down with the mouse on the on-screen keypad
+    pub virtual_keys: u16,
+    /// A text field has the keyboard, what is typed shouldn't press keys or change the speed
+    pub typing: bool,
+    pub emu_commands: Option<Sender<EmulatorCommand>>,
     pub emu_thread_handle: Option<std::thread::JoinHandle<isize>>,
     pub last_frame: Instant,
     pub render_timer: FixedStep,
+    pub movie_path: String,
+    pub rom_path: String,
+    pub symbols_path: String,
+    /// A symbol name or hex address typed into the disassembly window to break at
+    pub breakpoint_text: String,
+    /// What the memory viewer's address field holds, applied once it names an address
+    pub memory_text: String,
+    pub config: Config,
+    /// Settings for the loaded ROM
+    pub settings: RomSettings,
+    /// Which ROM `settings` and `key_map` were resolved for
+    pub settings_rom: String,
+    pub key_map: [KeyCode; 16],
+    /// Result of the last attempt to save the config, shown in the GUI
+    pub config_status: Option<String>,
+    /// None when controllers can't be read on this system
+    #[cfg(feature = "gamepad")]
+    pub gilrs: Option<gilrs::Gilrs>,
+    /// Controller buttons held in the last update, shown in the mapping editor
+    #[cfg(feature = "gamepad")]
+    pub gamepad_held: Vec<PadButton>,
+    /// None when the buzzer is turned off or there's nothing to play it on
+    #[cfg(feature = "audio")]
+    pub audio: Option<AudioOutput>,
     // TODO: Add UI state to this so it can be edited from anywhere
 }
 
+#[cfg(feature = "gui")]
 impl State {
-    pub fn new(gfx: &mut Graphics) -> State {
-        let render_texture = gfx
-            .create_render_texture(64, 32)
-            .with_depth()
-            .build()
-            .unwrap();
-
-        let tex_id = gfx.egui_register_texture(&render_texture);
+    pub fn new(gfx: &mut Graphics, config: Config) -> State {
+        let texture = emulator_texture(gfx, 64, 32);
+        let tex_id = gfx.egui_register_texture(&texture);
+        let (frame_publisher, frames) = frame_handoff();
+        #[cfg(feature = "audio")]
+        let audio = config
+            .audio
+            .enabled
+            .then(|| AudioOutput::open().map_err(|err| eprintln!("Can't play sound: {}", err)).ok())
+            .flatten();
 
         Self {
             emulator_out_tex_id: tex_id,
-            emulator_out_texture: render_texture,
-            display_buffer: Arc::new(Mutex::new(DisplayBuffer::new())),
+            emulator_out_texture: texture,
+            emulator_out_pixels: Vec::new(),
+            emulator_out_frame: None,
+            crt_preset: config.crt_preset().expect("checked when the config is loaded"),
+            crt: None,
+            crt_error: None,
+            frames,
+            frame_publisher: Some(frame_publisher),
             debug_info: Arc::new(Mutex::new(DebugInfo::new())),
+            keypad: Arc::new(Mutex::new(0)),
+            virtual_keys: 0,
+            typing: false,
+            emu_commands: None,
             emu_thread_handle: None,
             last_frame: Instant::now(),
             render_timer: FixedStep::start(60.0).unlimit(),
+            movie_path: String::from("session.c8m"),
+            rom_path: String::from("roms/ibm.ch8"),
+            symbols_path: String::new(),
+            breakpoint_text: String::new(),
+            memory_text: String::from("200"),
+            settings: config
+                .settings_for("")
+                .expect("defaults are checked when the config is loaded"),
+            settings_rom: String::new(),
+            key_map: input::KEY_MAP,
+            config,
+            config_status: None,
+            #[cfg(feature = "gamepad")]
+            gilrs: gilrs::Gilrs::new()
+                .map_err(|err| eprintln!("Can't read game controllers: {}", err))
+                .ok(),
+            #[cfg(feature = "gamepad")]
+            gamepad_held: Vec::new(),
+            #[cfg(feature = "audio")]
+            audio,
+        }
+    }
+
+    /// Replaces the texture the emulator's screen is uploaded to, for a platform with another
+    /// screen size
+    pub fn resize_emulator_texture(&mut self, gfx: &mut Graphics, width: usize, height: usize) {
+        gfx.egui_remove_texture(self.emulator_out_tex_id);
+        self.emulator_out_texture = emulator_texture(gfx, width, height);
+        self.emulator_out_tex_id = gfx.egui_register_texture(&self.emulator_out_texture);
+    }
+
+    /// Switches the key bindings and palette to those configured for a ROM, or suggested by the
+    /// ROM database when the config doesn't say
+    pub fn apply_rom_settings(&mut self, rom_sha1: &str, info: Option<&RomInfo>) {
+        // overrides were checked when the config was loaded
+        if let Ok(settings) = self.config.resolve(rom_sha1, info) {
+            self.key_map = input::key_map(&settings.keys);
+            self.settings = settings;
         }
+
+        self.settings_rom = rom_sha1.to_string();
     }
-}
\ No newline at end of file
+}
+
+/// An empty texture for the emulator's screen, sampled nearest-neighbor so pixels stay sharp
+/// when egui scales it up
+#[cfg(feature = "gui")]
+fn emulator_texture(gfx: &mut Graphics, width: usize, height: usize) -> Texture {
+    gfx.create_texture()
+        .from_empty_buffer(width as i32, height as i32)
+        .with_filter(TextureFilter::Nearest, TextureFilter::Nearest)
+        .build()
+        .unwrap()
+}
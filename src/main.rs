@@ -2,21 +2,36 @@ use std::process::exit;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
 
+#[cfg(feature = "audio")]
+use chip8_rs::audio::Mixer;
+use chip8_rs::config::Config;
 use chip8_rs::DebugInfo;
 use chip8_rs::emulator;
+use chip8_rs::emulator::EmulatorCommand;
+use chip8_rs::emulator::timing::{FrameClock, TimingModel};
 use chip8_rs::graphics;
+use chip8_rs::input;
+use chip8_rs::romdb::RomDatabase;
 use chip8_rs::State;
-use fixedstep::FixedStep;
 use notan::draw::*;
 use notan::egui::{self, *};
 use notan::prelude::*;
 
 #[notan_main]
 fn main() -> Result<(), String> {
-    notan::init_with(State::new)
+    let config = Config::load().unwrap_or_else(|err| {
+        eprintln!("{}, using the default settings", err);
+        Config::default()
+    });
+    let (width, height) = (config.window.width, config.window.height);
+
+    notan::init_with(move |gfx: &mut Graphics| State::new(gfx, config))
         .add_config(
             WindowConfig::new()
+                .size(width as i32, height as i32)
                 .vsync(true)
                 .high_dpi(true)
                 .multisampling(4)
@@ -25,33 +40,67 @@ fn main() -> Result<(), String> {
         )
         .add_config(EguiConfig)
         .add_config(DrawConfig)
+        .update(update)
         .draw(graphics::render)
         .initialize(start)
         .build()
 }
 
-fn start(state: &mut State) {
-    let display_buffer = state.display_buffer.clone();
-    let debug_info = state.debug_info.clone();
+fn update(app: &mut App, state: &mut State) {
+    let (speed, realtime_timers, rom_sha1, rom_info) = {
+        let debug_info = state.debug_info.lock().unwrap();
+        (debug_info.speed, debug_info.realtime_timers, debug_info.rom_sha1.clone(), debug_info.rom_info.clone())
+    };
+
+    if rom_sha1 != state.settings_rom {
+        state.apply_rom_settings(&rom_sha1, rom_info.as_ref());
+    }
+
+    let keyboard_keys = if state.typing { 0 } else { input::keypad_state(&app.keyboard, &state.key_map) };
+    #[allow(unused_mut)]
+    let mut keypad = keyboard_keys | state.virtual_keys;
+
+    #[cfg(feature = "gamepad")]
+    if let Some(gilrs) = &mut state.gilrs {
+        let (held, stick) = input::gamepad_input(gilrs);
+        keypad |= state.settings.gamepad.keypad(&held, stick);
+        state.gamepad_held = held;
+    }
 
-    // fill display buffer with test data, random data. 0 or 255
-    // for row in display_buffer.lock().unwrap().inactive_buffer_mut().iter_mut() {
-    //     for col in row.iter_mut() {
-    //         if rand::random::<bool>() {
-    //             *col = 255;
-    //         } else {
-    //             *col = 0;
-    //         }
-    //     }
-    // }
+    *state.keypad.lock().unwrap() = keypad;
 
-    // display_buffer.lock().unwrap().swap_buffers();
+    if let Some(speed) = input::speed_hotkey(&app.keyboard, speed).filter(|_| !state.typing) {
+        if let Some(commands) = &state.emu_commands {
+            let _ = commands.send(EmulatorCommand::SetSpeed { speed, realtime_timers });
+        }
+    }
+}
+
+fn start(state: &mut State) {
+    let frame_publisher = state.frame_publisher.take().expect("the emulator thread is only started once");
+    let debug_info = state.debug_info.clone();
+    let keypad = state.keypad.clone();
+    let config = state.config.clone();
+    let (command_sender, commands) = mpsc::channel();
+    #[cfg(feature = "audio")]
+    let mut audio = state
+        .audio
+        .as_ref()
+        .map(|output| (output.queue.clone(), Mixer::new(config.audio.clone(), output.rate)));
 
     let emu_thread = std::thread::spawn(move || {
-        let mut emu = emulator::Emulator::new(display_buffer.clone(), debug_info.clone());
+        let rom_database = RomDatabase::load_default();
+        let mut emu = emulator::Emulator::new(debug_info.clone());
+        emu.add_sink(Box::new(frame_publisher));
+        // every ROM goes on its own platform before it is loaded, MEGA-CHIP ones don't fit otherwise
+        emu.set_platform_lookup(config.platform_lookup(&rom_database));
 
         // Initialize the emulator
-        emu.initialize("roms/ibm.ch8", 10);
+        let result = emu.initialize("roms/ibm.ch8", TimingModel::default());
+        debug_info.lock().unwrap().record_rom_load(&result);
+
+        // the ROM the config settings were last applied for
+        let mut settings_rom = None;
         //emu.initialize("roms/test_opcode.ch8", 1000000000);
         //emu.initialize("roms/ibm.ch8", 3000);
 
@@ -68,25 +117,95 @@ fn start(state: &mut State) {
 
         // emu.initialize(rom_path, clock_speed);
 
-        let mut loops_last_second = 0;
         let mut last_second = std::time::Instant::now();
+        let mut last_frame = std::time::Instant::now();
 
-        let mut fixed_step = FixedStep::start(emu.clock_speed as f64).unlimit(); // 60.0Hz
+        // Timers and movie frames run at 60hz times the speed, how much code runs per frame is up
+        // to emu.timing
+        let mut frame_rate = emu.frame_rate();
+        let mut clock = FrameClock::new(frame_rate.unwrap_or(60.0));
 
         loop {
-            if fixed_step.update() {
-                if last_second.elapsed().as_secs_f64() < 1.0 {
-                    loops_last_second += 1;
-                } else {
-                    debug_info.lock().unwrap().clock_speed = loops_last_second;
-                    loops_last_second = 0;
-                    last_second = std::time::Instant::now();
+            while let Ok(command) = commands.try_recv() {
+                emu.handle_command(command);
+            }
+
+            if settings_rom.as_deref() != Some(emu.rom_sha1()) {
+                let rom_info = emu
+                    .rom_file_info()
+                    .or_else(|| rom_database.lookup(emu.rom_sha1()))
+                    .cloned();
+
+                match config.resolve(emu.rom_sha1(), rom_info.as_ref()) {
+                    Ok(settings) => {
+                        emu.timing = settings.timing;
+                        emu.speed = settings.speed;
+                        emu.set_quirks(settings.quirks);
+                        if let Err(err) = emu.set_font(settings.font, settings.font_address) {
+                            debug_info.lock().unwrap().rom_error = Some(err);
+                        }
+                        if let Err(err) = emu.set_platform(settings.platform) {
+                            debug_info.lock().unwrap().rom_error = Some(err.to_string());
+                        }
+                        emu.reset();
+                    }
+                    Err(err) => debug_info.lock().unwrap().rom_error = Some(err.to_string()),
                 }
 
-                emu.cycle();
+                let mut debug_info = debug_info.lock().unwrap();
+                debug_info.rom_sha1 = emu.rom_sha1().to_string();
+                debug_info.rom_info = rom_info;
+                settings_rom = Some(emu.rom_sha1().to_string());
+            }
+
+            if emu.frame_rate() != frame_rate {
+                frame_rate = emu.frame_rate();
+                clock = FrameClock::new(frame_rate.unwrap_or(60.0));
+            }
+
+            // sleep until the next frame is due, a command wakes the thread early. Without a
+            // frame rate frames run back to back.
+            let wait = frame_rate.map_or(Duration::ZERO, |_| clock.time_until_next());
+            if !wait.is_zero() {
+                match commands.recv_timeout(wait) {
+                    Ok(command) => {
+                        emu.handle_command(command);
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // the window is gone
+                    Err(RecvTimeoutError::Disconnected) => return 0,
+                }
             }
+            clock.advance();
+
+            if last_second.elapsed().as_secs_f64() >= 1.0 {
+                let stats = emu.take_stats();
+                debug_info.lock().unwrap().record_stats(&stats, last_second.elapsed());
+                last_second = std::time::Instant::now();
+            }
+
+            let keypad = *keypad.lock().unwrap();
+            emu.run_frame(keypad);
+
+            #[cfg(feature = "audio")]
+            if let Some((queue, mixer)) = &mut audio {
+                queue.push(&mixer.frame(&mut emu));
+            }
+
+            let mut debug_info = debug_info.lock().unwrap();
+            debug_info.frame_time = last_frame.elapsed().as_secs_f32() * 1000.0;
+            last_frame = std::time::Instant::now();
+            debug_info.movie_state = emu.movie_state();
+            debug_info.timing = emu.timing;
+            debug_info.speed = emu.speed;
+            debug_info.realtime_timers = emu.realtime_timers;
+            debug_info.quirks = emu.quirks();
+            debug_info.keypad = emu.keypad();
+            debug_info.keys_checked = emu.take_keys_checked();
         }
     });
     
+    state.emu_commands = Some(command_sender);
     state.emu_thread_handle = Some(emu_thread);
 }
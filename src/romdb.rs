@@ -0,0 +1,201 @@
+//! What's known about ROMs, looked up by SHA-1. The format is the `programs.json` of the
+//! community chip-8-database (https://github.com/chip-8/chip-8-database).
+//!
+//! Entries for the ROMs in `roms/` and a few classics are bundled with the emulator, a subset
+//! of the community database. To have every ROM the community knows recognised, download the
+//! full database next to `config.toml`:
+//!
+//! ```text
+//! curl -o ~/.config/chip8-rs/programs.json \
+//!     https://raw.githubusercontent.com/chip-8/chip-8-database/master/database/programs.json
+//! ```
+//!
+//! or point `CHIP8_DATABASE` at a copy somewhere else. Its entries win over the bundled ones.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::config::parse_color;
+use crate::emulator::quirks::Quirks;
+
+const BUNDLED: &str = include_str!("../data/chip8-database.json");
+
+/// Everything the database says about one ROM
+#[derive(Clone, Debug, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    pub release: Option<String>,
+    /// Database id of the platform the ROM is meant for, like `originalChip8` or `superchip`
+    pub platform: String,
+    pub quirks: Quirks,
+    /// Instructions per frame
+    pub tickrate: Option<u32>,
+    /// What some keypad keys do in the game, like ("up", 5)
+    pub key_hints: Vec<(String, u8)>,
+    /// Background and foreground
+    pub colors: Option<([u8; 3], [u8; 3])>,
+}
+
+#[derive(Clone, Default)]
+pub struct RomDatabase {
+    roms: HashMap<String, RomInfo>,
+}
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    release: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    roms: HashMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, HashMap<String, bool>>,
+    #[serde(default)]
+    tickrate: Option<u32>,
+    #[serde(default)]
+    keys: HashMap<String, u8>,
+    #[serde(default)]
+    colors: Option<Colors>,
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+impl RomDatabase {
+    /// The database that ships with the emulator
+    pub fn bundled() -> RomDatabase {
+        RomDatabase::parse(BUNDLED).expect("bundled ROM database is valid")
+    }
+
+    /// The bundled database plus the full one from `user_path`, if there is one
+    pub fn load_default() -> RomDatabase {
+        let mut database = RomDatabase::bundled();
+
+        if let Some(path) = RomDatabase::user_path().filter(|path| path.exists()) {
+            match RomDatabase::load(&path) {
+                Ok(user) => database.merge(user),
+                Err(err) => eprintln!("Couldn't load ROM database {}: {}", path.display(), err),
+            }
+        }
+
+        database
+    }
+
+    /// `CHIP8_DATABASE` if it's set, `programs.json` in the config directory otherwise
+    pub fn user_path() -> Option<PathBuf> {
+        match std::env::var_os("CHIP8_DATABASE") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => dirs::config_dir().map(|dir| dir.join("chip8-rs").join("programs.json")),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<RomDatabase, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        RomDatabase::parse(&text)
+    }
+
+    pub fn parse(json: &str) -> Result<RomDatabase, String> {
+        let programs: Vec<Program> = serde_json::from_str(json).map_err(|err| err.to_string())?;
+        let mut roms = HashMap::new();
+
+        for program in programs {
+            for (sha1, rom) in &program.roms {
+                roms.insert(sha1.to_lowercase(), rom_info(&program, rom));
+            }
+        }
+
+        Ok(RomDatabase { roms })
+    }
+
+    /// Adds the entries of another database, replacing any this one already has
+    pub fn merge(&mut self, other: RomDatabase) {
+        self.roms.extend(other.roms);
+    }
+
+    pub fn lookup(&self, rom_sha1: &str) -> Option<&RomInfo> {
+        self.roms.get(&rom_sha1.to_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+}
+
+fn rom_info(program: &Program, rom: &Rom) -> RomInfo {
+    // the first platform listed is the one the ROM was written for
+    let platform = rom.platforms.first().cloned().unwrap_or_else(|| "originalChip8".to_string());
+
+    let mut quirks = platform_quirks(&platform);
+    if let Some(overrides) = rom.quirky_platforms.get(&platform) {
+        apply_quirks(&mut quirks, overrides);
+    }
+
+    let mut key_hints: Vec<(String, u8)> = rom
+        .keys
+        .iter()
+        .filter(|(_, key)| **key < 16)
+        .map(|(action, key)| (action.clone(), *key))
+        .collect();
+    key_hints.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+
+    let colors = rom.colors.as_ref().and_then(|colors| match colors.pixels.as_slice() {
+        [background, foreground, ..] => Some((parse_color(background).ok()?, parse_color(foreground).ok()?)),
+        _ => None,
+    });
+
+    RomInfo {
+        title: program.title.clone(),
+        authors: program.authors.clone(),
+        description: program.description.clone(),
+        release: program.release.clone(),
+        platform,
+        quirks,
+        tickrate: rom.tickrate,
+        key_hints,
+        colors,
+    }
+}
+
+/// Closest quirks preset for a database platform id
+pub fn platform_quirks(platform: &str) -> Quirks {
+    match platform {
+        "chip48" | "superchip1" | "superchip" => Quirks::schip(),
+        "modernChip8" | "xochip" => Quirks::xo_chip(),
+        _ => Quirks::cosmac_vip(),
+    }
+}
+
+/// Applies the database's quirk flags, the ones this emulator doesn't have are ignored
+fn apply_quirks(quirks: &mut Quirks, flags: &HashMap<String, bool>) {
+    for (name, enabled) in flags {
+        match name.as_str() {
+            "shift" => quirks.shift_vx = *enabled,
+            "memoryLeaveIUnchanged" => quirks.memory_increment = !*enabled,
+            "wrap" => quirks.clipping = !*enabled,
+            "jump" => quirks.jump_vx = *enabled,
+            "logic" => quirks.vf_reset = *enabled,
+            _ => {}
+        }
+    }
+}
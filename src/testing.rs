@@ -0,0 +1,385 @@
+//! Running ROMs without a window and checking what they leave on the screen. Used by the
+//! `chip8-test` binary and the integration tests.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Serializer};
+
+use crate::emulator::platform::Platform;
+use crate::emulator::quirks::Quirks;
+use crate::emulator::timing::TimingModel;
+use crate::emulator::Emulator;
+use crate::DebugInfo;
+
+pub use crate::emulator::display::Framebuffer;
+
+/// How long to run a ROM for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunLength {
+    /// 60hz frames, timers tick once per frame
+    Frames(u32),
+    /// Single instructions, timers never tick
+    Cycles(u64),
+}
+
+/// Scripted keypad input, played before the `RunLength` starts counting
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputStep {
+    /// Run frames with no keys held
+    Wait(u32),
+    /// Hold a key down for some frames, it is released by the next step
+    Hold { key: u8, frames: u32 },
+}
+
+impl InputStep {
+    /// Taps a key the way a person would, holding it for a few frames and letting go
+    pub fn tap(key: u8) -> [InputStep; 2] {
+        [InputStep::Hold { key, frames: 6 }, InputStep::Wait(6)]
+    }
+}
+
+/// What the screen should look like once the run is over
+#[derive(Clone, Debug)]
+pub enum Expected {
+    /// `framebuffer_hash` of the screen
+    Hash(String),
+    Image(Box<Framebuffer>),
+    /// Only report the result, for recording new expectations
+    Nothing,
+}
+
+pub struct TestCase {
+    pub name: String,
+    pub rom: Vec<u8>,
+    pub run: RunLength,
+    pub timing: TimingModel,
+    pub quirks: Quirks,
+    pub platform: Platform,
+    pub seed: u64,
+    pub input: Vec<InputStep>,
+    pub expected: Expected,
+}
+
+impl TestCase {
+    pub fn new(name: &str, rom: Vec<u8>, run: RunLength, expected: Expected) -> TestCase {
+        TestCase {
+            name: name.to_string(),
+            rom,
+            run,
+            timing: TimingModel::default(),
+            quirks: Quirks::default(),
+            platform: Platform::default(),
+            seed: 0,
+            input: Vec::new(),
+            expected,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TestResult {
+    pub name: String,
+    pub failure: Option<String>,
+    #[serde(skip)]
+    pub framebuffer: Framebuffer,
+    pub hash: String,
+    /// Frames that ended with the sound timer running
+    pub sound_frames: u32,
+    #[serde(rename = "duration_ms", serialize_with = "milliseconds")]
+    pub duration: Duration,
+}
+
+fn milliseconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// An emulator that isn't connected to any frontend
+pub fn headless_emulator(seed: u64) -> Emulator {
+    Emulator::with_seed(Arc::new(Mutex::new(DebugInfo::new())), seed)
+}
+
+pub fn run_test(case: &TestCase) -> TestResult {
+    let start = Instant::now();
+
+    let mut emu = headless_emulator(case.seed);
+    let loaded = emu
+        .set_platform(case.platform)
+        .and_then(|_| emu.load_rom(case.rom.clone()));
+    if let Err(err) = loaded {
+        let framebuffer = emu.framebuffer();
+        return TestResult {
+            name: case.name.clone(),
+            failure: Some(err.to_string()),
+            hash: framebuffer_hash(&framebuffer),
+            framebuffer,
+            sound_frames: 0,
+            duration: start.elapsed(),
+        };
+    }
+    emu.timing = case.timing;
+    emu.set_quirks(case.quirks);
+    emu.reset();
+
+    let mut sound_frames = 0;
+    let mut run_frame = |emu: &mut Emulator, keypad: u16| {
+        emu.run_frame(keypad);
+        if emu.sound_timer() > 0 {
+            sound_frames += 1;
+        }
+    };
+
+    for step in &case.input {
+        let (keypad, frames) = match *step {
+            InputStep::Wait(frames) => (0, frames),
+            InputStep::Hold { key, frames } => (1 << (key & 0xF), frames),
+        };
+
+        for _ in 0..frames {
+            run_frame(&mut emu, keypad);
+        }
+    }
+
+    match case.run {
+        RunLength::Frames(frames) => {
+            for _ in 0..frames {
+                run_frame(&mut emu, 0);
+            }
+        }
+        RunLength::Cycles(cycles) => {
+            for _ in 0..cycles {
+                emu.cycle();
+            }
+        }
+    }
+
+    let framebuffer = emu.framebuffer();
+    let hash = framebuffer_hash(&framebuffer);
+
+    let failure = if let Some(err) = emu.cpu_error() {
+        Some(format!("{}\n{}", err, to_text(&framebuffer)))
+    } else {
+        match &case.expected {
+            Expected::Hash(expected) if !expected.eq_ignore_ascii_case(&hash) => Some(format!(
+                "expected hash {}, got {}\n{}",
+                expected,
+                hash,
+                to_text(&framebuffer)
+            )),
+            Expected::Image(expected) if pixel_difference(expected, &framebuffer) != 0 => Some(format!(
+                "screen differs from the expected image in {} pixels, expected:\n{}\ngot:\n{}",
+                pixel_difference(expected, &framebuffer),
+                to_text(expected),
+                to_text(&framebuffer)
+            )),
+            _ => None,
+        }
+    };
+
+    TestResult {
+        name: case.name.clone(),
+        failure,
+        framebuffer,
+        hash,
+        sound_frames,
+        duration: start.elapsed(),
+    }
+}
+
+/// Compares a screen to the golden snapshot at `path`. With `CHIP8_BLESS=1` in the
+/// environment the snapshot is written instead, look at it before committing it.
+pub fn check_snapshot(path: &Path, framebuffer: &Framebuffer) -> Result<(), String> {
+    if std::env::var("CHIP8_BLESS").as_deref() == Ok("1") {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+
+        return std::fs::write(path, to_pbm(framebuffer))
+            .map_err(|err| format!("Couldn't write {}: {}", path.display(), err));
+    }
+
+    let text = std::fs::read_to_string(path).map_err(|err| {
+        format!(
+            "Couldn't read snapshot {}: {}, run with CHIP8_BLESS=1 to record it. Got:\n{}",
+            path.display(),
+            err,
+            to_text(framebuffer)
+        )
+    })?;
+    let expected = parse_pbm(&text).map_err(|err| format!("{}: {}", path.display(), err))?;
+
+    if pixel_difference(&expected, framebuffer) == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "screen differs from {} in {} pixels, expected:\n{}\ngot:\n{}",
+            path.display(),
+            pixel_difference(&expected, framebuffer),
+            to_text(&expected),
+            to_text(framebuffer)
+        ))
+    }
+}
+
+/// SHA-1 of the screen packed to one bit per pixel, rows top to bottom and the leftmost pixel
+/// in the high bit, as lowercase hex
+pub fn framebuffer_hash(framebuffer: &Framebuffer) -> String {
+    let mut packed = Vec::with_capacity(framebuffer.pixels().len() / 8);
+
+    for row in framebuffer.rows() {
+        for chunk in row.chunks(8) {
+            let byte = chunk
+                .iter()
+                .fold(0u8, |byte, pixel| (byte << 1) | (*pixel != 0) as u8);
+            packed.push(byte);
+        }
+    }
+
+    sha1_smol::Sha1::from(&packed).digest().to_string()
+}
+
+/// Pixels lit in one screen and dark in the other, all of them when the sizes differ.
+/// Colors aren't compared.
+pub fn pixel_difference(a: &Framebuffer, b: &Framebuffer) -> usize {
+    if (a.width(), a.height()) != (b.width(), b.height()) {
+        return a.pixels().len().max(b.pixels().len());
+    }
+
+    a.pixels()
+        .iter()
+        .zip(b.pixels().iter())
+        .filter(|(a, b)| (**a != 0) != (**b != 0))
+        .count()
+}
+
+/// The screen as text, `#` for lit pixels and `.` for dark ones
+pub fn to_text(framebuffer: &Framebuffer) -> String {
+    let mut out = String::with_capacity((framebuffer.width() + 1) * framebuffer.height());
+
+    for row in framebuffer.rows() {
+        out.extend(row.iter().map(|pixel| if *pixel != 0 { '#' } else { '.' }));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// The screen as a plain (P1) PBM image, which any image viewer opens and diffs nicely
+pub fn to_pbm(framebuffer: &Framebuffer) -> String {
+    let mut out = format!("P1\n{} {}\n", framebuffer.width(), framebuffer.height());
+
+    for row in framebuffer.rows() {
+        let pixels: Vec<&str> = row.iter().map(|pixel| if *pixel != 0 { "1" } else { "0" }).collect();
+        out.push_str(&pixels.join(" "));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Reads a plain (P1) PBM image
+pub fn parse_pbm(text: &str) -> Result<Framebuffer, String> {
+    // comments run to the end of the line
+    let mut tokens = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split_whitespace());
+
+    if tokens.next() != Some("P1") {
+        return Err("not a plain PBM image, expected P1".to_string());
+    }
+
+    let width = tokens.next().and_then(|width| width.parse::<usize>().ok());
+    let height = tokens.next().and_then(|height| height.parse::<usize>().ok());
+    let (width, height) = match (width, height) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => (width, height),
+        _ => return Err(format!("bad image size {:?}x{:?}", width, height)),
+    };
+
+    // pixels may also be written without separators
+    let mut pixels = tokens.flat_map(|token| token.chars());
+    let mut framebuffer = Framebuffer::new(width, height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = match pixels.next() {
+                Some('0') => 0,
+                Some('1') => 255,
+                Some(other) => return Err(format!("bad pixel {:?}", other)),
+                None => return Err("image ends early".to_string()),
+            };
+            framebuffer.set(x, y, pixel);
+        }
+    }
+
+    Ok(framebuffer)
+}
+
+/// Results as a JUnit XML test suite, the format CI servers understand
+pub fn junit_report(suite: &str, results: &[TestResult]) -> String {
+    let failures = results.iter().filter(|result| !result.passed()).count();
+    let total_time: f64 = results.iter().map(|result| result.duration.as_secs_f64()).sum();
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        xml_escape(suite),
+        results.len(),
+        failures,
+        total_time
+    ));
+
+    for result in results {
+        out.push_str(&format!(
+            "  <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            xml_escape(&result.name),
+            xml_escape(suite),
+            result.duration.as_secs_f64()
+        ));
+
+        match &result.failure {
+            Some(failure) => {
+                let message = failure.lines().next().unwrap_or("");
+                out.push_str(&format!(
+                    ">\n    <failure message=\"{}\">{}</failure>\n  </testcase>\n",
+                    xml_escape(message),
+                    xml_escape(failure)
+                ));
+            }
+            None => out.push_str(" />\n"),
+        }
+    }
+
+    out.push_str("</testsuite>\n");
+    out
+}
+
+/// Results as a JSON array, one object per test
+pub fn json_report(results: &[TestResult]) -> String {
+    let entries: Vec<serde_json::Value> = results
+        .iter()
+        .map(|result| {
+            let mut entry = serde_json::to_value(result).expect("test results are plain data");
+            entry["passed"] = result.passed().into();
+            entry
+        })
+        .collect();
+
+    let mut out = serde_json::to_string_pretty(&entries).expect("test results are plain data");
+    out.push('\n');
+    out
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,110 @@
+use chip8_rs::audio::{Mixer, SampleQueue};
+use chip8_rs::config::AudioConfig;
+use chip8_rs::emulator::platform::Platform;
+use chip8_rs::emulator::timing::Speed;
+use chip8_rs::emulator::Emulator;
+use chip8_rs::testing::headless_emulator;
+
+/// V0 := 5, sound timer := V0, then loop forever
+fn beeping_emulator() -> Emulator {
+    let mut emu = headless_emulator(0);
+    emu.load_rom(vec![0x60, 0x05, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+    emu.reset();
+    emu
+}
+
+fn config(enabled: bool) -> AudioConfig {
+    AudioConfig {
+        enabled,
+        volume: 0.25,
+        frequency: 1000.0,
+    }
+}
+
+#[test]
+fn buzzer_plays_the_configured_tone_while_the_sound_timer_runs() {
+    let mut emu = beeping_emulator();
+    let mut mixer = Mixer::new(config(true), 6000);
+    assert_eq!(mixer.frame(&mut emu), vec![0.0; 100]);
+
+    emu.cycle();
+    emu.cycle();
+    let frame = mixer.frame(&mut emu);
+
+    // 1000hz at 6000 samples a second is three samples up, three down
+    assert_eq!(frame.len(), 100);
+    assert_eq!(frame[..6], [0.25, 0.25, 0.25, -0.25, -0.25, -0.25]);
+}
+
+#[test]
+fn disabled_buzzer_is_silent() {
+    let mut emu = beeping_emulator();
+    emu.cycle();
+    emu.cycle();
+
+    let mut mixer = Mixer::new(config(false), 6000);
+    assert_eq!(mixer.frame(&mut emu), vec![0.0; 100]);
+}
+
+#[test]
+fn frames_last_as_long_as_the_speed_makes_them() {
+    let mut emu = beeping_emulator();
+    let mut mixer = Mixer::new(config(true), 6000);
+
+    emu.speed = Speed::Double;
+    assert_eq!(mixer.frame(&mut emu).len(), 50);
+    emu.speed = Speed::Quarter;
+    assert_eq!(mixer.frame(&mut emu).len(), 400);
+
+    // a 240th of a second is 183.75 samples, the leftovers add up
+    let mut mixer = Mixer::new(config(true), 44100);
+    emu.speed = Speed::Quadruple;
+    let lengths: Vec<usize> = (0..2).map(|_| mixer.frame(&mut emu).len()).collect();
+    assert_eq!(lengths, [183, 184]);
+
+    // without a frame rate frames last as long as they take, never more than a 60th
+    emu.speed = Speed::Unlimited;
+    assert!(mixer.frame(&mut emu).len() <= 735);
+}
+
+#[test]
+fn queue_drops_the_oldest_samples_and_fills_every_channel() {
+    // holds a tenth of a second
+    let queue = SampleQueue::new(30);
+    queue.push(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert_eq!(queue.len(), 3);
+
+    let mut out = [9.0; 8];
+    queue.pop_into(&mut out, 2);
+    assert_eq!(out, [3.0, 3.0, 4.0, 4.0, 5.0, 5.0, 0.0, 0.0]);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn megachip_samples_play_through_the_mixer() {
+    #[rustfmt::skip]
+    let mut rom = vec![
+        0x01, 0x00, 0x03, 0x00, // I := 0x000300
+        0x06, 0x00,             // play the sample at I on a loop
+        0x12, 0x06,             // loop forever
+    ];
+    rom.resize(0x100, 0);
+    // 6000hz, 4 samples
+    rom.extend([0x17, 0x70, 0x00, 0x00, 0x04, 0x00, 0xC0, 0x40, 0xC0, 0x40]);
+
+    let mut emu = headless_emulator(0);
+    emu.set_platform(Platform::MegaChip).unwrap();
+    emu.load_rom(rom).unwrap();
+    emu.reset();
+    emu.cycle();
+    emu.cycle();
+
+    let mut mixer = Mixer::new(config(false), 6000);
+    assert_eq!(mixer.frame(&mut emu), vec![0.0; 100]);
+
+    // the sample is playing, not the buzzer, and it keeps going into the next frame
+    let mut mixer = Mixer::new(config(true), 6000);
+    let frame = mixer.frame(&mut emu);
+    assert_eq!(frame[..4], [0.125, -0.125, 0.125, -0.125]);
+    assert_eq!(mixer.frame(&mut emu)[..2], [0.125, -0.125]);
+}
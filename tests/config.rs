@@ -0,0 +1,91 @@
+use chip8_rs::config::{Config, ConfigError};
+use chip8_rs::crt::CrtPreset;
+use chip8_rs::emulator::quirks::Quirks;
+use chip8_rs::emulator::timing::{Speed, TimingModel};
+
+const ROM: &str = "0123456789abcdef0123456789abcdef01234567";
+
+#[test]
+fn empty_file_gives_the_defaults() {
+    assert_eq!(Config::parse("").unwrap(), Config::default());
+}
+
+#[test]
+fn rom_overrides_replace_only_what_they_set() {
+    let config = Config::parse(&format!(
+        r##"
+        speed = "2x"
+        quirks = "schip"
+
+        [palette]
+        foreground = "#33FF66"
+
+        [roms.{}]
+        name = "Some game"
+        timing = "cosmac-vip"
+        quirks = "vip"
+        "##,
+        ROM
+    ))
+    .unwrap();
+
+    let defaults = config.settings_for("unknown").unwrap();
+    assert_eq!(defaults.speed, Speed::Double);
+    assert_eq!(defaults.quirks, Quirks::schip());
+    assert_eq!(defaults.foreground, [0x33, 0xFF, 0x66]);
+    assert_eq!(defaults.background, [0, 0, 0]);
+
+    let rom = config.settings_for(ROM).unwrap();
+    assert_eq!(rom.speed, Speed::Double);
+    assert_eq!(rom.timing, TimingModel::CosmacVip);
+    assert_eq!(rom.quirks, Quirks::cosmac_vip());
+    assert_eq!(rom.keys, defaults.keys);
+}
+
+#[test]
+fn remembered_settings_survive_a_round_trip() {
+    let mut config = Config::default();
+    config.remember_rom(ROM, Speed::Half, TimingModel::InstructionsPerFrame(30), Quirks::xo_chip());
+
+    let text = toml::to_string(&config).unwrap();
+    let settings = Config::parse(&text).unwrap().settings_for(ROM).unwrap();
+
+    assert_eq!(settings.speed, Speed::Half);
+    assert_eq!(settings.timing, TimingModel::InstructionsPerFrame(30));
+    assert_eq!(settings.quirks, Quirks::xo_chip());
+}
+
+#[test]
+fn custom_quirks_are_remembered_flag_by_flag() {
+    let quirks = Quirks {
+        clipping: false,
+        stack_depth: 16,
+        ..Quirks::cosmac_vip()
+    };
+    let mut config = Config::default();
+    config.remember_rom(ROM, Speed::Normal, TimingModel::default(), quirks);
+
+    let text = toml::to_string(&config).unwrap();
+    assert!(text.contains("clipping = false"), "{}", text);
+    assert_eq!(Config::parse(&text).unwrap().settings_for(ROM).unwrap().quirks, quirks);
+}
+
+#[test]
+fn bad_values_are_reported_with_their_section() {
+    let err = Config::parse(&format!("[roms.{}]\nspeed = \"3x\"\n", ROM)).unwrap_err();
+    assert!(matches!(&err, ConfigError::Invalid { section, .. } if section.contains(ROM)), "{}", err);
+
+    let err = Config::parse("keys = [\"x\"]").unwrap_err();
+    assert!(matches!(err, ConfigError::Invalid { .. }), "{}", err);
+}
+
+#[test]
+fn crt_preset_comes_from_the_window_section() {
+    let config = Config::parse("[window]\ncrt = \"Television\"\n").unwrap();
+    assert_eq!(config.crt_preset().unwrap(), CrtPreset::Television);
+    assert!(!CrtPreset::Television.effects().is_off());
+    assert!(CrtPreset::Off.effects().is_off());
+
+    let err = Config::parse("[window]\ncrt = \"plasma\"\n").unwrap_err();
+    assert!(matches!(&err, ConfigError::Invalid { section, .. } if section == "[window]"), "{}", err);
+}
@@ -0,0 +1,166 @@
+use chip8_rs::emulator::cpu::CpuError;
+use chip8_rs::emulator::debugger::StackFrame;
+use chip8_rs::emulator::disasm::{self, Target};
+use chip8_rs::emulator::platform::Platform;
+use chip8_rs::emulator::quirks::Quirks;
+use chip8_rs::emulator::Emulator;
+use chip8_rs::testing::headless_emulator;
+
+/// V0 := 1, V1 := 2, call 0x208, jump back to the start. The subroutine adds V1 to V0.
+#[rustfmt::skip]
+const PROGRAM: [u8; 12] = [
+    0x60, 0x01,
+    0x61, 0x02,
+    0x22, 0x08,
+    0x12, 0x00,
+    0x80, 0x14,
+    0x00, 0xEE,
+];
+
+fn emulator() -> Emulator {
+    let mut emu = headless_emulator(0);
+    emu.load_rom(PROGRAM.to_vec()).unwrap();
+    emu.reset();
+    emu
+}
+
+#[test]
+fn disassembly_uses_the_usual_mnemonics() {
+    let lines = disasm::disassemble(&PROGRAM, 0x200, Platform::Chip8, Quirks::default(), &|_| None);
+    let text: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+
+    assert_eq!(text, ["LD V0, 0x01", "LD V1, 0x02", "CALL 0x208", "JP 0x200", "ADD V0, V1", "RET"]);
+    assert_eq!(lines[2].target, Some(Target::Call(0x208)));
+    assert_eq!(lines[4].address, 0x208);
+}
+
+#[test]
+fn jump_targets_get_names() {
+    let lines = disasm::disassemble(&PROGRAM, 0x200, Platform::Chip8, Quirks::default(), &|_| None);
+    let labels = disasm::auto_labels(&lines);
+    assert_eq!(labels.get(&0x208).map(String::as_str), Some("sub_208"));
+    assert_eq!(labels.get(&0x200).map(String::as_str), Some("L200"));
+
+    let named = disasm::disassemble(&PROGRAM, 0x200, Platform::Chip8, Quirks::default(), &|address| {
+        labels.get(&address).cloned()
+    });
+    assert_eq!(named[2].text, "CALL sub_208");
+}
+
+#[test]
+fn jump_with_offset_names_the_register_the_quirks_add() {
+    let text = |quirks| disasm::disassemble(&[0xB3, 0x40], 0x200, Platform::Chip8, quirks, &|_| None)[0].text.clone();
+
+    assert_eq!(text(Quirks::cosmac_vip()), "JP V0, 0x340");
+    assert_eq!(text(Quirks::schip()), "JP V3, 0x340");
+}
+
+#[test]
+fn megachip_long_index_is_one_instruction() {
+    let code = [0x01, 0x12, 0x34, 0x56, 0x00, 0x11];
+    let lines = disasm::disassemble(&code, 0x200, Platform::MegaChip, Quirks::default(), &|_| None);
+
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0].text, "LD I, 0x123456");
+    assert_eq!(lines[1].text, "MEGAON");
+}
+
+#[test]
+fn breakpoints_pause_before_the_instruction() {
+    let mut emu = emulator();
+    emu.debugger_mut().toggle_breakpoint(0x208);
+
+    emu.run_frame(0);
+    assert!(emu.debugger().paused);
+    assert_eq!(emu.cpu_state().pc, 0x208);
+
+    // paused frames run nothing
+    assert_eq!(emu.run_frame(0), 0);
+
+    // and resuming doesn't stop at the same breakpoint straight away
+    emu.debugger_mut().resume();
+    emu.debugger_mut().toggle_breakpoint(0x208);
+    emu.debugger_mut().toggle_breakpoint(0x206);
+    emu.run_frame(0);
+    assert_eq!(emu.cpu_state().pc, 0x206);
+}
+
+#[test]
+fn run_to_stops_once() {
+    let mut emu = emulator();
+    emu.debugger_mut().run_to(0x20A);
+
+    emu.run_frame(0);
+    assert!(emu.debugger().paused);
+    assert_eq!(emu.cpu_state().pc, 0x20A);
+
+    emu.debugger_mut().resume();
+    emu.run_frame(0);
+    assert!(!emu.debugger().paused);
+}
+
+#[test]
+fn steps_show_what_they_changed() {
+    let mut emu = emulator();
+
+    emu.step();
+    assert!(emu.debugger().paused);
+    assert_eq!(emu.cpu_state().pc, 0x202);
+
+    let before = emu.debugger().before_step.clone().unwrap();
+    assert_eq!(before.changes(&emu.cpu_state()), ["V0: 00 -> 01"]);
+}
+
+/// Calls itself forever: 0x200 CALL 0x200
+fn recursive(quirks: Quirks) -> Emulator {
+    let mut emu = headless_emulator(0);
+    emu.load_rom(vec![0x22, 0x00]).unwrap();
+    emu.set_quirks(quirks);
+    emu.reset();
+    emu
+}
+
+#[test]
+fn stack_overflows_at_the_platform_depth() {
+    for (quirks, depth) in [(Quirks::cosmac_vip(), 12), (Quirks::schip(), 16)] {
+        let mut emu = recursive(quirks);
+        for _ in 0..10 {
+            emu.run_frame(0);
+        }
+
+        assert_eq!(emu.cpu_error(), Some(CpuError::StackOverflow { pc: 0x200, depth }));
+        assert_eq!(emu.call_stack().len(), depth);
+        assert!(emu.debugger().paused);
+        assert_eq!(emu.cpu_state().pc, 0x200, "the PC stays on the failing call");
+    }
+}
+
+#[test]
+fn returning_from_nothing_is_an_underflow() {
+    let mut emu = headless_emulator(0);
+    emu.load_rom(vec![0x60, 0x01, 0x00, 0xEE]).unwrap();
+    emu.reset();
+    emu.run_frame(0);
+
+    assert_eq!(emu.cpu_error(), Some(CpuError::StackUnderflow { pc: 0x202 }));
+    assert!(emu.cpu_error().unwrap().to_string().contains("202"));
+
+    emu.reset();
+    assert_eq!(emu.cpu_error(), None);
+    assert!(!emu.debugger().paused);
+}
+
+#[test]
+fn call_stack_names_the_subroutine() {
+    let mut emu = emulator();
+    emu.debugger_mut().toggle_breakpoint(0x20A);
+    emu.run_frame(0);
+
+    assert_eq!(
+        emu.call_stack(),
+        [StackFrame {
+            return_address: 0x206,
+            subroutine: Some(0x208),
+        }]
+    );
+}
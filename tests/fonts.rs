@@ -0,0 +1,53 @@
+use chip8_rs::config::Config;
+use chip8_rs::emulator::font::{Font, FONT_SIZE};
+use chip8_rs::testing::headless_emulator;
+
+#[test]
+fn fx29_points_into_the_font_wherever_it_is() {
+    let mut emu = headless_emulator(0);
+    // V0 := 7, I := font digit V0, load V0 - V4
+    emu.load_rom(vec![0x60, 0x07, 0xF0, 0x29, 0xF4, 0x65]).unwrap();
+    emu.set_font(Font::cosmac_vip(), 0x050).unwrap();
+    emu.reset();
+
+    for _ in 0..3 {
+        emu.cycle();
+    }
+
+    let state = emu.cpu_state();
+    assert_eq!(state.v_reg[..5], Font::cosmac_vip().glyphs[35..40]);
+    assert_eq!(emu.font_address(), 0x050);
+}
+
+#[test]
+fn font_must_end_before_the_program() {
+    let mut emu = headless_emulator(0);
+
+    assert!(emu.set_font(Font::octo(), (0x200 - FONT_SIZE) as u16).is_ok());
+    assert!(emu.set_font(Font::octo(), 0x1B1).is_err());
+}
+
+#[test]
+fn custom_fonts_load_from_files() {
+    let dir = std::path::PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let path = dir.join("custom.font");
+    std::fs::write(&path, [0xAA; FONT_SIZE]).unwrap();
+
+    let font = Font::from_name_or_file(path.to_str().unwrap()).unwrap();
+    assert_eq!(font.glyphs, [0xAA; FONT_SIZE]);
+    assert_eq!(font.preset_name(), None);
+
+    std::fs::write(&path, [0xAA; 10]).unwrap();
+    assert!(Font::from_name_or_file(path.to_str().unwrap()).is_err());
+}
+
+#[test]
+fn config_picks_the_font_and_where_it_goes() {
+    let config = Config::parse("font = \"eti660\"\nfont_address = 0x50\n").unwrap();
+    let settings = config.settings_for("").unwrap();
+
+    assert_eq!(settings.font, Font::eti660());
+    assert_eq!(settings.font_address, 0x50);
+
+    assert!(Config::parse("font_address = 0x1F0\n").is_err());
+}
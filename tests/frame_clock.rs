@@ -0,0 +1,24 @@
+use std::time::Duration;
+
+use chip8_rs::emulator::timing::FrameClock;
+
+#[test]
+fn frames_are_due_one_interval_apart() {
+    let mut clock = FrameClock::new(10.0);
+    assert_eq!(clock.time_until_next(), Duration::ZERO);
+
+    clock.advance();
+    let wait = clock.time_until_next();
+    assert!(wait > Duration::from_millis(50) && wait <= Duration::from_millis(100), "{:?}", wait);
+}
+
+#[test]
+fn a_clock_far_behind_starts_over_instead_of_catching_up() {
+    let mut clock = FrameClock::new(100.0);
+    std::thread::sleep(Duration::from_millis(300));
+
+    // a frame later it would still be 290ms behind, so the next one is a frame from now
+    clock.advance();
+    clock.advance();
+    assert!(clock.time_until_next() > Duration::ZERO);
+}
@@ -0,0 +1,53 @@
+use chip8_rs::emulator::display::Framebuffer;
+use chip8_rs::frame_handoff::frame_handoff;
+
+#[test]
+fn reader_gets_the_newest_frame_and_keeps_it() {
+    let (mut publisher, mut reader) = frame_handoff();
+    assert_eq!(reader.latest_frame().number, 0);
+
+    let mut pixels = Framebuffer::default();
+    publisher.publish_frame(&pixels);
+    pixels.set(3, 4, 255);
+    publisher.publish_frame(&pixels);
+
+    let latest = reader.latest_frame();
+    assert_eq!(latest.number, 2);
+    assert!(latest.pixels.is_lit(3, 4));
+
+    // nothing new, the same frame again
+    assert_eq!(reader.latest_frame().number, 2);
+    assert_eq!(publisher.frames_published(), 2);
+}
+
+#[test]
+fn frames_are_never_torn_across_threads() {
+    let (mut publisher, mut reader) = frame_handoff();
+
+    let writer = std::thread::spawn(move || {
+        let mut pixels = Framebuffer::new(64, 32);
+        for number in 1..=2000u32 {
+            // every pixel holds the low byte of the frame number
+            for y in 0..32 {
+                for x in 0..64 {
+                    pixels.set(x, y, number as u8);
+                }
+            }
+            publisher.publish_frame(&pixels);
+        }
+    });
+
+    let mut last = 0;
+    while last < 2000 {
+        let frame = reader.latest_frame();
+        assert!(frame.number >= last, "frames went backwards");
+        last = frame.number;
+
+        if frame.number > 0 {
+            let expected = frame.number as u8;
+            assert!(frame.pixels.pixels().iter().all(|pixel| *pixel == expected), "torn frame {}", frame.number);
+        }
+    }
+
+    writer.join().unwrap();
+}
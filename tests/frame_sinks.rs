@@ -0,0 +1,106 @@
+use chip8_rs::emulator::display::Framebuffer;
+use chip8_rs::emulator::sink::{self, Frame, FrameSink, MemorySink, PngRecorder, TerminalSink};
+use chip8_rs::testing;
+
+#[test]
+fn every_sink_gets_every_frame() {
+    let mut emu = testing::headless_emulator(0);
+    emu.load_rom_from_file("roms/test_opcode.ch8").unwrap();
+    emu.reset();
+
+    let gui = MemorySink::new();
+    let recorder = MemorySink::new();
+    emu.add_sink(Box::new(gui.clone()));
+    emu.add_sink(Box::new(recorder.clone()));
+
+    for _ in 0..30 {
+        emu.run_frame(0);
+    }
+
+    assert_eq!(gui.frames().len(), 30);
+    assert_eq!(gui.frames(), recorder.frames());
+
+    let last = gui.last_frame().unwrap();
+    assert_eq!(last.number, 29);
+    assert_eq!(last.pixels, emu.framebuffer());
+}
+
+#[test]
+fn half_blocks_pack_two_rows_per_line() {
+    let mut pixels = Framebuffer::default();
+    pixels.set(0, 0, 255);
+    pixels.set(1, 1, 255);
+    pixels.set(2, 0, 255);
+    pixels.set(2, 1, 255);
+
+    let lines = sink::half_block_lines(&pixels);
+
+    assert_eq!(lines.len(), 16);
+    assert!(lines[0].starts_with("▀▄█ "));
+    assert_eq!(lines[0].chars().count(), 64);
+}
+
+#[test]
+fn rgba_takes_the_palette_unless_pixels_have_colors() {
+    let mut pixels = Framebuffer::new(2, 1);
+    pixels.set(0, 0, 255);
+
+    let mut rgba = Vec::new();
+    pixels.write_rgba(&mut rgba, [1, 2, 3], [4, 5, 6]);
+    assert_eq!(rgba, [1, 2, 3, 255, 4, 5, 6, 255]);
+
+    pixels.set_colors(Some(vec![[7, 8, 9], [10, 11, 12]]));
+    pixels.write_rgba(&mut rgba, [1, 2, 3], [4, 5, 6]);
+    assert_eq!(rgba, [7, 8, 9, 255, 10, 11, 12, 255]);
+}
+
+#[test]
+fn terminal_sink_draws_changed_frames_at_its_origin() {
+    let mut pixels = Framebuffer::new(4, 4);
+    pixels.set(0, 0, 255);
+    let frame = Frame { number: 0, pixels };
+
+    let mut out = Vec::new();
+    {
+        let mut terminal = TerminalSink::new(&mut out).at(1, 1);
+        terminal.present(&frame);
+        // the same screen again draws nothing
+        terminal.present(&Frame { number: 1, ..frame.clone() });
+    }
+
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text, "\x1b[2;2H▀   \x1b[3;2H    ");
+}
+
+#[test]
+fn png_recorder_saves_scaled_frames_that_changed() {
+    let dir = std::env::temp_dir().join(format!("chip8-png-recorder-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let mut emu = testing::headless_emulator(0);
+    // draw the 0 glyph, then loop forever
+    emu.load_rom(vec![0xA0, 0x00, 0xD0, 0x05, 0x12, 0x04]).unwrap();
+    emu.reset();
+    emu.add_sink(Box::new(PngRecorder::new(&dir, 2).unwrap()));
+
+    for _ in 0..5 {
+        emu.run_frame(0);
+    }
+
+    let mut files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+    files.sort();
+    assert_eq!(files, ["frame_000000.png"]);
+
+    let decoder = png::Decoder::new(std::fs::File::open(dir.join("frame_000000.png")).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut image = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut image).unwrap();
+
+    assert_eq!((info.width, info.height), (128, 64));
+    assert_eq!(info.color_type, png::ColorType::Grayscale);
+    // the glyph's top row is lit, each pixel is 2x2
+    assert_eq!(image[..8], [255; 8]);
+    assert_eq!(image[128..136], [255; 8]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,67 @@
+use chip8_rs::config::Config;
+use chip8_rs::gamepad::{PadButton, PadLayout, PadMapping};
+
+const ROM: &str = "0123456789abcdef0123456789abcdef01234567";
+
+#[test]
+fn dpad_presses_the_layouts_direction_keys() {
+    let numpad = PadLayout::Numpad.mapping();
+    assert_eq!(numpad.keypad(&[PadButton::DPadUp, PadButton::South], (0.0, 0.0)), 1 << 0x2 | 1 << 0x5);
+
+    let octo = PadLayout::Octo.mapping();
+    assert_eq!(octo.keypad(&[PadButton::DPadLeft], (0.0, 0.0)), 1 << 0x7);
+}
+
+#[test]
+fn stick_counts_outside_the_deadzone() {
+    let mapping = PadMapping::default();
+
+    assert_eq!(mapping.keypad(&[], (0.2, -0.25)), 0);
+    assert_eq!(mapping.keypad(&[], (0.8, 0.0)), 1 << 0x6);
+    // y is up, diagonals press both directions
+    assert_eq!(mapping.keypad(&[], (-0.7, 0.7)), 1 << 0x4 | 1 << 0x2);
+
+    let mut no_stick = mapping.clone();
+    no_stick.stick = false;
+    assert_eq!(no_stick.keypad(&[], (0.8, 0.0)), 0);
+}
+
+#[test]
+fn mappings_can_be_set_per_rom() {
+    let config = Config::parse(&format!(
+        r#"
+        [roms.{}.gamepad]
+        deadzone = 0.5
+        buttons = {{ dpad-up = "5", start = "f" }}
+        "#,
+        ROM
+    ))
+    .unwrap();
+
+    assert_eq!(config.settings_for("unknown").unwrap().gamepad, PadMapping::default());
+
+    let mapping = config.settings_for(ROM).unwrap().gamepad;
+    assert_eq!(mapping.key(PadButton::DPadUp), Some(0x5));
+    assert_eq!(mapping.key(PadButton::Start), Some(0xF));
+    assert_eq!(mapping.key(PadButton::South), None);
+    assert_eq!(mapping.deadzone, 0.5);
+}
+
+#[test]
+fn remembered_mappings_survive_a_round_trip() {
+    let mut mapping = PadLayout::Octo.mapping();
+    mapping.set_key(PadButton::RightTrigger, Some(0x1));
+
+    let mut config = Config::default();
+    config.remember_gamepad(ROM, &mapping);
+
+    let text = toml::to_string(&config).unwrap();
+    assert_eq!(Config::parse(&text).unwrap().settings_for(ROM).unwrap().gamepad, mapping);
+}
+
+#[test]
+fn bad_mappings_are_rejected() {
+    assert!(Config::parse("[gamepad]\nbuttons = { dpad-up = \"10\" }\n").is_err());
+    assert!(Config::parse("[gamepad]\nbuttons = { turbo = \"1\" }\n").is_err());
+    assert!(Config::parse("[gamepad]\ndeadzone = 1.5\n").is_err());
+}
@@ -0,0 +1,16 @@
+use chip8_rs::testing::headless_emulator;
+
+#[test]
+fn frontends_see_held_keys_and_the_keys_the_rom_checks() {
+    // V0 := 5, then check key V0 forever
+    let mut emu = headless_emulator(0);
+    emu.load_rom(vec![0x60, 0x05, 0xE0, 0x9E, 0x12, 0x02]).unwrap();
+    emu.reset();
+
+    assert_eq!(emu.take_keys_checked(), 0);
+
+    emu.run_frame(1 << 0xA);
+    assert_eq!(emu.keypad(), 1 << 0xA);
+    assert_eq!(emu.take_keys_checked(), 1 << 5);
+    assert_eq!(emu.take_keys_checked(), 0);
+}
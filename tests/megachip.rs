@@ -0,0 +1,135 @@
+use chip8_rs::config::Config;
+use chip8_rs::emulator::platform::Platform;
+use chip8_rs::emulator::{Emulator, EmulatorCommand};
+use chip8_rs::romdb::RomDatabase;
+use chip8_rs::testing::headless_emulator;
+
+/// A MEGA-CHIP emulator running `program` from 0x200, with `data` at 0x300
+fn megachip(program: &[u8], data: &[u8]) -> Emulator {
+    let mut rom = program.to_vec();
+    rom.resize(0x100, 0);
+    rom.extend(data);
+
+    let mut emu = headless_emulator(0);
+    emu.set_platform(Platform::MegaChip).unwrap();
+    emu.load_rom(rom).unwrap();
+    emu.reset();
+    emu
+}
+
+#[rustfmt::skip]
+const DRAW_TWICE: [u8; 26] = [
+    0x00, 0x11,             // MEGA-CHIP mode on
+    0x01, 0x00, 0x03, 0x00, // I := 0x000300
+    0x02, 0x01,             // one palette entry from I
+    0x01, 0x00, 0x03, 0x04, // I := 0x000304
+    0x03, 0x02,             // sprites 2 wide
+    0x04, 0x01,             // and 1 high
+    0x09, 0x01,             // collide with palette entry 1
+    0x60, 0x0A,             // V0 := 10
+    0x61, 0x14,             // V1 := 20
+    0xD0, 0x10,             // sprite V0 V1
+    0xD0, 0x10,             // sprite V0 V1
+];
+
+#[test]
+fn sprites_show_up_in_color_after_00e0() {
+    let mut program = DRAW_TWICE[..24].to_vec();
+    program.extend([0x00, 0xE0]);
+    // opaque red, then a sprite of palette entry 1 and a transparent pixel
+    let mut emu = megachip(&program, &[0xFF, 0xFF, 0x00, 0x00, 0x01, 0x00]);
+
+    for _ in 0..10 {
+        emu.cycle();
+    }
+    assert!(!emu.framebuffer().is_lit(10, 20), "nothing shows before 00E0");
+
+    emu.cycle();
+    let framebuffer = emu.framebuffer();
+    assert_eq!((framebuffer.width(), framebuffer.height()), (256, 192));
+    assert_eq!(framebuffer.color(10, 20), Some([0xFF, 0x00, 0x00]));
+    assert_eq!(framebuffer.color(11, 20), Some([0x00, 0x00, 0x00]));
+}
+
+#[test]
+fn drawing_over_the_collision_color_sets_vf() {
+    let mut emu = megachip(&DRAW_TWICE, &[0xFF, 0xFF, 0x00, 0x00, 0x01, 0x01]);
+
+    for _ in 0..10 {
+        emu.cycle();
+    }
+    assert_eq!(emu.cpu_state().v_reg[0xF], 0);
+
+    emu.cycle();
+    assert_eq!(emu.cpu_state().v_reg[0xF], 1);
+}
+
+#[test]
+fn index_register_has_24_bits() {
+    // I := 0x123456, V0 := 0x42, save V0, V0 := 0, load V0
+    let mut emu = megachip(&[0x01, 0x12, 0x34, 0x56, 0x60, 0x42, 0xF0, 0x55, 0x60, 0x00, 0x01, 0x12, 0x34, 0x56, 0xF0, 0x65], &[]);
+
+    emu.cycle();
+    assert_eq!(emu.cpu_state().idx, 0x123456);
+
+    for _ in 0..5 {
+        emu.cycle();
+    }
+    assert_eq!(emu.cpu_state().v_reg[0], 0x42);
+}
+
+#[test]
+fn roms_can_be_larger_than_4k() {
+    let mut emu = headless_emulator(0);
+    assert!(emu.load_rom(vec![0; 0x2000]).is_err());
+
+    emu.set_platform(Platform::MegaChip).unwrap();
+    assert!(emu.load_rom(vec![0; 0x2000]).is_ok());
+}
+
+#[test]
+fn sampled_sound_plays_from_060n() {
+    // I := 0x000300, play once
+    let header = [0x1F, 0x40, 0x00, 0x00, 0x02, 0x00];
+    let mut emu = megachip(&[0x01, 0x00, 0x03, 0x00, 0x06, 0x01], &[&header[..], &[0xFF, 0x00]].concat());
+
+    let mut out = [0.0; 4];
+    assert!(!emu.fill_sample_audio(&mut out, 8000));
+
+    emu.cycle();
+    emu.cycle();
+    assert!(emu.fill_sample_audio(&mut out, 8000));
+    assert_eq!(out, [127.0 / 128.0, -1.0, 0.0, 0.0]);
+    assert!(!emu.fill_sample_audio(&mut out, 8000));
+}
+
+#[test]
+fn platform_lookup_runs_before_the_size_check() {
+    let rom = vec![0; 0x2000];
+    let sha1 = sha1_smol::Sha1::from(&rom).digest().to_string();
+    let config = Config::parse(&format!("[roms.{}]\nplatform = \"megachip\"\n", sha1)).unwrap();
+
+    let mut emu = headless_emulator(0);
+    emu.set_platform_lookup(config.platform_lookup(&RomDatabase::default()));
+    emu.load_rom(rom).unwrap();
+
+    assert_eq!(emu.platform(), Platform::MegaChip);
+    // ROMs the config doesn't mention go back to the default
+    emu.load_rom(vec![0x12, 0x00]).unwrap();
+    assert_eq!(emu.platform(), Platform::Chip8);
+}
+
+#[test]
+fn load_rom_command_switches_platforms_first() {
+    let rom = vec![0xAA; 0x2000];
+    let path = std::env::temp_dir().join(format!("chip8-big-rom-{}.mc8", std::process::id()));
+    std::fs::write(&path, &rom).unwrap();
+
+    let mut emu = headless_emulator(0);
+    emu.set_platform_lookup(Box::new(|_, _| Some(Platform::MegaChip)));
+    emu.handle_command(EmulatorCommand::LoadRom(path.display().to_string()));
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(emu.platform(), Platform::MegaChip);
+    assert_eq!(emu.rom_sha1(), sha1_smol::Sha1::from(&rom).digest().to_string());
+}
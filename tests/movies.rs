@@ -0,0 +1,92 @@
+use chip8_rs::emulator::font::Font;
+use chip8_rs::emulator::movie::{Movie, MovieError};
+use chip8_rs::emulator::platform::Platform;
+use chip8_rs::emulator::rng::FixedSequenceRng;
+use chip8_rs::emulator::Emulator;
+use chip8_rs::testing::headless_emulator;
+
+/// V0 := random, then wait for a key
+const ROM: [u8; 4] = [0xC0, 0xFF, 0xF1, 0x0A];
+
+fn emulator() -> Emulator {
+    let mut emu = headless_emulator(7);
+    emu.load_rom(ROM.to_vec()).unwrap();
+    emu.reset();
+    emu
+}
+
+fn emulator_movie() -> Movie {
+    let mut emu = emulator();
+    emu.start_recording().unwrap();
+    emu.run_frame(0);
+    emu.stop_recording().unwrap()
+}
+
+#[test]
+fn header_keeps_the_platform_and_font() {
+    let mut emu = emulator();
+    emu.set_platform(Platform::Hires).unwrap();
+    emu.set_font(Font::preset("schip").unwrap(), 0x100).unwrap();
+
+    emu.start_recording().unwrap();
+    emu.run_frame(0b10);
+    let movie = emu.stop_recording().unwrap();
+
+    let parsed = Movie::parse(&movie.serialize()).unwrap();
+    assert_eq!(parsed, movie);
+    assert_eq!(parsed.platform, Platform::Hires);
+    assert_eq!(parsed.font_address, 0x100);
+    assert_eq!(parsed.font, Font::preset("schip").unwrap());
+}
+
+#[test]
+fn playback_switches_to_the_recorded_platform_and_font() {
+    let mut emu = emulator();
+    emu.set_platform(Platform::Hires).unwrap();
+    emu.set_font(Font::preset("octo").unwrap(), 0x080).unwrap();
+    emu.start_recording().unwrap();
+    emu.run_frame(0);
+    let movie = emu.stop_recording().unwrap();
+
+    let mut other = emulator();
+    other.play_movie(movie).unwrap();
+
+    assert_eq!(other.platform(), Platform::Hires);
+    assert_eq!(other.font(), Font::preset("octo").unwrap());
+    assert_eq!(other.font_address(), 0x080);
+}
+
+#[test]
+fn movies_without_a_platform_are_rejected() {
+    let movie = emulator_movie();
+    let text: String = movie
+        .serialize()
+        .lines()
+        .filter(|line| !line.starts_with("platform"))
+        .map(|line| format!("{}\n", line))
+        .collect();
+
+    assert!(matches!(Movie::parse(&text), Err(MovieError::Parse(_))));
+}
+
+#[test]
+fn fixed_random_sequences_cant_be_recorded() {
+    let mut emu = emulator();
+    emu.set_rng(Box::new(FixedSequenceRng::new(vec![1, 2, 3])));
+
+    let err = emu.start_recording().unwrap_err();
+    assert!(matches!(err, MovieError::UnrecordableRng(ref rng) if rng == "fixed"), "{}", err);
+    assert!(emu.stop_recording().is_none());
+}
+
+#[test]
+fn every_quirk_is_required() {
+    let text = emulator_movie().serialize();
+    let quirks = text.lines().find(|line| line.starts_with("quirks ")).unwrap();
+
+    for pair in quirks.split_whitespace().skip(1) {
+        let name = pair.split_once('=').unwrap().0;
+        let err = Movie::parse(&text.replace(&format!(" {}", pair), "")).unwrap_err();
+        assert!(err.to_string().contains(&format!("missing quirk {}", name)), "{}", err);
+    }
+}
@@ -0,0 +1,80 @@
+use chip8_rs::config::Config;
+use chip8_rs::emulator::platform::Platform;
+use chip8_rs::testing::headless_emulator;
+
+#[test]
+fn hires_starts_at_0x2c0_with_a_64x64_screen() {
+    let mut emu = headless_emulator(0);
+    // the interpreter patch is left as zeroes, the program clears the screen and draws the
+    // 0 glyph at 0, 40: 0230, V2 := 40, I := font digit V0, sprite V1 V2 5
+    let mut rom = vec![0; 0xC0];
+    rom.extend([0x02, 0x30, 0x62, 0x28, 0xF0, 0x29, 0xD1, 0x25]);
+    emu.set_platform(Platform::Hires).unwrap();
+    emu.load_rom(rom).unwrap();
+    emu.reset();
+
+    assert_eq!(emu.cpu_state().pc, 0x2C0);
+    for _ in 0..4 {
+        emu.cycle();
+    }
+
+    let framebuffer = emu.framebuffer();
+    assert_eq!((framebuffer.width(), framebuffer.height()), (64, 64));
+    assert!(framebuffer.is_lit(0, 40));
+}
+
+#[test]
+fn chip8x_loads_at_0x300_and_colors_the_screen() {
+    let mut emu = headless_emulator(0);
+    // 02A0, V0 := 0x10, V1 := 0, V2 := 2 (blue), color zones V0 V1 with V2, I := font digit V1,
+    // sprite V1 V1 5
+    emu.set_platform(Platform::Chip8X).unwrap();
+    emu.load_rom(vec![0x02, 0xA0, 0x60, 0x10, 0x61, 0x00, 0x62, 0x02, 0xB0, 0x20, 0xF1, 0x29, 0xD1, 0x15])
+        .unwrap();
+    emu.reset();
+
+    assert_eq!(emu.cpu_state().pc, 0x300);
+    for _ in 0..7 {
+        emu.cycle();
+    }
+
+    let framebuffer = emu.framebuffer();
+    // the background stepped from blue to black, the lit pixels of the 0 take the zone's blue
+    assert_eq!(framebuffer.color(5, 0), Some([0x00, 0x00, 0x00]));
+    assert_eq!(framebuffer.color(0, 0), Some([0x00, 0x00, 0xFF]));
+}
+
+#[test]
+fn chip8e_stores_and_loads_register_ranges() {
+    let mut emu = headless_emulator(0);
+    // V1 := 0xAB, V2 := 0xCD, I := 0x300, store V1 - V2, I := 0x300, load V3 - V4
+    emu.set_platform(Platform::Chip8E).unwrap();
+    emu.load_rom(vec![0x61, 0xAB, 0x62, 0xCD, 0xA3, 0x00, 0x51, 0x22, 0xA3, 0x00, 0x53, 0x43])
+        .unwrap();
+    emu.reset();
+
+    for _ in 0..6 {
+        emu.cycle();
+    }
+
+    let state = emu.cpu_state();
+    assert_eq!(state.v_reg[3..5], [0xAB, 0xCD]);
+    assert_eq!(state.idx, 0x302);
+}
+
+#[test]
+fn roms_must_fit_the_platform() {
+    let mut emu = headless_emulator(0);
+    emu.load_rom(vec![0; 0xD01]).unwrap();
+
+    assert!(emu.set_platform(Platform::Chip8X).is_err());
+    assert_eq!(emu.platform(), Platform::Chip8);
+}
+
+#[test]
+fn config_picks_the_platform() {
+    let config = Config::parse("platform = \"chip8x\"\n").unwrap();
+    assert_eq!(config.settings_for("").unwrap().platform, Platform::Chip8X);
+
+    assert!(Config::parse("platform = \"chip48\"\n").is_err());
+}
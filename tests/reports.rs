@@ -0,0 +1,24 @@
+use std::time::Duration;
+
+use chip8_rs::testing::{self, Framebuffer, TestResult};
+
+#[test]
+fn json_report_escapes_names_and_failures() {
+    let result = TestResult {
+        name: "\"quoted\" \\ rom".to_string(),
+        failure: Some("expected hash 1\nscreen:\t##".to_string()),
+        framebuffer: Framebuffer::new(64, 32),
+        hash: "abc".to_string(),
+        sound_frames: 3,
+        duration: Duration::from_millis(5),
+    };
+
+    let report: serde_json::Value = serde_json::from_str(&testing::json_report(&[result])).unwrap();
+    let entry = &report[0];
+
+    assert_eq!(entry["name"], "\"quoted\" \\ rom");
+    assert_eq!(entry["failure"], "expected hash 1\nscreen:\t##");
+    assert_eq!(entry["passed"], false);
+    assert_eq!(entry["duration_ms"], 5.0);
+    assert!(entry.get("framebuffer").is_none());
+}
@@ -0,0 +1,149 @@
+use std::io::{Cursor, Write};
+use std::path::PathBuf;
+
+use chip8_rs::config::Config;
+use chip8_rs::emulator::cartridge::read_cartridge;
+use chip8_rs::emulator::quirks::Quirks;
+use chip8_rs::emulator::rom::{read_rom, RomError};
+use chip8_rs::emulator::timing::TimingModel;
+use chip8_rs::testing::headless_emulator;
+
+/// A fresh directory under the target dir for files a test writes
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_zip(path: &PathBuf, files: &[(&str, &[u8])]) {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+    for (name, contents) in files {
+        zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap();
+}
+
+/// Encodes a payload the way Octo does: two bits in the low bits of each pixel
+fn cartridge_gif(json: &str) -> Vec<u8> {
+    let mut bytes = (json.len() as u32).to_be_bytes().to_vec();
+    bytes.extend_from_slice(json.as_bytes());
+
+    let mut pixels: Vec<u8> = bytes
+        .iter()
+        .flat_map(|byte| [byte >> 6, (byte >> 4) & 3, (byte >> 2) & 3, byte & 3])
+        .collect();
+    pixels.resize(128 * 64, 0);
+
+    let mut gif = Vec::new();
+    {
+        let palette = [0, 0, 0, 85, 85, 85, 170, 170, 170, 255, 255, 255];
+        let mut encoder = gif::Encoder::new(Cursor::new(&mut gif), 128, 64, &palette).unwrap();
+        encoder.write_frame(&gif::Frame::from_indexed_pixels(128, 64, pixels, None)).unwrap();
+    }
+    gif
+}
+
+#[test]
+fn zip_with_one_rom_loads_it() {
+    let dir = scratch_dir("zip_with_one_rom");
+    let path = dir.join("game.zip");
+    write_zip(&path, &[("readme.txt", b"hello"), ("game/GAME.CH8", &[0x12, 0x00])]);
+
+    assert_eq!(read_rom(path.to_str().unwrap()).unwrap(), vec![0x12, 0x00]);
+}
+
+#[test]
+fn zip_with_several_roms_asks_which() {
+    let dir = scratch_dir("zip_with_several_roms");
+    let path = dir.join("pack.zip");
+    write_zip(&path, &[("b.sc8", &[0x12, 0x02]), ("a.ch8", &[0x12, 0x00])]);
+    let path = path.to_str().unwrap();
+
+    let err = read_rom(path).unwrap_err();
+    assert!(matches!(&err, RomError::ChooseEntry { entries, .. } if *entries == ["a.ch8", "b.sc8"]));
+
+    let choice = &err.choices()[1];
+    assert_eq!(*choice, format!("{}#b.sc8", path));
+    assert_eq!(read_rom(choice).unwrap(), vec![0x12, 0x02]);
+}
+
+#[test]
+fn zip_without_roms_is_an_error() {
+    let dir = scratch_dir("zip_without_roms");
+    let path = dir.join("empty.zip");
+    write_zip(&path, &[("readme.txt", b"hello")]);
+
+    assert!(matches!(read_rom(path.to_str().unwrap()), Err(RomError::NoRomInArchive(_))));
+}
+
+#[test]
+fn cartridge_program_and_options_are_decoded() {
+    let gif = cartridge_gif(
+        r##"{"program": ": main\n  loop again", "options": {"tickrate": 500, "fillColor": "#FF0000", "shiftQuirks": true, "loadStoreQuirks": true}}"##,
+    );
+
+    let cartridge = read_cartridge(&gif).unwrap();
+    assert_eq!(cartridge.program, ": main\n  loop again");
+    assert_eq!(cartridge.options.tickrate, 500);
+    assert_eq!(cartridge.options.colors(), Some(([0x99, 0x66, 0x00], [0xFF, 0x00, 0x00])));
+    assert_eq!(
+        cartridge.options.quirks(),
+        Quirks {
+            shift_vx: true,
+            memory_increment: false,
+            ..Quirks::xo_chip()
+        }
+    );
+}
+
+#[test]
+fn cartridges_need_assembling() {
+    let dir = scratch_dir("cartridges_need_assembling");
+    let path = dir.join("game.gif");
+    std::fs::write(&path, cartridge_gif(r#"{"program": ": main"}"#)).unwrap();
+
+    assert!(matches!(read_rom(path.to_str().unwrap()), Err(RomError::OctoSource { .. })));
+}
+
+#[test]
+fn cartridge_on_its_own_is_rejected_with_the_way_out() {
+    let gif = std::fs::read("tests/roms/hello.gif").unwrap();
+    let cartridge = read_cartridge(&gif).unwrap();
+    assert!(cartridge.program.contains(": main"));
+    assert_eq!(cartridge.options.tickrate, 20);
+    assert_eq!(cartridge.options.quirks(), Quirks::xo_chip());
+
+    let err = read_rom("tests/roms/hello.gif").unwrap_err();
+    assert!(matches!(err, RomError::OctoSource { .. }));
+    let message = err.to_string();
+    assert!(message.contains("can't assemble"), "{}", message);
+    assert!(message.contains("tests/roms/hello.ch8"), "{}", message);
+}
+
+#[test]
+fn cartridge_settings_apply_to_the_exported_program() {
+    let dir = scratch_dir("cartridge_settings_apply");
+    std::fs::write(
+        dir.join("game.gif"),
+        cartridge_gif(r##"{"program": ": main", "options": {"tickrate": 200, "fillColor": "#00FF00", "logicQuirks": true}}"##),
+    )
+    .unwrap();
+    std::fs::write(dir.join("game.ch8"), [0x12, 0x00]).unwrap();
+
+    let mut emu = headless_emulator(0);
+    emu.load_rom_from_file(dir.join("game.gif").to_str().unwrap()).unwrap();
+
+    let info = emu.rom_file_info().unwrap();
+    assert_eq!(info.title, "game");
+    let settings = Config::default().resolve(emu.rom_sha1(), Some(info)).unwrap();
+    assert_eq!(settings.timing, TimingModel::InstructionsPerFrame(200));
+    assert_eq!(settings.foreground, [0x00, 0xFF, 0x00]);
+    assert_eq!(settings.background, [0x99, 0x66, 0x00]);
+    assert!(settings.quirks.vf_reset);
+
+    // a plain ROM has nothing to say
+    emu.load_rom_from_file(dir.join("game.ch8").to_str().unwrap()).unwrap();
+    assert!(emu.rom_file_info().is_none());
+}
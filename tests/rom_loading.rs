@@ -0,0 +1,46 @@
+use chip8_rs::emulator::rom::{RomError, RomWarning, MAX_ROM_SIZE};
+use chip8_rs::testing::headless_emulator;
+
+#[test]
+fn missing_file_is_not_found() {
+    let mut emu = headless_emulator(0);
+
+    match emu.load_rom_from_file("roms/does-not-exist.ch8") {
+        Err(RomError::NotFound(path)) => assert_eq!(path, "roms/does-not-exist.ch8"),
+        other => panic!("expected NotFound, got {:?}", other),
+    }
+}
+
+#[test]
+fn empty_rom_is_rejected() {
+    let mut emu = headless_emulator(0);
+
+    assert!(matches!(emu.load_rom(Vec::new()), Err(RomError::Empty)));
+}
+
+#[test]
+fn oversized_rom_is_rejected_and_keeps_the_loaded_one() {
+    let mut emu = headless_emulator(0);
+    emu.load_rom(vec![0x12, 0x00]).unwrap();
+    let sha1 = emu.rom_sha1().to_string();
+
+    match emu.load_rom(vec![0; MAX_ROM_SIZE + 1]) {
+        Err(RomError::TooLarge { size, max }) => assert_eq!((size, max), (MAX_ROM_SIZE + 1, MAX_ROM_SIZE)),
+        other => panic!("expected TooLarge, got {:?}", other),
+    }
+    assert_eq!(emu.rom_sha1(), sha1);
+}
+
+#[test]
+fn rom_filling_memory_loads() {
+    let mut emu = headless_emulator(0);
+
+    assert_eq!(emu.load_rom(vec![0; MAX_ROM_SIZE]).unwrap(), vec![]);
+}
+
+#[test]
+fn odd_length_loads_with_a_warning() {
+    let mut emu = headless_emulator(0);
+
+    assert_eq!(emu.load_rom(vec![0x12, 0x00, 0xFF]).unwrap(), vec![RomWarning::OddLength(3)]);
+}
@@ -0,0 +1,115 @@
+use chip8_rs::config::Config;
+use chip8_rs::emulator::quirks::Quirks;
+use chip8_rs::emulator::timing::TimingModel;
+use chip8_rs::romdb::RomDatabase;
+use chip8_rs::testing::headless_emulator;
+
+const ROM: &str = "0123456789abcdef0123456789abcdef01234567";
+
+const DATABASE: &str = r##"[
+    {
+        "title": "Some game",
+        "authors": ["Someone"],
+        "roms": {
+            "0123456789ABCDEF0123456789ABCDEF01234567": {
+                "platforms": ["superchip", "xochip"],
+                "quirkyPlatforms": { "superchip": { "wrap": true } },
+                "tickrate": 30,
+                "keys": { "left": 7, "right": 9, "up": 5, "bogus": 99 },
+                "colors": { "pixels": ["#102030", "#A0B0C0"] }
+            }
+        }
+    }
+]"##;
+
+#[test]
+fn bundled_database_knows_the_bundled_roms() {
+    let database = RomDatabase::bundled();
+
+    for entry in std::fs::read_dir("roms").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "ch8") {
+            continue;
+        }
+        let mut emu = headless_emulator(0);
+        emu.load_rom_from_file(path.to_str().unwrap()).unwrap();
+
+        assert!(database.lookup(emu.rom_sha1()).is_some(), "{} is missing", path.display());
+    }
+
+    let mut emu = headless_emulator(0);
+    emu.load_rom_from_file("roms/test_opcode.ch8").unwrap();
+    let info = database.lookup(emu.rom_sha1()).unwrap();
+    assert_eq!(info.authors, ["corax89"]);
+    assert_eq!(info.platform, "originalChip8");
+    assert_eq!(info.quirks, Quirks::cosmac_vip());
+}
+
+#[test]
+fn bundled_database_knows_the_classics() {
+    let database = RomDatabase::bundled();
+    let known = [
+        ("1ba58656810b67fd131eb9af3e3987863bf26c90", "IBM Logo", None),
+        ("193915dcde1365ae054c4eaa21a35baa27cd3356", "Breakout", Some("Carmelo Cortez")),
+        ("f13766c14aeb02ad8d4d103cb5eadd282d20cddc", "Brix", Some("Andreas Gustafsson")),
+        ("B9272AE1ACDAAA79AB649F6B48B72088CA2B1D74", "Maze", Some("David Winter")),
+        ("5f518084744bf3cb8733f6e5454dfd1634320563", "Tetris", Some("Fran Dachille")),
+    ];
+
+    for (sha1, title, author) in known {
+        let info = database.lookup(sha1).unwrap_or_else(|| panic!("{} is missing", title));
+        assert_eq!(info.title, title);
+        assert_eq!(info.authors.first().map(String::as_str), author);
+        assert_eq!(info.platform, "originalChip8");
+    }
+    assert_eq!(database.lookup("5f518084744bf3cb8733f6e5454dfd1634320564"), None);
+}
+
+/// The full database isn't in the repository, run this with
+/// `CHIP8_DATABASE=path/to/programs.json cargo test --test romdb -- --ignored`
+#[test]
+#[ignore = "needs the full chip-8-database, see the romdb module docs"]
+fn full_database_loads() {
+    let path = RomDatabase::user_path()
+        .filter(|path| path.exists())
+        .expect("CHIP8_DATABASE should name the full programs.json");
+    let database = RomDatabase::load(&path).unwrap();
+    assert!(database.len() > 100, "only {} ROMs in {}", database.len(), path.display());
+
+    // it replaces the bundled entries and keeps the rest
+    let mut merged = RomDatabase::bundled();
+    merged.merge(database);
+    assert!(merged.lookup("f1cfcffe1937ed6dd6eeed1a7f85dfc777bda700").is_some());
+}
+
+#[test]
+fn entries_give_platform_quirks_and_hints() {
+    let database = RomDatabase::parse(DATABASE).unwrap();
+    let info = database.lookup(ROM).unwrap();
+
+    assert_eq!(info.title, "Some game");
+    assert_eq!(info.platform, "superchip");
+    assert_eq!(info.quirks, Quirks { clipping: false, ..Quirks::schip() });
+    assert_eq!(info.tickrate, Some(30));
+    assert_eq!(
+        info.key_hints,
+        vec![("up".to_string(), 5), ("left".to_string(), 7), ("right".to_string(), 9)]
+    );
+    assert_eq!(info.colors, Some(([0x10, 0x20, 0x30], [0xA0, 0xB0, 0xC0])));
+}
+
+#[test]
+fn config_overrides_win_over_the_database() {
+    let database = RomDatabase::parse(DATABASE).unwrap();
+    let info = database.lookup(ROM);
+
+    let settings = Config::default().resolve(ROM, info).unwrap();
+    assert_eq!(settings.timing, TimingModel::InstructionsPerFrame(30));
+    assert_eq!(settings.foreground, [0xA0, 0xB0, 0xC0]);
+
+    let config = Config::parse(&format!("[roms.{}]\ntiming = \"cosmac-vip\"\nquirks = \"vip\"\n", ROM)).unwrap();
+    let settings = config.resolve(ROM, info).unwrap();
+    assert_eq!(settings.timing, TimingModel::CosmacVip);
+    assert_eq!(settings.quirks, Quirks::cosmac_vip());
+    assert_eq!(settings.background, [0x10, 0x20, 0x30]);
+}
@@ -0,0 +1,86 @@
+use chip8_rs::emulator::disasm;
+use chip8_rs::emulator::platform::Platform;
+use chip8_rs::emulator::quirks::Quirks;
+use chip8_rs::emulator::symbols::{SymbolError, Symbols};
+use chip8_rs::testing::headless_emulator;
+
+#[test]
+fn text_files_are_address_then_name() {
+    let symbols = Symbols::parse("# made by an assembler\n0x200 main\n$208 draw\n\n20A sprites\n; the end\n").unwrap();
+
+    assert_eq!(symbols.len(), 3);
+    assert_eq!(symbols.name(0x200), Some("main"));
+    assert_eq!(symbols.address("draw"), Some(0x208));
+    assert_eq!(symbols.address("sprites"), Some(0x20A));
+}
+
+#[test]
+fn text_errors_name_the_line() {
+    let err = Symbols::parse("200 main\nmain\n").unwrap_err();
+    assert!(matches!(err, SymbolError::Parse { line: 2, .. }), "{}", err);
+
+    let err = Symbols::parse("200 main\nzz9 loop\n").unwrap_err();
+    assert!(err.to_string().contains("line 2"), "{}", err);
+}
+
+#[test]
+fn octo_json_has_labels_and_breakpoints() {
+    let json = r#"{
+        "labels": { "main": 512, "draw": "0x208" },
+        "breakpoints": { "522": "after-draw" }
+    }"#;
+    let symbols = Symbols::parse(json).unwrap();
+
+    assert_eq!(symbols.address("main"), Some(0x200));
+    assert_eq!(symbols.address("draw"), Some(0x208));
+    assert_eq!(symbols.name(0x20A), Some("after-draw"));
+    assert_eq!(symbols.breakpoints(), [0x20A]);
+
+    let flat = Symbols::parse(r#"{ "main": 512 }"#).unwrap();
+    assert_eq!(flat.name(0x200), Some("main"));
+}
+
+#[test]
+fn addresses_are_described_relative_to_the_symbol_before_them() {
+    let symbols = Symbols::parse("200 main\n300 sprites\n").unwrap();
+
+    assert_eq!(symbols.describe(0x200), "main");
+    assert_eq!(symbols.describe(0x204), "main+0x4");
+    assert_eq!(symbols.describe(0x308), "sprites+0x8");
+    assert_eq!(symbols.describe(0x050), "050");
+    assert_eq!(symbols.describe(0x800), "800");
+}
+
+#[test]
+fn typed_addresses_can_be_names() {
+    let symbols = Symbols::parse("208 add\n").unwrap();
+
+    // a name that is also hex means the name
+    assert_eq!(symbols.resolve("add"), Ok(0x208));
+    assert_eq!(symbols.resolve(" 0x300 "), Ok(0x300));
+    assert!(symbols.resolve("nowhere").is_err());
+}
+
+#[test]
+fn disassembly_uses_symbol_names() {
+    let symbols = Symbols::parse("208 double\n").unwrap();
+    let lines = disasm::disassemble(&[0x22, 0x08, 0xA2, 0x0A], 0x200, Platform::Chip8, Quirks::default(), &|address| {
+        symbols.name(address).map(String::from)
+    });
+
+    assert_eq!(lines[0].text, "CALL double");
+    assert_eq!(lines[1].text, "LD I, 0x20A");
+}
+
+#[test]
+fn symbol_breakpoints_are_set_on_load() {
+    let mut emu = headless_emulator(0);
+    emu.load_rom(vec![0x60, 0x01, 0x70, 0x01, 0x12, 0x02]).unwrap();
+    emu.reset();
+    emu.set_symbols(Symbols::parse(r#"{ "labels": {}, "breakpoints": { "514": "loop" } }"#).unwrap());
+
+    emu.run_frame(0);
+    assert!(emu.debugger().paused);
+    assert_eq!(emu.cpu_state().pc, 0x202);
+    assert_eq!(emu.symbols().describe(emu.cpu_state().pc), "loop");
+}
@@ -0,0 +1,18 @@
+//! corax89's opcode test, every tile on the screen should read OK
+
+use chip8_rs::testing::{self, Expected, RunLength, TestCase};
+
+#[test]
+fn test_opcode_rom_passes() {
+    let rom = std::fs::read("roms/test_opcode.ch8").unwrap();
+    let expected = testing::parse_pbm(include_str!("snapshots/test_opcode.pbm")).unwrap();
+
+    let result = testing::run_test(&TestCase::new(
+        "test_opcode",
+        rom,
+        RunLength::Frames(120),
+        Expected::Image(Box::new(expected)),
+    ));
+
+    assert!(result.passed(), "{}", result.failure.unwrap());
+}
@@ -0,0 +1,156 @@
+//! Timendus' CHIP-8 test suite, https://github.com/Timendus/chip8-test-suite
+//!
+//! Only the IBM logo, which is bundled in `roms/`, runs by default. The suite's own ROMs
+//! aren't part of this repository yet, so their tests are ignored. Put the `.ch8` files from
+//! the suite's `bin` directory in `roms/chip8-test-suite`, or point `CHIP8_TEST_SUITE` at
+//! them, and run `cargo test --test timendus -- --ignored`. A missing ROM or snapshot fails
+//! the test. Every ROM runs under each quirks preset and the final screen is compared to
+//! `tests/snapshots/timendus/<rom>-<preset>.pbm`. Record new snapshots with `CHIP8_BLESS=1`
+//! and check them by eye before committing.
+
+use std::path::{Path, PathBuf};
+
+use chip8_rs::emulator::quirks::Quirks;
+use chip8_rs::emulator::timing::TimingModel;
+use chip8_rs::testing::{self, Expected, InputStep, RunLength, TestCase, TestResult};
+
+const PRESETS: [&str; 3] = ["vip", "schip", "xo-chip"];
+
+fn suite_dir() -> PathBuf {
+    std::env::var("CHIP8_TEST_SUITE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("roms/chip8-test-suite"))
+}
+
+/// Runs the ROM under every preset and checks the snapshots, `input` picks the menu entries
+/// the ROM needs for a preset
+fn run_suite_rom(
+    rom_name: &str,
+    frames: u32,
+    input: impl Fn(&str) -> Vec<InputStep>,
+) -> Vec<TestResult> {
+    run_rom(&suite_dir().join(rom_name), frames, input)
+}
+
+fn run_rom(path: &Path, frames: u32, input: impl Fn(&str) -> Vec<InputStep>) -> Vec<TestResult> {
+    let rom_name = path.file_name().unwrap().to_string_lossy();
+    let rom = std::fs::read(path).unwrap_or_else(|err| {
+        panic!(
+            "Couldn't read {}: {}, get it from https://github.com/Timendus/chip8-test-suite",
+            path.display(),
+            err
+        )
+    });
+
+    let mut failures = Vec::new();
+    let mut results = Vec::new();
+
+    for preset in PRESETS {
+        let name = format!("{}-{}", rom_name.trim_end_matches(".ch8"), preset);
+
+        let mut case = TestCase::new(&name, rom.clone(), RunLength::Frames(frames), Expected::Nothing);
+        case.quirks = Quirks::preset(preset).unwrap();
+        // the VIP waits for the display interrupt on every sprite, which the quirks test checks
+        case.timing = match preset {
+            "vip" => TimingModel::CosmacVip,
+            _ => TimingModel::InstructionsPerFrame(1000),
+        };
+        case.input = input(preset);
+
+        let result = testing::run_test(&case);
+
+        let snapshot = PathBuf::from("tests/snapshots/timendus").join(format!("{}.pbm", name));
+        if let Err(err) = testing::check_snapshot(&snapshot, &result.framebuffer) {
+            failures.push(format!("{}: {}", name, err));
+        }
+
+        results.push(result);
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+    results
+}
+
+fn taps(keys: &[u8]) -> Vec<InputStep> {
+    keys.iter().flat_map(|key| InputStep::tap(*key)).collect()
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite ROMs and snapshots, see the module docs"]
+fn chip8_logo() {
+    run_suite_rom("1-chip8-logo.ch8", 60, |_| Vec::new());
+}
+
+#[test]
+fn ibm_logo() {
+    run_rom(Path::new("roms/ibm.ch8"), 60, |_| Vec::new());
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite ROMs and snapshots, see the module docs"]
+fn corax_plus() {
+    run_suite_rom("3-corax+.ch8", 120, |_| Vec::new());
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite ROMs and snapshots, see the module docs"]
+fn flags() {
+    run_suite_rom("4-flags.ch8", 120, |_| Vec::new());
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite ROMs and snapshots, see the module docs"]
+fn quirks() {
+    // the menu asks for the platform, SUPER-CHIP then asks for modern or legacy
+    run_suite_rom("5-quirks.ch8", 600, |preset| match preset {
+        "vip" => taps(&[1]),
+        "schip" => taps(&[2, 1]),
+        _ => taps(&[3]),
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite ROMs and snapshots, see the module docs"]
+fn keypad_down() {
+    // EX9E, every key lights up on screen while it is held
+    run_suite_rom("6-keypad.ch8", 30, |_| {
+        let mut input = taps(&[1]);
+        input.extend(taps(&(0..16).collect::<Vec<u8>>()));
+        input
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite ROMs and snapshots, see the module docs"]
+fn keypad_up() {
+    // EXA1, the opposite of EX9E
+    run_suite_rom("6-keypad.ch8", 30, |_| {
+        let mut input = taps(&[2]);
+        input.extend(taps(&(0..16).collect::<Vec<u8>>()));
+        input
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite ROMs and snapshots, see the module docs"]
+fn keypad_getkey() {
+    // FX0A has to wait for the key to be released, like the VIP does
+    run_suite_rom("6-keypad.ch8", 30, |_| {
+        let mut input = taps(&[3]);
+        input.extend(taps(&[5]));
+        input
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus test suite ROMs and snapshots, see the module docs"]
+fn beep() {
+    // the ROM beeps for as long as B is held
+    let results = run_suite_rom("7-beep.ch8", 10, |_| {
+        vec![InputStep::Wait(30), InputStep::Hold { key: 0xB, frames: 30 }]
+    });
+
+    for result in results {
+        assert!(result.sound_frames > 0, "{} never started the sound timer", result.name);
+    }
+}